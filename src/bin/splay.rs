@@ -0,0 +1,737 @@
+//! `splay`, a small interactive playlist manager built on the crate's public API.
+//!
+//! The client is configured from the environment (see `Configs::from_env`).
+//! Run it with `cargo run --features cli --bin splay`, then type `help` for the commands.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
+use spotify_client_rs::model::{Id, Playlist, PlaylistId, Track, TrackId};
+use spotify_client_rs::require::*;
+
+const HELP: &str = "\
+commands:
+  playlists                       list the current user's playlists
+  open <index|id|uri>             open a playlist
+  tracks                          list the opened playlist's tracks
+  search <query>                  search tracks
+  add <result index>              add a searched track to the opened playlist
+  remove <track index>            remove a track from the opened playlist
+  dedup [--dry-run]               remove the duplicated tracks of the opened playlist
+  export <path> [json|jsonl|csv]  export the opened playlist to a file
+  help                            show this message
+  quit                            exit";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Playlists,
+    Open(String),
+    Tracks,
+    Search(String),
+    Add(usize),
+    Remove(usize),
+    Dedup { dry_run: bool },
+    Export { path: PathBuf, format: ExportFormat },
+    Help,
+    Quit,
+}
+
+#[derive(Default)]
+/// The state of an interactive session
+struct State {
+    playlists: Vec<Playlist>,
+    opened: Option<Opened>,
+    search_results: Vec<Track>,
+}
+
+/// An opened playlist, whose listed tracks are the playlist's available tracks
+struct Opened {
+    playlist: Playlist,
+    tracks: Vec<Track>,
+    /// the positions of the listed tracks in the playlist
+    positions: Vec<usize>,
+}
+
+impl State {
+    fn opened(&self) -> Result<&Opened> {
+        self.opened
+            .as_ref()
+            .context("no opened playlist, use `open` first")
+    }
+}
+
+impl Opened {
+    /// lists the available tracks of a playlist's items ordered by their positions
+    fn new(playlist: Playlist, items: Vec<Option<Track>>) -> Self {
+        let (positions, tracks) = items
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, item)| item.map(|track| (i, track)))
+            .unzip();
+        Self {
+            playlist,
+            tracks,
+            positions,
+        }
+    }
+
+    /// gets a listed track along with its position in the playlist
+    fn track(&self, index: usize) -> Result<(&Track, usize)> {
+        self.tracks
+            .get(index)
+            .map(|track| (track, self.positions[index]))
+            .with_context(|| format!("no track at index {index}"))
+    }
+}
+
+/// parses a command line, returning `None` for a blank line
+fn parse_command(line: &str) -> Result<Option<Command>> {
+    let line = line.trim();
+    let (name, arg) = match line.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (line, ""),
+    };
+    let index = |arg: &str| {
+        arg.parse::<usize>()
+            .with_context(|| format!("invalid index `{arg}`"))
+    };
+
+    let command = match name {
+        "" => return Ok(None),
+        "playlists" => Command::Playlists,
+        "open" if !arg.is_empty() => Command::Open(arg.to_string()),
+        "tracks" => Command::Tracks,
+        "search" if !arg.is_empty() => Command::Search(arg.to_string()),
+        "add" => Command::Add(index(arg)?),
+        "remove" => Command::Remove(index(arg)?),
+        "dedup" => match arg {
+            "" => Command::Dedup { dry_run: false },
+            "--dry-run" => Command::Dedup { dry_run: true },
+            _ => anyhow::bail!("unexpected argument `{arg}`"),
+        },
+        "export" if !arg.is_empty() => {
+            let (path, format) = match arg.rsplit_once(char::is_whitespace) {
+                Some((path, "json")) => (path.trim(), ExportFormat::Json),
+                Some((path, "jsonl")) => (path.trim(), ExportFormat::JsonLines),
+                Some((path, "csv")) => (path.trim(), ExportFormat::Csv),
+                _ => (arg, ExportFormat::Json),
+            };
+            Command::Export {
+                path: path.into(),
+                format,
+            }
+        }
+        "help" => Command::Help,
+        "quit" | "exit" => Command::Quit,
+        "open" | "search" | "export" => anyhow::bail!("missing argument, see `help`"),
+        _ => anyhow::bail!("unknown command `{name}`, see `help`"),
+    };
+    Ok(Some(command))
+}
+
+/// resolves a playlist argument, either an index in the listed playlists or a playlist's id/URI
+fn resolve_playlist(state: &State, arg: &str) -> Result<PlaylistId<'static>> {
+    if let Ok(index) = arg.parse::<usize>() {
+        return state
+            .playlists
+            .get(index)
+            .map(|p| p.id.clone())
+            .with_context(|| format!("no playlist at index {index}, use `playlists` first"));
+    }
+    Ok(PlaylistId::from_id_or_uri(arg)?.into_static())
+}
+
+/// gets the ids of the tracks occurring more than once, in their first occurrences' order
+fn duplicated_tracks(tracks: &[Track]) -> Vec<(TrackId<'static>, usize)> {
+    let mut counts = HashMap::new();
+    let mut order = vec![];
+    for track in tracks {
+        let count = counts.entry(track.id.clone()).or_insert(0);
+        if *count == 0 {
+            order.push(track.id.clone());
+        }
+        *count += 1;
+    }
+    order
+        .into_iter()
+        .map(|id| {
+            let count = counts[&id];
+            (id, count)
+        })
+        .filter(|(_, count)| *count > 1)
+        .collect()
+}
+
+/// exports a playlist and its tracks
+fn export_playlist<W: Write>(
+    writer: W,
+    format: ExportFormat,
+    playlist: &Playlist,
+    tracks: &[Track],
+) -> Result<W> {
+    let mut writer = ExportWriter::with_header(writer, format, "playlist", playlist, "tracks")?;
+    for track in tracks {
+        writer.write_item(track)?;
+    }
+    writer.finish()
+}
+
+/// writes a numbered list of items
+fn write_list<T: std::fmt::Display>(out: &mut impl Write, items: &[T]) -> Result<()> {
+    for (i, item) in items.iter().enumerate() {
+        writeln!(out, "{i:>4}  {item}")?;
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+/// The Spotify API calls made by the commands, implemented by `Client`
+trait SpotifyApi {
+    async fn current_user_playlists(&self) -> Result<Vec<Playlist>>;
+
+    async fn playlist_tracks_by_position(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<(Playlist, Vec<Option<Track>>)>;
+
+    async fn search_tracks(&self, query: &str) -> Result<Vec<Track>>;
+
+    async fn add_track_to_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_id: TrackId<'_>,
+        options: AddTrackOptions,
+    ) -> Result<bool>;
+
+    async fn remove_playlist_items_at(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        positions: Vec<usize>,
+        snapshot_id: &str,
+    ) -> Result<String>;
+
+    async fn dedup_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        key: DedupKey,
+    ) -> Result<DedupReport>;
+}
+
+#[async_trait::async_trait]
+impl SpotifyApi for Client {
+    async fn current_user_playlists(&self) -> Result<Vec<Playlist>> {
+        Client::current_user_playlists(self).await
+    }
+
+    async fn playlist_tracks_by_position(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<(Playlist, Vec<Option<Track>>)> {
+        Client::playlist_tracks_by_position(self, playlist_id).await
+    }
+
+    async fn search_tracks(&self, query: &str) -> Result<Vec<Track>> {
+        Ok(self.search(query).await?.tracks)
+    }
+
+    async fn add_track_to_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_id: TrackId<'_>,
+        options: AddTrackOptions,
+    ) -> Result<bool> {
+        Client::add_track_to_playlist(self, playlist_id, track_id, options).await
+    }
+
+    async fn remove_playlist_items_at(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        positions: Vec<usize>,
+        snapshot_id: &str,
+    ) -> Result<String> {
+        Client::remove_playlist_items_at(self, playlist_id, positions, snapshot_id).await
+    }
+
+    async fn dedup_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        key: DedupKey,
+    ) -> Result<DedupReport> {
+        Client::dedup_playlist(self, playlist_id, key).await
+    }
+}
+
+/// (re-)opens a playlist, fetching its tracks
+async fn open_playlist(api: &impl SpotifyApi, state: &mut State, id: PlaylistId<'_>) -> Result<()> {
+    let (playlist, items) = api.playlist_tracks_by_position(id).await?;
+    state.opened = Some(Opened::new(playlist, items));
+    Ok(())
+}
+
+/// runs a command, returning `false` if the session should end
+async fn run_command(
+    api: &impl SpotifyApi,
+    state: &mut State,
+    command: Command,
+    out: &mut impl Write,
+) -> Result<bool> {
+    match command {
+        Command::Playlists => {
+            state.playlists = api.current_user_playlists().await?;
+            write_list(out, &state.playlists)?;
+        }
+        Command::Open(arg) => {
+            let id = resolve_playlist(state, &arg)?;
+            open_playlist(api, state, id).await?;
+            let opened = state.opened()?;
+            writeln!(
+                out,
+                "opened {} ({} tracks)",
+                opened.playlist,
+                opened.tracks.len()
+            )?;
+        }
+        Command::Tracks => write_list(out, &state.opened()?.tracks)?,
+        Command::Search(query) => {
+            state.search_results = api.search_tracks(&query).await?;
+            write_list(out, &state.search_results)?;
+        }
+        Command::Add(index) => {
+            let track = state
+                .search_results
+                .get(index)
+                .with_context(|| format!("no search result at index {index}"))?;
+            let id = state.opened()?.playlist.id.clone();
+            let options = AddTrackOptions {
+                dedup: true,
+                position: None,
+            };
+            if api
+                .add_track_to_playlist(id.as_ref(), track.id.as_ref(), options)
+                .await?
+            {
+                writeln!(out, "added {track}")?;
+            } else {
+                writeln!(out, "{track} is already in the playlist")?;
+            }
+            open_playlist(api, state, id).await?;
+        }
+        Command::Remove(index) => {
+            let opened = state.opened()?;
+            let (track, position) = opened.track(index)?;
+            // only the listed occurrence is removed, provided the playlist wasn't modified since
+            let id = opened.playlist.id.clone();
+            api.remove_playlist_items_at(id.as_ref(), vec![position], &opened.playlist.snapshot_id)
+                .await?;
+            writeln!(out, "removed {track}")?;
+            open_playlist(api, state, id).await?;
+        }
+        Command::Dedup { dry_run } => {
+            let opened = state.opened()?;
+            let duplicates = duplicated_tracks(&opened.tracks);
+            for (id, count) in &duplicates {
+                writeln!(out, "{} occurs {count} times", id.uri())?;
+            }
+            if duplicates.is_empty() {
+                writeln!(out, "no duplicated tracks")?;
+            } else if dry_run {
+                writeln!(out, "dry run, the playlist was not modified")?;
+            } else {
+                let id = opened.playlist.id.clone();
+                let report = api.dedup_playlist(id.as_ref(), DedupKey::TrackId).await?;
+                writeln!(
+                    out,
+                    "removed {} duplicates, the first occurrences were kept",
+                    report.removed.len()
+                )?;
+                open_playlist(api, state, id).await?;
+            }
+        }
+        Command::Export { path, format } => {
+            let opened = state.opened()?;
+            let file = std::fs::File::create(&path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            export_playlist(
+                std::io::BufWriter::new(file),
+                format,
+                &opened.playlist,
+                &opened.tracks,
+            )?;
+            writeln!(
+                out,
+                "exported {} tracks to {}",
+                opened.tracks.len(),
+                path.display()
+            )?;
+        }
+        Command::Help => writeln!(out, "{HELP}")?,
+        Command::Quit => return Ok(false),
+    }
+    Ok(true)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let configs = Configs::from_env()?;
+    let client = ClientHandler::new().client_new(&configs).await?;
+
+    let mut state = State::default();
+    let mut out = std::io::stdout();
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        write!(out, "splay> ")?;
+        out.flush()?;
+        let Some(line) = lines.next() else {
+            break;
+        };
+
+        let result = match parse_command(&line?) {
+            Ok(Some(command)) => run_command(&client, &mut state, command, &mut out).await,
+            Ok(None) => Ok(true),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => writeln!(out, "error: {err:#}")?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use spotify_client_rs::model::{Artist, ArtistId};
+
+    fn track(id: &str) -> Track {
+        Track {
+            id: TrackId::from_id(id.to_string()).unwrap(),
+            name: id.to_string(),
+            artists: vec![Artist {
+                id: ArtistId::from_id("artist").unwrap(),
+                name: "artist".to_string(),
+                followers: None,
+                popularity: None,
+            }],
+            album: None,
+            duration: std::time::Duration::from_secs(200),
+            explicit: false,
+            added_at: 0,
+            isrc: None,
+            popularity: None,
+            is_playable: None,
+            available_markets: None,
+            relinked_id: None,
+        }
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse_command("  ").unwrap(), None);
+        assert_eq!(
+            parse_command("playlists").unwrap(),
+            Some(Command::Playlists)
+        );
+        assert_eq!(
+            parse_command("search  never gonna ").unwrap(),
+            Some(Command::Search("never gonna".to_string()))
+        );
+        assert_eq!(parse_command("add 3").unwrap(), Some(Command::Add(3)));
+        assert_eq!(
+            parse_command("dedup --dry-run").unwrap(),
+            Some(Command::Dedup { dry_run: true })
+        );
+        assert_eq!(
+            parse_command("export my list.json jsonl").unwrap(),
+            Some(Command::Export {
+                path: "my list.json".into(),
+                format: ExportFormat::JsonLines
+            })
+        );
+        assert_eq!(
+            parse_command("export out.json").unwrap(),
+            Some(Command::Export {
+                path: "out.json".into(),
+                format: ExportFormat::Json
+            })
+        );
+
+        assert!(parse_command("open").is_err());
+        assert!(parse_command("remove first").is_err());
+        assert!(parse_command("dedup --force").is_err());
+        assert!(parse_command("play").is_err());
+    }
+
+    #[test]
+    fn resolve_playlist_argument() {
+        let id = "37i9dQZF1DXcBWIGoYBM5M";
+        assert_eq!(
+            resolve_playlist(&State::default(), &format!("spotify:playlist:{id}")).unwrap(),
+            PlaylistId::from_id(id).unwrap()
+        );
+        assert_eq!(
+            resolve_playlist(&State::default(), id).unwrap(),
+            PlaylistId::from_id(id).unwrap()
+        );
+        assert!(resolve_playlist(&State::default(), "0").is_err());
+    }
+
+    #[test]
+    fn find_duplicated_tracks() {
+        let tracks = ["b", "a", "b", "c", "a", "b"].map(track);
+        assert_eq!(
+            duplicated_tracks(&tracks),
+            vec![
+                (TrackId::from_id("b").unwrap(), 3),
+                (TrackId::from_id("a").unwrap(), 2)
+            ]
+        );
+        assert!(duplicated_tracks(&tracks[..2]).is_empty());
+    }
+
+    fn playlist() -> Playlist {
+        Playlist {
+            id: PlaylistId::from_id("playlist").unwrap(),
+            collaborative: false,
+            name: "playlist".to_string(),
+            owner: (
+                "owner".to_string(),
+                spotify_client_rs::model::UserId::from_id("owner").unwrap(),
+            ),
+            desc: String::new(),
+            snapshot_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn locate_opened_tracks() {
+        let items = vec![Some(track("a")), None, Some(track("b")), Some(track("a"))];
+        let opened = Opened::new(playlist(), items);
+        assert_eq!(opened.tracks.len(), 3);
+
+        // a track is removed by its position, not by its index in the list
+        let (track, position) = opened.track(2).unwrap();
+        assert_eq!((track.name.as_str(), position), ("a", 3));
+        assert_eq!(opened.track(1).unwrap().1, 2);
+        assert!(opened.track(3).is_err());
+    }
+
+    #[test]
+    fn export_playlist_tracks() {
+        let output =
+            export_playlist(vec![], ExportFormat::JsonLines, &playlist(), &[track("a")]).unwrap();
+        let lines = String::from_utf8(output).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+        assert!(lines.lines().nth(1).unwrap().contains("\"name\":\"a\""));
+    }
+
+    /// A single in-memory playlist, recording the writes made to it
+    struct FakeSpotifyApi {
+        items: Mutex<Vec<Option<Track>>>,
+        search_results: Vec<Track>,
+        writes: Mutex<Vec<String>>,
+    }
+
+    impl FakeSpotifyApi {
+        fn new(items: Vec<Option<Track>>, search_results: Vec<Track>) -> Self {
+            Self {
+                items: Mutex::new(items),
+                search_results,
+                writes: Mutex::new(vec![]),
+            }
+        }
+
+        /// the playlist's snapshot id changes on every write
+        fn snapshot_id(&self) -> String {
+            format!("snapshot-{}", self.writes.lock().len())
+        }
+
+        fn playlist(&self) -> Playlist {
+            Playlist {
+                snapshot_id: self.snapshot_id(),
+                ..playlist()
+            }
+        }
+
+        fn write(&self, write: String) -> String {
+            self.writes.lock().push(write);
+            self.snapshot_id()
+        }
+
+        /// the names of the playlist's items, `-` for an unavailable item
+        fn names(&self) -> Vec<String> {
+            self.items
+                .lock()
+                .iter()
+                .map(|item| item.as_ref().map_or("-".to_string(), |t| t.name.clone()))
+                .collect()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl SpotifyApi for FakeSpotifyApi {
+        async fn current_user_playlists(&self) -> Result<Vec<Playlist>> {
+            Ok(vec![self.playlist()])
+        }
+
+        async fn playlist_tracks_by_position(
+            &self,
+            _playlist_id: PlaylistId<'_>,
+        ) -> Result<(Playlist, Vec<Option<Track>>)> {
+            Ok((self.playlist(), self.items.lock().clone()))
+        }
+
+        async fn search_tracks(&self, _query: &str) -> Result<Vec<Track>> {
+            Ok(self.search_results.clone())
+        }
+
+        async fn add_track_to_playlist(
+            &self,
+            _playlist_id: PlaylistId<'_>,
+            track_id: TrackId<'_>,
+            options: AddTrackOptions,
+        ) -> Result<bool> {
+            let mut items = self.items.lock();
+            if options.dedup && items.iter().flatten().any(|t| t.id.id() == track_id.id()) {
+                return Ok(false);
+            }
+            let track = self
+                .search_results
+                .iter()
+                .find(|t| t.id.id() == track_id.id())
+                .context("unknown track")?;
+            items.push(Some(track.clone()));
+            drop(items);
+            self.write(format!("add {}", track.name));
+            Ok(true)
+        }
+
+        async fn remove_playlist_items_at(
+            &self,
+            _playlist_id: PlaylistId<'_>,
+            mut positions: Vec<usize>,
+            snapshot_id: &str,
+        ) -> Result<String> {
+            anyhow::ensure!(snapshot_id == self.snapshot_id(), "outdated snapshot");
+            positions.sort_unstable();
+            let mut items = self.items.lock();
+            for position in positions.iter().rev() {
+                items.remove(*position);
+            }
+            drop(items);
+            Ok(self.write(format!("remove {positions:?}")))
+        }
+
+        async fn dedup_playlist(
+            &self,
+            _playlist_id: PlaylistId<'_>,
+            _key: DedupKey,
+        ) -> Result<DedupReport> {
+            let mut seen = std::collections::HashSet::new();
+            let mut removed = vec![];
+            let mut items = self.items.lock();
+            let mut position = 0;
+            items.retain(|item| {
+                position += 1;
+                match item {
+                    Some(track) if !seen.insert(track.id.clone()) => {
+                        removed.push((track.clone(), position - 1));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+            drop(items);
+            Ok(DedupReport {
+                removed,
+                snapshot_id: self.write("dedup".to_string()),
+            })
+        }
+    }
+
+    /// runs a command line, returning its output
+    async fn run(api: &FakeSpotifyApi, state: &mut State, line: &str) -> String {
+        let command = parse_command(line).unwrap().unwrap();
+        let mut out = vec![];
+        assert!(run_command(api, state, command, &mut out).await.unwrap());
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_tracks() {
+        let items = vec![Some(track("a")), None, Some(track("b"))];
+        let api = FakeSpotifyApi::new(items, vec![track("c"), track("a")]);
+        let mut state = State::default();
+        assert!(
+            run_command(&api, &mut state, Command::Tracks, &mut std::io::sink())
+                .await
+                .is_err()
+        );
+
+        run(&api, &mut state, "playlists").await;
+        assert!(run(&api, &mut state, "open 0").await.contains("(2 tracks)"));
+        run(&api, &mut state, "search anything").await;
+        assert!(run(&api, &mut state, "add 0").await.starts_with("added"));
+        assert!(run(&api, &mut state, "add 1")
+            .await
+            .contains("already in the playlist"));
+        assert_eq!(api.names(), ["a", "-", "b", "c"]);
+
+        // the listed track `b` is removed at its position, with the snapshot id after the addition
+        assert!(run(&api, &mut state, "remove 1")
+            .await
+            .starts_with("removed"));
+        assert_eq!(api.names(), ["a", "-", "c"]);
+        assert_eq!(*api.writes.lock(), ["add c", "remove [2]"]);
+        assert_eq!(state.opened().unwrap().tracks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn dedup_opened_playlist() {
+        let items = ["a", "b", "a", "a"].map(|id| Some(track(id))).to_vec();
+        let api = FakeSpotifyApi::new(items, vec![]);
+        let mut state = State::default();
+        run(&api, &mut state, "open spotify:playlist:playlist").await;
+
+        let output = run(&api, &mut state, "dedup --dry-run").await;
+        assert!(output.contains("spotify:track:a occurs 3 times"));
+        assert!(output.contains("dry run"));
+        assert!(api.writes.lock().is_empty());
+        assert_eq!(state.opened().unwrap().tracks.len(), 4);
+
+        let output = run(&api, &mut state, "dedup").await;
+        assert!(output.contains("removed 2 duplicates"));
+        assert_eq!(api.names(), ["a", "b"]);
+        assert_eq!(state.opened().unwrap().tracks.len(), 2);
+
+        let output = run(&api, &mut state, "dedup").await;
+        assert!(output.contains("no duplicated tracks"));
+        assert_eq!(*api.writes.lock(), ["dedup"]);
+    }
+
+    #[tokio::test]
+    async fn export_opened_playlist() {
+        let api = FakeSpotifyApi::new(vec![Some(track("a")), None, Some(track("b"))], vec![]);
+        let path = std::env::temp_dir().join(format!("splay-export-{}.jsonl", std::process::id()));
+        let mut state = State::default();
+        run(&api, &mut state, "open spotify:playlist:playlist").await;
+
+        let output = run(
+            &api,
+            &mut state,
+            &format!("export {} jsonl", path.display()),
+        )
+        .await;
+        assert!(output.starts_with("exported 2 tracks"));
+        let exported = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(exported.lines().count(), 3);
+        assert!(exported.lines().last().unwrap().contains("\"name\":\"b\""));
+        assert!(api.writes.lock().is_empty());
+    }
+}
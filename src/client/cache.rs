@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use rspotify::http::Query;
+
+use crate::config::CacheConfig;
+use crate::constant::SPOTIFY_API_ENDPOINT;
+
+/// the endpoints modified by the client's writes (the playlists and the current user's library,
+/// follows and playback), whose responses are never cached so that a read following a write
+/// (e.g. the existence check before adding a track) sees the write
+const UNCACHED_ENDPOINTS: [&str; 2] = ["me", "playlists"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How the requests of an operation use the response cache, see `Client::with_cache_control`
+pub enum CacheControl {
+    #[default]
+    /// use the cached responses if not expired
+    Default,
+    /// bypass the cached responses, fetching (and caching) fresh responses
+    NoCache,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The counters of the response cache, see `Client::cache_stats`
+pub struct CacheStats {
+    /// the number of requests whose response was cached
+    pub hits: u64,
+    /// the number of requests whose response wasn't cached or expired
+    pub misses: u64,
+    /// the number of cached responses
+    pub entries: usize,
+}
+
+/// An in-memory cache of the responses of GET requests, keyed by the request's URL and query.
+///
+/// The cache is bounded by its number of entries, evicting the least recently used entries.
+pub(crate) struct ResponseCache {
+    default_ttl: Duration,
+    max_entries: usize,
+    /// the time-to-live of the endpoints by their path prefix, the longest prefix first
+    endpoint_ttls: Vec<(String, Duration)>,
+    entries: parking_lot::Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// a logical clock ordering the uses of the entries
+    clock: u64,
+}
+
+struct Entry {
+    text: String,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+impl ResponseCache {
+    /// creates a response cache from the configs, ignoring (with a warning)
+    /// the invalid endpoint time-to-live entries
+    pub fn new(config: &CacheConfig) -> Self {
+        let mut endpoint_ttls = config
+            .endpoint_ttls
+            .iter()
+            .filter_map(|entry| {
+                let ttl = entry.split_once('=').and_then(|(path, secs)| {
+                    let secs = secs.trim().parse::<u64>().ok()?;
+                    Some((
+                        path.trim().trim_matches('/').to_string(),
+                        Duration::from_secs(secs),
+                    ))
+                });
+                if ttl.is_none() {
+                    tracing::warn!("failed to parse the endpoint cache TTL `{entry}`");
+                }
+                ttl
+            })
+            .collect::<Vec<_>>();
+        endpoint_ttls.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        Self {
+            default_ttl: Duration::from_secs(config.default_ttl_secs),
+            max_entries: config.max_entries,
+            endpoint_ttls,
+            entries: parking_lot::Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// gets the cache key of a request, the query parameters being sorted
+    pub fn key(url: &str, payload: &Query<'_>) -> String {
+        let mut params = payload.iter().collect::<Vec<_>>();
+        params.sort();
+        let query = params
+            .into_iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{url}?{query}")
+    }
+
+    /// gets the time-to-live of the responses of a request to `url`,
+    /// zero for the `UNCACHED_ENDPOINTS` whatever the configured time-to-live
+    fn ttl(&self, url: &str) -> Duration {
+        let path = url
+            .strip_prefix(SPOTIFY_API_ENDPOINT)
+            .unwrap_or(url)
+            .trim_start_matches('/');
+        let matches = |prefix: &str| {
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+        };
+        if UNCACHED_ENDPOINTS.into_iter().any(&matches) {
+            return Duration::ZERO;
+        }
+        self.endpoint_ttls
+            .iter()
+            .find(|(prefix, _)| matches(prefix))
+            .map_or(self.default_ttl, |(_, ttl)| *ttl)
+    }
+
+    /// gets the cached (not expired) response of a key, counting a hit or a miss
+    pub fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+
+        let text = match entries.map.get_mut(key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                entry.last_used = clock;
+                Some(entry.text.clone())
+            }
+            Some(_) => {
+                entries.map.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if text.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        text
+    }
+
+    /// caches the response of a request to `url`, unless its endpoint isn't cached
+    pub fn insert(&self, key: String, url: &str, text: String) {
+        let ttl = self.ttl(url);
+        if ttl.is_zero() {
+            return;
+        }
+        self.insert_until(key, text, Instant::now() + ttl);
+    }
+
+    fn insert_until(&self, key: String, text: String, expires_at: Instant) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock();
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.max_entries {
+            let now = Instant::now();
+            entries.map.retain(|_, entry| entry.expires_at > now);
+        }
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.max_entries {
+            let lru = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                entries.map.remove(&lru);
+            }
+        }
+
+        entries.clock += 1;
+        let last_used = entries.clock;
+        entries.map.insert(
+            key,
+            Entry {
+                text,
+                expires_at,
+                last_used,
+            },
+        );
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().map.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().map.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize, endpoint_ttls: &[&str]) -> ResponseCache {
+        ResponseCache::new(&CacheConfig {
+            enabled: true,
+            default_ttl_secs: 60,
+            max_entries,
+            endpoint_ttls: endpoint_ttls.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn cache_keys_ignore_query_order() {
+        let a = Query::from([("market", "SE"), ("limit", "50")]);
+        let b = Query::from([("limit", "50"), ("market", "SE")]);
+        assert_eq!(ResponseCache::key("u", &a), ResponseCache::key("u", &b));
+        assert_eq!(ResponseCache::key("u", &a), "u?limit=50&market=SE");
+    }
+
+    #[test]
+    fn endpoint_ttls() {
+        let cache = cache(
+            10,
+            &["me=0", "artists=86400", "artists/x/albums=10", "invalid"],
+        );
+        let ttl = |path: &str| cache.ttl(&format!("{SPOTIFY_API_ENDPOINT}/{path}"));
+
+        assert_eq!(ttl("me/tracks"), Duration::ZERO);
+        assert_eq!(ttl("artists/y"), Duration::from_secs(86400));
+        assert_eq!(ttl("artists/x/albums"), Duration::from_secs(10));
+        assert_eq!(ttl("albums/z"), Duration::from_secs(60));
+        // a prefix only matches whole path segments
+        assert_eq!(ttl("meta"), Duration::from_secs(60));
+
+        cache.insert(
+            "me".to_string(),
+            &format!("{SPOTIFY_API_ENDPOINT}/me"),
+            "{}".into(),
+        );
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn never_cache_written_endpoints() {
+        let cache = cache(10, &["playlists=3600", "me/following=3600"]);
+        let ttl = |path: &str| cache.ttl(&format!("{SPOTIFY_API_ENDPOINT}/{path}"));
+        assert_eq!(ttl("playlists/p/tracks"), Duration::ZERO);
+        assert_eq!(ttl("me/following/contains"), Duration::ZERO);
+        assert_eq!(ttl("users/u/playlists"), Duration::from_secs(60));
+
+        // a track is only added if the playlist's items (read through the cache) don't have it
+        let items = std::cell::RefCell::new(vec![]);
+        let add = |track: &str| {
+            let url = format!("{SPOTIFY_API_ENDPOINT}/playlists/p/tracks");
+            let key = ResponseCache::key(&url, &Query::new());
+            let text = cache.get(&key).unwrap_or_else(|| {
+                let text = items.borrow().join(",");
+                cache.insert(key, &url, text.clone());
+                text
+            });
+            if text.split(',').any(|t| t == track) {
+                return false;
+            }
+            items.borrow_mut().push(track.to_string());
+            true
+        };
+        assert!(add("a"));
+        // the second addition sees the first one
+        assert!(!add("a"));
+        assert_eq!(*items.borrow(), ["a"]);
+    }
+
+    #[test]
+    fn evict_expired_and_least_recently_used() {
+        let cache = cache(2, &[]);
+        let later = Instant::now() + Duration::from_secs(60);
+
+        cache.insert_until("a".into(), "1".into(), later);
+        cache.insert_until("b".into(), "2".into(), later);
+        assert_eq!(cache.get("a").as_deref(), Some("1"));
+        // `b` is the least recently used entry
+        cache.insert_until("c".into(), "3".into(), later);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c").as_deref(), Some("3"));
+
+        cache.insert_until("d".into(), "4".into(), Instant::now());
+        assert_eq!(cache.get("d"), None);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 1
+            }
+        );
+
+        cache.clear();
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
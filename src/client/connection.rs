@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::watch;
+
+use crate::error::ClientError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The state of the client's connection to Spotify
+pub enum ConnectionState {
+    Connected,
+    /// A new session is being created to replace an invalid one
+    Reconnecting,
+    /// The last attempt to create a new session failed
+    Disconnected,
+}
+
+/// waits (up to `deadline`) for the connection to stop reconnecting.
+///
+/// Returns immediately if the connection is not reconnecting.
+/// The function is cancel-safe: dropping it only drops the watch receiver.
+pub(crate) async fn wait_until_connected(
+    state: &mut watch::Receiver<ConnectionState>,
+    deadline: Duration,
+) -> Result<()> {
+    if *state.borrow_and_update() != ConnectionState::Reconnecting {
+        return Ok(());
+    }
+
+    tracing::debug!("Waiting for the session to reconnect...");
+    match tokio::time::timeout(
+        deadline,
+        state.wait_for(|s| *s != ConnectionState::Reconnecting),
+    )
+    .await
+    {
+        // a dropped sender means the client is gone, let the call proceed and fail by itself
+        Ok(_) => Ok(()),
+        Err(_) => Err(ClientError::ReconnectTimeout(deadline).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_during_reconnect_window() {
+        let (tx, rx) = watch::channel(ConnectionState::Connected);
+        assert!(wait_until_connected(&mut rx.clone(), Duration::ZERO)
+            .await
+            .is_ok());
+
+        // simulate a 2-second reconnect window, on a paused clock advanced by the test
+        tokio::time::pause();
+        tx.send_replace(ConnectionState::Reconnecting);
+        let wait = |deadline| {
+            let mut rx = rx.clone();
+            tokio::spawn(async move { wait_until_connected(&mut rx, deadline).await })
+        };
+        let patient = wait(Duration::from_secs(4));
+        let impatient = wait(Duration::from_millis(500));
+        tokio::task::yield_now().await;
+
+        tokio::time::advance(Duration::from_millis(600)).await;
+        assert!(matches!(
+            impatient.await.unwrap().unwrap_err().downcast_ref::<ClientError>(),
+            Some(ClientError::ReconnectTimeout(_))
+        ));
+        assert!(!patient.is_finished());
+
+        tokio::time::advance(Duration::from_millis(1400)).await;
+        tx.send_replace(ConnectionState::Connected);
+        assert!(patient.await.unwrap().is_ok());
+        assert_eq!(*rx.borrow(), ConnectionState::Connected);
+    }
+}
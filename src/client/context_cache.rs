@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Context;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+/// A context persisted by the context cache
+pub(crate) struct CachedContext {
+    pub cached_at: DateTime<Utc>,
+    pub context: Context,
+}
+
+/// A cache of the fetched contexts persisted as JSON files in a folder, one file per context URI.
+///
+/// The cache never fails: a missing or corrupted file is a cache miss and a failed write
+/// is logged, the corrupted file being rewritten by the next write.
+pub(crate) struct ContextCache {
+    folder: PathBuf,
+}
+
+impl CachedContext {
+    /// gets the snapshot id of a cached playlist context
+    pub fn snapshot_id(&self) -> Option<&str> {
+        match &self.context {
+            Context::Playlist { playlist, .. } => Some(&playlist.snapshot_id),
+            _ => None,
+        }
+    }
+}
+
+impl ContextCache {
+    pub fn new(folder: impl AsRef<Path>) -> Self {
+        Self {
+            folder: folder.as_ref().to_path_buf(),
+        }
+    }
+
+    /// gets the path of the file persisting a context by its URI
+    fn path(&self, uri: &str) -> PathBuf {
+        let name = uri.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        self.folder.join(format!("{name}.json"))
+    }
+
+    /// reads the cached copy of a context by its URI
+    pub fn read(&self, uri: &str) -> Option<CachedContext> {
+        let path = self.path(uri);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                tracing::warn!(
+                    "failed to read the cached context {}: {err:#}",
+                    path.display()
+                );
+                return None;
+            }
+        };
+        serde_json::from_str(&text)
+            .map_err(|err| {
+                tracing::warn!(
+                    "ignored the corrupted cached context {}: {err:#}",
+                    path.display()
+                )
+            })
+            .ok()
+    }
+
+    /// persists a context by its URI, replacing its cached copy
+    pub fn write(&self, uri: &str, context: &Context) {
+        let path = self.path(uri);
+        let cached = CachedContext {
+            cached_at: Utc::now(),
+            context: context.clone(),
+        };
+        let result = (|| -> anyhow::Result<()> {
+            std::fs::create_dir_all(&self.folder)?;
+            // the context is written to a temporary file first to never leave a partial file
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, serde_json::to_vec(&cached)?)?;
+            std::fs::rename(&tmp, &path)?;
+            Ok(())
+        })();
+        if let Err(err) = result {
+            tracing::warn!("failed to cache the context {}: {err:#}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Playlist, PlaylistId, UserId};
+
+    fn cache(name: &str) -> ContextCache {
+        let folder = std::env::temp_dir().join(format!(
+            "spotify-client-contexts-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&folder);
+        ContextCache::new(folder)
+    }
+
+    fn playlist_context(snapshot_id: &str) -> Context {
+        Context::Playlist {
+            playlist: Playlist {
+                id: PlaylistId::from_id("37i9dQZF1DXcBWIGoYBM5M").unwrap(),
+                collaborative: false,
+                name: "Today's Top Hits".to_string(),
+                owner: ("Spotify".to_string(), UserId::from_id("spotify").unwrap()),
+                desc: String::new(),
+                snapshot_id: snapshot_id.to_string(),
+            },
+            tracks: vec![],
+        }
+    }
+
+    #[test]
+    fn persist_contexts() {
+        let cache = cache("persist");
+        let uri = "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M";
+        assert!(cache.read(uri).is_none());
+
+        cache.write(uri, &playlist_context("v1"));
+        cache.write(uri, &playlist_context("v2"));
+        let cached = cache.read(uri).unwrap();
+        assert_eq!(cached.snapshot_id(), Some("v2"));
+        assert!(matches!(cached.context, Context::Playlist { .. }));
+    }
+
+    #[test]
+    fn ignore_and_rewrite_corrupted_contexts() {
+        let cache = cache("corrupted");
+        let uri = "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M";
+        std::fs::create_dir_all(&cache.folder).unwrap();
+        std::fs::write(cache.path(uri), "{\"cached_at\":").unwrap();
+        assert!(cache.read(uri).is_none());
+
+        cache.write(uri, &playlist_context("v1"));
+        assert_eq!(cache.read(uri).unwrap().snapshot_id(), Some("v1"));
+    }
+}
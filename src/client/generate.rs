@@ -0,0 +1,301 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::model::{Exclusion, GenerationCriteria, GenerationOrder};
+use crate::utils::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The audio features used to select the tracks of a generated playlist
+pub(crate) struct TrackFeatures {
+    pub tempo: f32,
+    pub energy: f32,
+    pub acousticness: f32,
+}
+
+#[derive(Debug, Clone)]
+/// A candidate track of a generated playlist
+pub(crate) struct Candidate<'a> {
+    pub id: &'a str,
+    pub duration: Duration,
+    /// `None` if the track's audio features are unavailable
+    pub features: Option<TrackFeatures>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The selected tracks of a generated playlist
+pub(crate) struct Selection {
+    /// the indices of the selected candidates, in the playlist's order
+    pub indices: Vec<usize>,
+    /// see `GenerationReport::exclusions`
+    pub exclusions: Vec<(Exclusion, usize)>,
+}
+
+/// returns the reason of a candidate's exclusion by the criteria's audio feature filters, if any
+fn filter(features: Option<TrackFeatures>, criteria: &GenerationCriteria) -> Option<Exclusion> {
+    let Some(features) = features else {
+        return Some(Exclusion::MissingFeatures);
+    };
+    if matches!(&criteria.tempo, Some(range) if !range.contains(&features.tempo)) {
+        Some(Exclusion::Tempo)
+    } else if matches!(criteria.min_energy, Some(min) if features.energy < min) {
+        Some(Exclusion::Energy)
+    } else if matches!(criteria.max_acousticness, Some(max) if features.acousticness > max) {
+        Some(Exclusion::Acousticness)
+    } else {
+        None
+    }
+}
+
+/// selects and orders the candidate tracks of a generated playlist.
+///
+/// The candidates are randomly shuffled, so that the tracks filling the target duration
+/// are a random sample of the accepted tracks. The result is deterministic for a given
+/// `criteria.seed`.
+///
+/// The target duration is filled greedily: an accepted track is skipped if it would exceed
+/// the remaining duration, letting shorter tracks fill the gap.
+pub(crate) fn select_tracks(candidates: &[Candidate], criteria: &GenerationCriteria) -> Selection {
+    let mut order = (0..candidates.len()).collect::<Vec<_>>();
+    Rng::new(criteria.seed).shuffle(&mut order);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut exclusions = HashMap::new();
+    let mut remaining = criteria.target_duration.unwrap_or(Duration::MAX);
+    let mut indices = vec![];
+    for i in order {
+        let candidate = &candidates[i];
+        let exclusion = if !seen.insert(candidate.id) {
+            Some(Exclusion::Duplicate)
+        } else if let Some(exclusion) = filter(candidate.features, criteria) {
+            Some(exclusion)
+        } else if candidate.duration > remaining {
+            Some(Exclusion::Duration)
+        } else {
+            None
+        };
+
+        match exclusion {
+            Some(exclusion) => *exclusions.entry(exclusion).or_insert(0) += 1,
+            None => {
+                remaining -= candidate.duration;
+                indices.push(i);
+            }
+        }
+    }
+
+    // the candidates' features are available after the filters
+    let features = |i: usize| {
+        candidates[i]
+            .features
+            .expect("features should be available")
+    };
+    match criteria.order {
+        GenerationOrder::Shuffled => {}
+        GenerationOrder::TempoAscending => {
+            indices.sort_by(|&x, &y| features(x).tempo.total_cmp(&features(y).tempo))
+        }
+        GenerationOrder::TempoDescending => {
+            indices.sort_by(|&x, &y| features(y).tempo.total_cmp(&features(x).tempo))
+        }
+        GenerationOrder::EnergyAscending => {
+            indices.sort_by(|&x, &y| features(x).energy.total_cmp(&features(y).energy))
+        }
+        GenerationOrder::EnergyDescending => {
+            indices.sort_by(|&x, &y| features(y).energy.total_cmp(&features(x).energy))
+        }
+    }
+
+    let mut exclusions = exclusions.into_iter().collect::<Vec<_>>();
+    exclusions.sort_by_key(|&(exclusion, count)| (std::cmp::Reverse(count), exclusion));
+
+    Selection {
+        indices,
+        exclusions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::GenerationSource;
+
+    fn criteria() -> GenerationCriteria {
+        GenerationCriteria {
+            name: "Workout".to_string(),
+            source: GenerationSource::LikedSongs,
+            tempo: None,
+            min_energy: None,
+            max_acousticness: None,
+            target_duration: None,
+            order: GenerationOrder::Shuffled,
+            seed: Some(42),
+        }
+    }
+
+    /// creates candidates of 3 minutes with the given tempos, energies and acousticnesses
+    fn candidates<'a>(ids: &'a [String], features: &[(f32, f32, f32)]) -> Vec<Candidate<'a>> {
+        ids.iter()
+            .zip(features)
+            .map(|(id, &(tempo, energy, acousticness))| Candidate {
+                id,
+                duration: Duration::from_secs(180),
+                features: Some(TrackFeatures {
+                    tempo,
+                    energy,
+                    acousticness,
+                }),
+            })
+            .collect()
+    }
+
+    fn ids(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("track{i}")).collect()
+    }
+
+    #[test]
+    fn filter_by_audio_features() {
+        let ids = ids(6);
+        let mut candidates = candidates(
+            &ids,
+            &[
+                (120.0, 0.8, 0.1),
+                (90.0, 0.8, 0.1),
+                (130.0, 0.3, 0.1),
+                (140.0, 0.9, 0.7),
+                (160.0, 0.2, 0.9),
+                (125.0, 0.7, 0.2),
+            ],
+        );
+        candidates.push(candidates[0].clone());
+        candidates.push(Candidate {
+            id: "unknown",
+            features: None,
+            ..candidates[0].clone()
+        });
+        let criteria = GenerationCriteria {
+            tempo: Some(110.0..=150.0),
+            min_energy: Some(0.5),
+            max_acousticness: Some(0.5),
+            ..criteria()
+        };
+
+        let selection = select_tracks(&candidates, &criteria);
+        let mut selected = selection
+            .indices
+            .iter()
+            .map(|&i| candidates[i].id)
+            .collect::<Vec<_>>();
+        selected.sort_unstable();
+        assert_eq!(selected, vec!["track0", "track5"]);
+
+        // a track failing multiple filters is excluded by the first one
+        assert_eq!(
+            selection.exclusions,
+            vec![
+                (Exclusion::Tempo, 2),
+                (Exclusion::Duplicate, 1),
+                (Exclusion::MissingFeatures, 1),
+                (Exclusion::Energy, 1),
+                (Exclusion::Acousticness, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn fill_target_duration() {
+        let ids = ids(20);
+        let mut candidates = candidates(&ids, &[(120.0, 0.8, 0.1); 20]);
+        candidates[3].duration = Duration::from_secs(60);
+        let criteria = GenerationCriteria {
+            target_duration: Some(Duration::from_secs(10 * 60)),
+            ..criteria()
+        };
+
+        let selection = select_tracks(&candidates, &criteria);
+        let duration = selection
+            .indices
+            .iter()
+            .map(|&i| candidates[i].duration)
+            .sum::<Duration>();
+        // three 3-minute tracks and the 1-minute track fill the 10 minutes
+        assert_eq!(duration, Duration::from_secs(10 * 60));
+        assert!(selection.indices.contains(&3));
+        assert_eq!(
+            selection.exclusions,
+            vec![(Exclusion::Duration, candidates.len() - 4)]
+        );
+
+        // no track fits in a shorter duration than the tracks
+        let criteria = GenerationCriteria {
+            target_duration: Some(Duration::from_secs(30)),
+            ..criteria
+        };
+        assert!(select_tracks(&candidates, &criteria).indices.is_empty());
+    }
+
+    #[test]
+    fn order_by_audio_features() {
+        let features = (0..30)
+            .map(|i| {
+                (
+                    (i * 7 % 30) as f32 + 100.0,
+                    (i * 11 % 30) as f32 / 30.0,
+                    0.0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let ids = ids(30);
+        let candidates = candidates(&ids, &features);
+        let ordered = |order| {
+            let selection = select_tracks(
+                &candidates,
+                &GenerationCriteria {
+                    order,
+                    ..criteria()
+                },
+            );
+            assert_eq!(selection.indices.len(), candidates.len());
+            selection
+                .indices
+                .iter()
+                .map(|&i| candidates[i].features.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(ordered(GenerationOrder::TempoAscending)
+            .windows(2)
+            .all(|w| w[0].tempo <= w[1].tempo));
+        assert!(ordered(GenerationOrder::TempoDescending)
+            .windows(2)
+            .all(|w| w[0].tempo >= w[1].tempo));
+        assert!(ordered(GenerationOrder::EnergyAscending)
+            .windows(2)
+            .all(|w| w[0].energy <= w[1].energy));
+        assert!(ordered(GenerationOrder::EnergyDescending)
+            .windows(2)
+            .all(|w| w[0].energy >= w[1].energy));
+    }
+
+    #[test]
+    fn deterministic_selection() {
+        let ids = ids(50);
+        let candidates = candidates(&ids, &[(120.0, 0.8, 0.1); 50]);
+        let criteria = GenerationCriteria {
+            target_duration: Some(Duration::from_secs(30 * 60)),
+            ..criteria()
+        };
+
+        let selection = select_tracks(&candidates, &criteria);
+        assert_eq!(selection.indices.len(), 10);
+        assert_eq!(selection, select_tracks(&candidates, &criteria));
+
+        // another seed samples other tracks
+        let other = select_tracks(
+            &candidates,
+            &GenerationCriteria {
+                seed: Some(7),
+                ..criteria
+            },
+        );
+        assert_ne!(selection.indices, other.indices);
+    }
+}
@@ -1,27 +1,73 @@
+use std::collections::HashMap;
 use std::ops::Deref;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::auth::AuthConfig;
+use crate::config::{AppConfig, RetryConfig};
 use crate::constant::*;
+use crate::error::{ApiError, ClientError};
+use crate::export::{ExportFormat, ExportWriter, ImportSource};
+use crate::timeseries::{Sample, TimeSeries};
 
 use anyhow::Context as _;
 use anyhow::Result;
+use futures::Stream;
 use librespot_core::session::Session;
 use rspotify::{
     http::Query,
-    model::{FullPlaylist, Market, Page, SimplifiedPlaylist},
+    model::{FullPlaylist, Market, Page, SimplifiedPlaylist, TimeLimits},
     prelude::*,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+mod cache;
+mod connection;
+mod context_cache;
+mod generate;
+mod operation;
+mod paging;
+mod quirks;
+mod restore;
+mod retry;
+mod single_flight;
 mod spotify;
+mod sync;
+mod windowed;
 
+pub use cache::{CacheControl, CacheStats};
+pub use connection::ConnectionState;
+pub use operation::{CancellationToken, DeadlineClient, OperationClient, Partial};
+pub use paging::{Paginator, SearchPage};
+pub use quirks::{Quirk, Quirks};
+pub use windowed::{PageFuture, WindowedCollection};
 
 /// The application's Spotify client
 pub struct Client {
     http: reqwest::Client,
+    /// the `Accept-Encoding` of the HTTP client's requests, `None` if unknown (a custom client)
+    accept_encoding: Option<&'static str>,
     spotify: Arc<spotify::Spotify>,
     auth_config: AuthConfig,
+    connection_state: tokio::sync::watch::Sender<ConnectionState>,
+    /// the deadline of a call waiting for the session to reconnect,
+    /// `None` if calls don't wait during reconnects
+    reconnect_deadline: Option<std::time::Duration>,
+    quirks: parking_lot::RwLock<Quirks>,
+    /// the current user's id, fetched on the first use
+    user_id: tokio::sync::OnceCell<UserId<'static>>,
+    /// the configured locale of the localized items, see `AppConfig::locale`
+    locale: Option<LanguageTag>,
+    /// the available genre seeds of recommendations, fetched on the first use
+    genre_seeds: tokio::sync::OnceCell<Vec<String>>,
+    /// the retries of the failed HTTP requests, see `AppConfig::retry`
+    retry: RetryConfig,
+    /// the cache of the GET responses, `None` if disabled, see `AppConfig::cache`
+    cache: Option<cache::ResponseCache>,
+    /// the persisted contexts, `None` if disabled, see `CacheConfig::persist_contexts`
+    context_cache: Option<context_cache::ContextCache>,
+    /// the in-flight GET requests, keyed by their URL and query
+    in_flight: single_flight::SingleFlight<String>,
 }
 
 impl Deref for Client {
@@ -31,17 +77,796 @@ impl Deref for Client {
     }
 }
 
+/// the maximum size of a (base64-encoded) playlist cover image accepted by Spotify
+const MAX_PLAYLIST_COVER_SIZE: usize = 256 * 1024;
+/// the maximum number of items in a playlist items request
+const PLAYLIST_ITEMS_LIMIT: usize = 100;
+/// the maximum number of items in a page of an offset-based collection (e.g. search results)
+const MAX_PAGE_SIZE: usize = 50;
+/// the maximum number of albums in a (several) albums request
+const ALBUMS_BATCH_LIMIT: usize = 20;
+/// the maximum number of artists in a (several) artists request
+const ARTISTS_BATCH_LIMIT: usize = 50;
+/// the maximum number of tracks in a (several) tracks request
+const TRACKS_BATCH_LIMIT: usize = 50;
+/// the maximum number of items in a request saving/removing items of the user's library
+const LIBRARY_ITEMS_LIMIT: usize = 50;
+/// the maximum number of moves of a playlist sorted with `SortStrategy::Auto`,
+/// above which the playlist's items are replaced
+const AUTO_SORT_MAX_MOVES: usize = 50;
+/// the maximum number of tracks in an audio features request
+const AUDIO_FEATURES_BATCH_LIMIT: usize = 100;
+/// the maximum number of items in a top items request
+const TOP_ITEMS_LIMIT: usize = 50;
+/// the maximum offset of a top items request, which caps the top items at 99 per time range
+const TOP_ITEMS_MAX_OFFSET: usize = 49;
+/// the maximum number of retries of a rate-limited request
+const MAX_RATE_LIMIT_RETRIES: usize = 5;
+/// the maximum number of concurrent requests of a batch operation
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+/// the maximum number of artists whose albums are fetched concurrently
+const ARTIST_ALBUMS_CONCURRENCY: usize = 8;
+/// the number of reachable search results, Spotify rejecting the larger offsets
+const SEARCH_MAX_RESULTS: usize = 1000;
+const JPEG_MAGIC_BYTES: &[u8] = &[0xFF, 0xD8, 0xFF];
+const PNG_MAGIC_BYTES: &[u8] = &[0x89, b'P', b'N', b'G'];
+
+/// computes Spotify's `insert_before` reorder parameter, which is a position in the playlist
+/// *before* the reorder, from the position (`insert_index`) of the first moved item *after* the reorder
+fn reorder_insert_before(range_start: usize, insert_index: usize, range_length: usize) -> usize {
+    if insert_index <= range_start {
+        // moving the items earlier: they are inserted before the item currently at `insert_index`
+        insert_index
+    } else {
+        // moving the items later: the moved items are removed first,
+        // so the insert position has to account for the range's length
+        insert_index + range_length
+    }
+}
+
+/// validates a playlist cover image (a JPEG image within Spotify's size limit)
+/// and encodes it in base64, as expected by Spotify's upload endpoint
+fn encode_playlist_cover(image: &[u8]) -> Result<String> {
+    use base64::Engine as _;
+
+    if image.starts_with(PNG_MAGIC_BYTES) {
+        anyhow::bail!("PNG images are not supported as playlist covers, please use a JPEG image");
+    }
+    if !image.starts_with(JPEG_MAGIC_BYTES) {
+        anyhow::bail!("the playlist cover should be a JPEG image");
+    }
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(image);
+    if encoded.len() > MAX_PLAYLIST_COVER_SIZE {
+        anyhow::bail!(
+            "the encoded playlist cover ({} KB) exceeds Spotify's limit of {} KB",
+            encoded.len() / 1024,
+            MAX_PLAYLIST_COVER_SIZE / 1024
+        );
+    }
+    Ok(encoded)
+}
+
+/// groups the positions of the playlist items to remove by the items' URIs, as expected by
+/// Spotify's endpoint, in chunks of at most `PLAYLIST_ITEMS_LIMIT` positions.
+///
+/// The chunks go from the highest positions to the lowest ones, so that removing a chunk
+/// never shifts the positions of the items in the remaining chunks.
+fn playlist_removals(
+    uris: &[Option<PlaylistItemUris>],
+    mut positions: Vec<usize>,
+) -> Result<Vec<Vec<(&str, Vec<u32>)>>> {
+    positions.sort_unstable_by(|x, y| y.cmp(x));
+    positions.dedup();
+    if let Some(&position) = positions.first() {
+        if position >= uris.len() {
+            anyhow::bail!(
+                "position {position} is out of range for a playlist of {} items",
+                uris.len()
+            );
+        }
+    }
+
+    positions
+        .chunks(PLAYLIST_ITEMS_LIMIT)
+        .map(|chunk| {
+            let mut groups: Vec<(&str, Vec<u32>)> = vec![];
+            for &position in chunk {
+                let uri = uris[position]
+                    .as_ref()
+                    .map(|u| u.stored_uri())
+                    .with_context(|| {
+                        format!("cannot remove the unavailable item at position {position}")
+                    })?;
+                match groups.iter_mut().find(|(u, _)| *u == uri) {
+                    Some((_, positions)) => positions.push(position as u32),
+                    None => groups.push((uri, vec![position as u32])),
+                }
+            }
+            Ok(groups)
+        })
+        .collect()
+}
+
+/// gets albums along with their tracks, see `Client::albums_with_tracks`.
+///
+/// The albums are fetched in batches with `fetch_albums`, each album including the first page
+/// of its tracks. The remaining tracks are collected with `fetch_tracks(first_page)`
+/// only for the albums with more tracks.
+async fn collect_albums_with_tracks<'a, FA, FAFut, FT, FTFut>(
+    album_ids: &[AlbumId<'a>],
+    fetch_albums: FA,
+    mut fetch_tracks: FT,
+) -> Result<Vec<(Album, Vec<Track>)>>
+where
+    FA: FnMut(Vec<AlbumId<'a>>) -> FAFut,
+    FAFut: std::future::Future<Output = Result<Vec<rspotify_model::FullAlbum>>>,
+    FT: FnMut(Page<rspotify_model::SimplifiedTrack>) -> FTFut,
+    FTFut: std::future::Future<Output = Result<Vec<rspotify_model::SimplifiedTrack>>>,
+{
+    let full_albums = paging::fetch_batches(album_ids, ALBUMS_BATCH_LIMIT, fetch_albums).await?;
+
+    let mut albums = Vec::with_capacity(full_albums.len());
+    for album in full_albums {
+        let first_page = album.tracks.clone();
+        let album: Album = album.into();
+        let tracks = match first_page.next {
+            Some(_) => fetch_tracks(first_page).await?,
+            None => first_page.items,
+        };
+        let tracks = tracks
+            .into_iter()
+            .filter_map(|t| {
+                Track::try_from_simplified_track(t).map(|mut t| {
+                    t.album = Some(album.clone());
+                    t
+                })
+            })
+            .collect::<Vec<_>>();
+        albums.push((album, tracks));
+    }
+
+    Ok(albums)
+}
+
+/// makes the writes of a playlist's chunks with `write(chunk, snapshot_id)` in order,
+/// passing to each write the snapshot id returned by the previous write (`snapshot_id`
+/// for the first write), and returns the snapshot id of the last write.
+///
+/// The writes fail once the current operation's deadline is reached or the operation
+/// is cancelled, never leaving a chunk half-written.
+async fn chain_snapshots<'c, T, F, Fut>(
+    chunks: impl IntoIterator<Item = &'c [T]>,
+    mut snapshot_id: Option<String>,
+    mut write: F,
+) -> Result<Option<String>>
+where
+    T: 'c,
+    F: FnMut(&'c [T], Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<String>>,
+{
+    for chunk in chunks {
+        operation::check()?;
+        snapshot_id = Some(write(chunk, snapshot_id).await?);
+    }
+    Ok(snapshot_id)
+}
+
+/// returns a `ClientError::SnapshotMismatch` if a playlist's `actual` snapshot id
+/// isn't the `expected` one
+fn ensure_snapshot(expected: &str, actual: String) -> Result<()> {
+    if actual != expected {
+        return Err(ClientError::SnapshotMismatch {
+            expected: expected.to_string(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// computes the items of a playlist sorted by the sorted positions of its sorted items,
+/// the other items staying at their positions.
+///
+/// Returns `None` if an item cannot be re-added to the playlist (a local file).
+fn sorted_items<T: Clone>(items: &[Option<T>], sorted_positions: &[usize]) -> Option<Vec<T>> {
+    let mut slots = sorted_positions.to_vec();
+    slots.sort_unstable();
+    let mut sorted = items.to_vec();
+    for (slot, &i) in slots.into_iter().zip(sorted_positions) {
+        sorted[slot] = items[i].clone();
+    }
+    sorted.into_iter().collect()
+}
+
+/// converts a track or episode URI into a `PlayableId`
+fn playable_id_from_uri(uri: &str) -> Option<PlayableId<'_>> {
+    TrackId::from_uri(uri)
+        .map(PlayableId::Track)
+        .or_else(|_| rspotify_model::EpisodeId::from_uri(uri).map(PlayableId::Episode))
+        .ok()
+}
+
+/// gets the delay before retrying a rate-limited `rspotify` request from its `Retry-After`
+/// header, returns `None` if the error isn't a rate limit error of a `rspotify` request.
+///
+/// The requests made with `Client::http_request` are already retried following the retry
+/// configuration, which may give up (e.g. on a `Retry-After` above the maximum delay),
+/// so their rate limit errors aren't retried again.
+fn rate_limit_retry_after(err: &anyhow::Error) -> Option<Option<std::time::Duration>> {
+    let rspotify::ClientError::Http(err) = err.downcast_ref::<rspotify::ClientError>()? else {
+        return None;
+    };
+    let rspotify::http::HttpError::StatusCode(response) = err.as_ref() else {
+        return None;
+    };
+    // `rspotify` depends on another version of `reqwest`, so its status codes and headers
+    // are compared by their values
+    if response.status().as_u16() != 429 {
+        return None;
+    }
+    Some(
+        response
+            .headers()
+            .get("retry-after")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs),
+    )
+}
+
+/// checks if the error is a "400 Bad Request" response to a `rspotify` request,
+/// e.g. because of an invalid id in the request
+fn is_bad_request(err: &anyhow::Error) -> bool {
+    let Some(rspotify::ClientError::Http(err)) = err.downcast_ref::<rspotify::ClientError>() else {
+        return false;
+    };
+    let rspotify::http::HttpError::StatusCode(response) = err.as_ref() else {
+        return false;
+    };
+    response.status().as_u16() == 400
+}
+
+/// creates the error of a request to `url` whose response has a non-2xx `status` code,
+/// parsed from the response's `text`
+fn api_error(status: reqwest::StatusCode, url: &str, text: &str) -> anyhow::Error {
+    ClientError::Api(ApiError::from_response(status.as_u16(), url, text)).into()
+}
+
+/// serializes the JSON body of a request, along with its content type
+fn json_body<B: serde::Serialize + ?Sized>(body: &B) -> Result<(&'static str, Vec<u8>)> {
+    Ok(("application/json", serde_json::to_vec(body)?))
+}
+
+/// parses the JSON of a successful response's `text`. The mutating endpoints may respond
+/// without a body (e.g. `204 No Content`), an empty response is read as `null`.
+fn json_response<T: serde::de::DeserializeOwned>(text: &str) -> Result<T> {
+    let text = if text.trim().is_empty() { "null" } else { text };
+    Ok(serde_json::from_str(text)?)
+}
+
+/// fetches items by their ids with `fetch`, in batches of at most `batch_size` ids
+/// fetched `MAX_CONCURRENT_REQUESTS` at a time, pairing each id with its item.
+/// Fails if a batch's response doesn't list exactly one item (or `None`) per requested id,
+/// as its items couldn't be matched to the ids.
+async fn items_by_id<'a, T, F, Fut>(
+    ids: &'a [String],
+    batch_size: usize,
+    mut fetch: F,
+) -> Result<Vec<(String, Option<T>)>>
+where
+    F: FnMut(&'a [String]) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<Option<T>>>>,
+{
+    let batches = ids.chunks(batch_size).collect();
+    let batches = paging::map_concurrently(batches, MAX_CONCURRENT_REQUESTS, |batch| {
+        let items = fetch(batch);
+        async move {
+            let items = items.await?;
+            anyhow::ensure!(
+                items.len() == batch.len(),
+                "expect {} items in the response, got {}",
+                batch.len(),
+                items.len()
+            );
+            anyhow::Ok(batch.iter().cloned().zip(items).collect::<Vec<_>>())
+        }
+    })
+    .await?;
+    Ok(batches.into_iter().flatten().collect())
+}
+
+/// checks if items are saved in the current user's library with `contains`,
+/// in chunks of at most `LIBRARY_ITEMS_LIMIT` ids.
+/// The ids of a chunk rejected as a bad request are considered unsaved.
+async fn contains_or_unsaved<I, F, Fut>(ids: &[I], mut contains: F) -> Result<Vec<bool>>
+where
+    I: Clone,
+    F: FnMut(Vec<I>) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<bool>>>,
+{
+    paging::map_chunks(ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+        let len = chunk.len();
+        let request = contains(chunk);
+        async move {
+            match request.await {
+                Err(err) if is_bad_request(&err) => {
+                    tracing::warn!("Failed to check if items are saved: {err:#}");
+                    Ok(vec![false; len])
+                }
+                result => result,
+            }
+        }
+    })
+    .await
+}
+
+/// toggles the state of an item (e.g. whether a track is saved): checks its state with `check`,
+/// sets the opposite state with `set`, then checks the state again, returning the resulting state.
+///
+/// The state is checked again after the write because another device may change the item
+/// concurrently, in which case the returned state is the item's actual state rather than
+/// the state that was set from a stale check.
+async fn toggle_state<C, CFut, S, SFut>(mut check: C, set: S) -> Result<bool>
+where
+    C: FnMut() -> CFut,
+    CFut: std::future::Future<Output = Result<Vec<bool>>>,
+    S: FnOnce(bool) -> SFut,
+    SFut: std::future::Future<Output = Result<()>>,
+{
+    let target = !check().await?.first().copied().unwrap_or_default();
+    set(target).await?;
+    let state = check().await?.first().copied().unwrap_or_default();
+    if state != target {
+        tracing::warn!("the toggled item was changed concurrently, its state is {state}");
+    }
+    Ok(state)
+}
+
+/// runs a `rspotify` request, retrying it with an exponential backoff while it is rate limited
+async fn with_rate_limit_backoff<T, F, Fut>(mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut delay = std::time::Duration::from_secs(1);
+    let mut retries = 0;
+    loop {
+        match request().await {
+            Err(err) if retries < MAX_RATE_LIMIT_RETRIES => match rate_limit_retry_after(&err) {
+                Some(retry_after) => {
+                    let wait = retry_after.unwrap_or(delay);
+                    tracing::warn!("request is rate limited, retrying in {wait:?}");
+                    operation::sleep(wait).await?;
+                    delay *= 2;
+                    retries += 1;
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+/// builds the query searching a track by its name and its first artist
+/// (of the comma-separated `artists`), e.g. to match an imported track
+fn track_search_query(name: &str, artists: &str) -> SearchQuery {
+    let query = SearchQuery::default().track(name);
+    match artists.split(',').next().map(str::trim) {
+        Some(artist) if !artist.is_empty() => query.artist(artist),
+        _ => query,
+    }
+}
+
+/// converts the result of a single type search into `SearchResults`,
+/// along with the total number of matching items
+fn into_search_results(result: rspotify_model::SearchResult) -> Result<(SearchResults, usize)> {
+    let mut results = SearchResults::default();
+    let total = match result {
+        rspotify_model::SearchResult::Tracks(p) => {
+            results.tracks = p
+                .items
+                .into_iter()
+                .filter_map(Track::try_from_full_track)
+                .collect();
+            p.total
+        }
+        rspotify_model::SearchResult::Artists(p) => {
+            results.artists = p.items.into_iter().map(|a| a.into()).collect();
+            p.total
+        }
+        rspotify_model::SearchResult::Albums(p) => {
+            results.albums = p
+                .items
+                .into_iter()
+                .filter_map(Album::try_from_simplified_album)
+                .collect();
+            p.total
+        }
+        rspotify_model::SearchResult::Playlists(p) => {
+            results.playlists = p.items.into_iter().map(|i| i.into()).collect();
+            p.total
+        }
+        rspotify_model::SearchResult::Shows(p) => {
+            results.shows = p.items.into_iter().map(Show::from).collect();
+            p.total
+        }
+        rspotify_model::SearchResult::Episodes(p) => {
+            results.episodes = p.items.into_iter().map(Episode::from).collect();
+            p.total
+        }
+        _ => anyhow::bail!("unsupported search result type"),
+    };
+    Ok((results, total as usize))
+}
+
 fn market_query() -> Query<'static> {
     Query::from([("market", "from_token")])
 }
 
+/// gets the request payload of a market, the current user's market if `None`
+fn market_query_for(market: Option<Market>) -> Query<'static> {
+    Query::from([("market", <&str>::from(market.unwrap_or(Market::FromToken)))])
+}
+
+/// resolves the locale of the localized items: the configured locale or (if not set) the locale
+/// of the `LANG` environment variable. An invalid configured locale falls back to Spotify's
+/// default (American English).
+fn resolve_locale(configured: Option<&str>, lang: Option<&str>) -> Option<LanguageTag> {
+    match configured {
+        Some(locale) => LanguageTag::new(locale)
+            .map_err(|err| tracing::warn!("failed to parse the configured locale: {err:#}"))
+            .ok(),
+        None => lang.and_then(LanguageTag::from_posix_locale),
+    }
+}
+
+/// converts the query parameters of `BrowseParams` into a request payload
+fn browse_query(query: &[(&'static str, String)]) -> Query<'_> {
+    query.iter().map(|(k, v)| (*k, v.as_str())).collect()
+}
+
+#[derive(Debug, Deserialize)]
+/// A user's profile returned by the users' profile endpoints
+struct UserProfile {
+    id: UserId<'static>,
+    display_name: Option<String>,
+    country: Option<String>,
+    product: Option<String>,
+    followers: Option<Followers>,
+    #[serde(default)]
+    images: Vec<Image>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Followers {
+    total: u32,
+}
+
+impl From<UserProfile> for User {
+    fn from(profile: UserProfile) -> Self {
+        Self {
+            id: profile.id,
+            display_name: profile.display_name,
+            country: profile.country,
+            product: profile.product,
+            followers: profile.followers.map(|f| f.total).unwrap_or_default(),
+            images: profile.images,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// A response of the search endpoint, with the results of the requested types
+struct SearchResponse {
+    tracks: Option<Page<rspotify_model::FullTrack>>,
+    artists: Option<Page<rspotify_model::FullArtist>>,
+    albums: Option<Page<rspotify_model::SimplifiedAlbum>>,
+    playlists: Option<Page<SimplifiedPlaylist>>,
+    shows: Option<PodcastPage<PodcastShow>>,
+    episodes: Option<PodcastPage<PodcastEpisode>>,
+}
+
+impl SearchResponse {
+    /// gets the total number of matching items of the searched types
+    fn total(&self) -> usize {
+        [
+            self.tracks.as_ref().map(|p| p.total as usize),
+            self.artists.as_ref().map(|p| p.total as usize),
+            self.albums.as_ref().map(|p| p.total as usize),
+            self.playlists.as_ref().map(|p| p.total as usize),
+            self.shows.as_ref().map(|p| p.total),
+            self.episodes.as_ref().map(|p| p.total),
+        ]
+        .into_iter()
+        .flatten()
+        .sum()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// A page of podcast items (shows or episodes), which can include `null` items
+struct PodcastPage<T> {
+    #[serde(default)]
+    items: Vec<Option<T>>,
+    #[serde(default)]
+    next: Option<String>,
+    #[serde(default)]
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+/// A show, deserialized permissively as Spotify returns some of the podcasts' fields as `null`
+struct PodcastShow {
+    id: ShowId<'static>,
+    name: Option<String>,
+    publisher: Option<String>,
+    description: Option<String>,
+    /// the first page of the show's episodes, only returned with a full show
+    #[serde(default)]
+    episodes: Option<PodcastPage<PodcastEpisode>>,
+}
+
+#[derive(Debug, Deserialize)]
+/// An episode, deserialized permissively as Spotify returns some of the podcasts' fields as `null`
+struct PodcastEpisode {
+    id: EpisodeId<'static>,
+    name: Option<String>,
+    description: Option<String>,
+    duration_ms: Option<u64>,
+    release_date: Option<String>,
+    /// the episode's show, only returned with a full episode
+    #[serde(default)]
+    show: Option<PodcastShow>,
+    #[serde(default)]
+    resume_point: Option<PodcastResumePoint>,
+}
+
+#[derive(Debug, Deserialize)]
+/// The current user's playback progress of an episode, see `PodcastEpisode`
+struct PodcastResumePoint {
+    fully_played: Option<bool>,
+    resume_position_ms: Option<u64>,
+}
+
+impl From<PodcastShow> for Show {
+    fn from(show: PodcastShow) -> Self {
+        Self {
+            id: show.id,
+            name: show.name.unwrap_or_default(),
+            publisher: show.publisher.unwrap_or_default(),
+            description: show.description.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<PodcastEpisode> for Episode {
+    fn from(episode: PodcastEpisode) -> Self {
+        Self {
+            id: episode.id,
+            name: episode.name.unwrap_or_default(),
+            show_name: episode.show.and_then(|show| show.name).unwrap_or_default(),
+            description: crate::utils::unescape_html(&episode.description.unwrap_or_default())
+                .into_owned(),
+            duration: std::time::Duration::from_millis(episode.duration_ms.unwrap_or_default()),
+            release_date: episode.release_date.unwrap_or_default(),
+            resume_point: episode.resume_point.map(|point| ResumePoint {
+                fully_played: point.fully_played.unwrap_or_default(),
+                resume_position: std::time::Duration::from_millis(
+                    point.resume_position_ms.unwrap_or_default(),
+                ),
+            }),
+        }
+    }
+}
+
+impl From<SearchResponse> for SearchResults {
+    fn from(response: SearchResponse) -> Self {
+        fn items<T, U: From<T>>(page: Option<PodcastPage<T>>) -> Vec<U> {
+            page.map(|p| p.items.into_iter().flatten().map(U::from).collect())
+                .unwrap_or_default()
+        }
+
+        Self {
+            tracks: response
+                .tracks
+                .map(|p| {
+                    p.items
+                        .into_iter()
+                        .filter_map(Track::try_from_full_track)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            artists: response
+                .artists
+                .map(|p| p.items.into_iter().map(|a| a.into()).collect())
+                .unwrap_or_default(),
+            albums: response
+                .albums
+                .map(|p| {
+                    p.items
+                        .into_iter()
+                        .filter_map(Album::try_from_simplified_album)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            playlists: response
+                .playlists
+                .map(|p| p.items.into_iter().map(|i| i.into()).collect())
+                .unwrap_or_default(),
+            shows: items(response.shows),
+            episodes: items(response.episodes),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// The URIs of a playlist item, returned by a request filtered with the `fields` query parameter
+struct PlaylistItemUris {
+    uri: String,
+    /// the original track of a relinked track
+    linked_from: Option<LinkedFromUri>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkedFromUri {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistItemUrisItem {
+    track: Option<PlaylistItemUris>,
+}
+
+#[derive(Debug, Deserialize)]
+/// A page of a playlist's item URIs. The filtered response doesn't have all the fields
+/// of `rspotify_model::Page`.
+struct PlaylistItemUrisPage {
+    items: Vec<PlaylistItemUrisItem>,
+    next: Option<String>,
+}
+
+/// gets the request payload of a playlist's item URIs. The items are only relinked
+/// (with their original URIs in `linked_from`) when a market is requested.
+fn playlist_item_uris_query() -> Query<'static> {
+    Query::from([
+        ("fields", "items(track(uri,linked_from(uri))),next"),
+        ("market", "from_token"),
+    ])
+}
+
+impl PlaylistItemUris {
+    /// gets the item's URI as stored in the playlist
+    fn stored_uri(&self) -> &str {
+        match &self.linked_from {
+            Some(linked_from) => &linked_from.uri,
+            None => &self.uri,
+        }
+    }
+
+    /// gets the item's URIs, including the original URI of a relinked track
+    fn uris(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.uri.as_str()).chain(self.linked_from.as_ref().map(|l| l.uri.as_str()))
+    }
+
+    /// gets the item's lowercased base-62 track ids, including the original id of a relinked track.
+    /// Local files and episodes don't have any track id.
+    fn track_ids(&self) -> impl Iterator<Item = String> + '_ {
+        self.uris()
+            .filter_map(|uri| uri.strip_prefix("spotify:track:"))
+            .map(|id| id.to_lowercase())
+    }
+}
+
 impl Client {
-    /// Construct a new client
-    pub fn new(session: Session, auth_config: AuthConfig, client_id: String) -> Self {
+    /// Construct a new client, whose HTTP client is built from `AppConfig::http_client`.
+    ///
+    /// Fails if the configured HTTP client cannot be built, e.g. because of an invalid
+    /// TLS backend, rather than silently ignoring the configuration.
+    pub fn new(session: Session, auth_config: AuthConfig, app_config: &AppConfig) -> Result<Self> {
+        let http = app_config
+            .http_client()
+            .context("build the configured HTTP client")?;
+        let mut client = Self::with_http_client(session, auth_config, app_config, http);
+        client.accept_encoding = Some(app_config.http.accept_encoding());
+        Ok(client)
+    }
+
+    /// Construct a new client sending the requests to the Spotify Web API with `http`,
+    /// e.g. a client with custom timeouts, proxy or TLS settings.
+    ///
+    /// `AppConfig::http` and the proxy configs are ignored. The requests of `rspotify` don't use the client.
+    pub fn with_http_client(
+        session: Session,
+        auth_config: AuthConfig,
+        app_config: &AppConfig,
+        http: reqwest::Client,
+    ) -> Self {
+        let quirks = Quirks::with_disabled_ignoring_unknown(&app_config.disabled_quirks);
+        let locale = resolve_locale(
+            app_config.locale.as_deref(),
+            std::env::var("LANG").ok().as_deref(),
+        );
+
         Self {
-            spotify: Arc::new(spotify::Spotify::new(session, client_id)),
-            http: reqwest::Client::new(),
+            spotify: Arc::new(spotify::Spotify::new(
+                session,
+                app_config.client_id.to_owned(),
+                app_config.max_concurrent_requests,
+            )),
+            http,
+            accept_encoding: None,
             auth_config,
+            connection_state: tokio::sync::watch::channel(ConnectionState::Connected).0,
+            reconnect_deadline: None,
+            quirks: parking_lot::RwLock::new(quirks),
+            user_id: tokio::sync::OnceCell::new(),
+            locale,
+            genre_seeds: tokio::sync::OnceCell::new(),
+            retry: app_config.retry.clone(),
+            cache: app_config
+                .cache
+                .enabled
+                .then(|| cache::ResponseCache::new(&app_config.cache)),
+            context_cache: app_config
+                .cache
+                .contexts_folder()
+                .map(context_cache::ContextCache::new),
+            in_flight: single_flight::SingleFlight::default(),
+        }
+    }
+
+    /// Enable or disable a quirk workaround by its name, see `Quirk` for the list of quirks
+    pub fn set_quirk(&self, name: &str, enabled: bool) -> Result<()> {
+        self.quirks.write().set(name, enabled)
+    }
+
+    /// Clear the cached responses, see `AppConfig::cache`
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Get the counters of the response cache, `None` if the cache is disabled
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(cache::ResponseCache::stats)
+    }
+
+    /// Get the client's quirk workarounds
+    pub fn quirks(&self) -> Quirks {
+        self.quirks.read().clone()
+    }
+
+    fn is_quirk_enabled(&self, quirk: Quirk) -> bool {
+        self.quirks.read().is_enabled(quirk)
+    }
+
+    /// Make API calls issued while the session is reconnecting wait (up to `deadline`)
+    /// for the reconnection to complete instead of failing immediately.
+    ///
+    /// A call exceeding the deadline fails with `ClientError::ReconnectTimeout`.
+    /// Only the requests made by the client's HTTP layer are queued,
+    /// calls to the underlying `rspotify` client are not.
+    pub fn queue_during_reconnect(mut self, deadline: std::time::Duration) -> Self {
+        self.reconnect_deadline = Some(deadline);
+        self
+    }
+
+    /// Subscribe to the changes of the client's connection state
+    pub fn connection_state(&self) -> tokio::sync::watch::Receiver<ConnectionState> {
+        self.connection_state.subscribe()
+    }
+
+    /// Wait for the session to reconnect if the client is configured to queue calls during reconnects
+    async fn wait_for_connection(&self) -> Result<()> {
+        match self.reconnect_deadline {
+            Some(deadline) => {
+                let mut state = self.connection_state.subscribe();
+                operation::cancellable(connection::wait_until_connected(&mut state, deadline))
+                    .await?
+            }
+            None => Ok(()),
         }
     }
 
@@ -50,18 +875,65 @@ impl Client {
     // - `state` when the `streaming` feature is not enabled
     #[allow(unused_variables)]
     async fn new_session(&self) -> Result<()> {
-        let session = crate::auth::new_session(&self.auth_config, false).await?;
+        self.connection_state.send_replace(ConnectionState::Reconnecting);
+
+        let session = match crate::auth::new_session(&self.auth_config, false).await {
+            Ok(session) => session,
+            Err(err) => {
+                self.connection_state.send_replace(ConnectionState::Disconnected);
+                return Err(err);
+            }
+        };
         *self.session.lock().await = Some(session);
 
+        self.connection_state.send_replace(ConnectionState::Connected);
         tracing::info!("Used a new session for Spotify client.");
 
         Ok(())
     }
 
-    /// Get the UserName of Spotify
-    pub fn username(&self) -> UserId {
-        let name: &str = self.auth_config.login_info.0.as_ref();
-        UserId::from_id(name).unwrap()
+    /// Get the current user's id.
+    ///
+    /// The id is fetched from the API once and cached. If the request fails, the configured
+    /// login is used as the id, unless it isn't a valid id (e.g. an email address).
+    pub async fn username(&self) -> Result<UserId<'static>> {
+        match self.current_user_id().await {
+            Ok(user_id) => Ok(user_id),
+            Err(err) => {
+                let login = &self.auth_config.login_info.0;
+                tracing::warn!("Failed to get the current user's id, using the login: {err:#}");
+                UserId::from_id(login.clone()).map_err(|_| err)
+            }
+        }
+    }
+
+    /// Get the current user's profile, including the private details
+    /// (country and subscription level) allowed by the granted scopes
+    pub async fn current_user_profile(&self) -> Result<User> {
+        let profile = self
+            .http_get::<UserProfile>(&format!("{SPOTIFY_API_ENDPOINT}/me"), &Query::new())
+            .await?;
+        Ok(profile.into())
+    }
+
+    /// Get the public profile of a user
+    pub async fn user_profile(&self, user_id: UserId<'_>) -> Result<User> {
+        let profile = self
+            .http_get::<UserProfile>(
+                &format!("{SPOTIFY_API_ENDPOINT}/users/{}", user_id.id()),
+                &Query::new(),
+            )
+            .await?;
+        Ok(profile.into())
+    }
+
+    /// Get the current user's id, which is fetched once and cached
+    async fn current_user_id(&self) -> Result<UserId<'static>> {
+        let user_id = self
+            .user_id
+            .get_or_try_init(|| async { anyhow::Ok(self.current_user().await?.id) })
+            .await?;
+        Ok(user_id.clone())
     }
 
     /// Check if the current session is valid and if invalid, create a new session
@@ -75,277 +947,2366 @@ impl Client {
         Ok(())
     }
 
-    /// Get Spotify's available browse categories
-    pub async fn browse_categories(&self) -> Result<Vec<Category>> {
+    /// Re-authenticate the client after its access token was rejected:
+    /// create a new session if the current one is invalid and refresh the access token
+    async fn reauthenticate(&self) -> Result<()> {
+        self.check_valid_session().await?;
+        self.force_token_refresh().await?;
+        Ok(())
+    }
+
+    /// Retrieve a new access token, even if the current one is still valid,
+    /// e.g. to recover from a token rejected by the server
+    pub async fn force_token_refresh(&self) -> Result<()> {
+        self.spotify
+            .force_token_refresh()
+            .await
+            .context("refresh the access token")?;
+        Ok(())
+    }
+
+    /// Get Spotify's available browse categories (the first `limit` categories if specified),
+    /// whose names are localized in the params' locale or (if not set) the configured locale
+    pub async fn browse_categories(
+        &self,
+        params: &BrowseParams,
+        limit: Option<usize>,
+    ) -> Result<Vec<Category>> {
+        #[derive(Debug, Deserialize)]
+        struct CategoriesResponse {
+            categories: Page<rspotify_model::category::Category>,
+        }
+
+        let query = params.clone().or_locale(self.locale.as_ref()).query();
+        let page_size = limit.unwrap_or(50).clamp(1, 50).to_string();
+        let mut payload = browse_query(&query);
+        payload.insert("limit", &page_size);
+
         let first_page = self
-            .categories_manual(Some("EN"), None, Some(50), None)
+            .http_get::<CategoriesResponse>(
+                &format!("{SPOTIFY_API_ENDPOINT}/browse/categories"),
+                &payload,
+            )
+            .await?
+            .categories;
+        // the next pages' URLs include the query parameters
+        let categories = paging::collect_pages_limited(
+            first_page.items,
+            first_page.next,
+            limit,
+            |url| async move {
+                let page = self
+                    .http_get::<CategoriesResponse>(&url, &Query::new())
+                    .await?
+                    .categories;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await?;
+
+        Ok(categories.into_iter().map(Category::from).collect())
+    }
+
+    /// Get a browse category by its id, whose name is localized in the given locale
+    /// (e.g. `sv_SE`) or (if not specified) the configured locale.
+    ///
+    /// Category ids differ between markets: a category missing in the current user's market
+    /// fails with `ClientError::CategoryUnavailableInMarket`.
+    pub async fn category(&self, category_id: &str, locale: Option<&str>) -> Result<Category> {
+        let params = match locale {
+            Some(locale) => BrowseParams::new().locale(LanguageTag::new(locale)?),
+            None => BrowseParams::new(),
+        };
+        let query = params.or_locale(self.locale.as_ref()).query();
+
+        let url = format!("{SPOTIFY_API_ENDPOINT}/browse/categories/{category_id}");
+        let (status, text) = self
+            .http_request(reqwest::Method::GET, &url, &browse_query(&query), None)
+            .await?;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::CategoryUnavailableInMarket(category_id.to_string()).into());
+        }
+        if !status.is_success() {
+            return Err(api_error(status, &url, &text));
+        }
+
+        let text = self.quirks.read().fix_response(text);
+        let category = serde_json::from_str::<rspotify_model::category::Category>(&text)?;
+        Ok(category.into())
+    }
+
+    /// Get Spotify's featured playlists along with their (localized) message header
+    ///
+    /// The playlists featured at a past date and time can be requested with
+    /// `BrowseParams::timestamp`. The message is localized in the params' locale
+    /// or (if not set) the configured locale.
+    pub async fn featured_playlists(
+        &self,
+        params: &BrowseParams,
+    ) -> Result<(String, Vec<Playlist>)> {
+        let query = params.clone().or_locale(self.locale.as_ref()).query();
+        let mut payload = browse_query(&query);
+        payload.insert("limit", "50");
+
+        let featured = self
+            .http_get::<rspotify_model::FeaturedPlaylists>(
+                &format!("{SPOTIFY_API_ENDPOINT}/browse/featured-playlists"),
+                &payload,
+            )
             .await?;
+        // the next pages are wrapped with the message like the first page's response
+        let playlists = paging::collect_pages(
+            featured.playlists.items,
+            featured.playlists.next,
+            |url| async move {
+                let page = self
+                    .http_get::<rspotify_model::FeaturedPlaylists>(&url, &Query::new())
+                    .await?
+                    .playlists;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await?;
+
+        Ok((
+            featured.message,
+            playlists.into_iter().map(Playlist::from).collect(),
+        ))
+    }
+
+    /// Get Spotify's new album releases
+    pub async fn new_releases(&self, params: &BrowseParams) -> Result<Vec<Album>> {
+        #[derive(Debug, Deserialize)]
+        struct NewReleasesResponse {
+            albums: Page<rspotify_model::SimplifiedAlbum>,
+        }
 
-        Ok(first_page.items.into_iter().map(Category::from).collect())
+        let query = params.query();
+        let mut payload = browse_query(&query);
+        payload.insert("limit", "50");
+
+        let first_page = self
+            .http_get::<NewReleasesResponse>(
+                &format!("{SPOTIFY_API_ENDPOINT}/browse/new-releases"),
+                &payload,
+            )
+            .await?
+            .albums;
+
+        Ok(first_page
+            .items
+            .into_iter()
+            .filter_map(Album::try_from_simplified_album)
+            .collect())
     }
 
     /// Get Spotify's available browse playlists of a given category
-    pub async fn browse_category_playlists(&self, category_id: &str) -> Result<Vec<Playlist>> {
+    /// (the first `limit` playlists if specified)
+    pub async fn browse_category_playlists(
+        &self,
+        category_id: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<Playlist>> {
+        #[derive(Debug, Deserialize)]
+        struct CategoryPlaylistsResponse {
+            playlists: Page<SimplifiedPlaylist>,
+        }
+
+        let page_size = limit.unwrap_or(50).clamp(1, 50) as u32;
         let first_page = self
-            .category_playlists_manual(category_id, None, Some(50), None)
+            .category_playlists_manual(category_id, None, Some(page_size), None)
             .await?;
+        // the next pages are wrapped in a `playlists` object like the first page's response
+        let playlists = paging::collect_pages_limited(
+            first_page.items,
+            first_page.next,
+            limit,
+            |url| async move {
+                let page = self
+                    .http_get::<CategoryPlaylistsResponse>(&url, &Query::new())
+                    .await?
+                    .playlists;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await?;
 
-        Ok(first_page.items.into_iter().map(Playlist::from).collect())
+        Ok(playlists.into_iter().map(Playlist::from).collect())
     }
 
     /// Get the saved (liked) tracks of the current user
     pub async fn current_user_saved_tracks(&self) -> Result<Vec<Track>> {
+        self.current_user_saved_tracks_with(&FetchOptions::default())
+            .await
+    }
+
+    /// Get the saved tracks of the current user, paginated with the fetch options
+    /// and relinked for the options' market. The tracks unavailable in the market are skipped.
+    pub async fn current_user_saved_tracks_with(
+        &self,
+        options: &FetchOptions,
+    ) -> Result<Vec<Track>> {
         let first_page = self
-            .current_user_saved_tracks_manual(Some(Market::FromToken), Some(50), None)
+            .current_user_saved_tracks_manual(
+                Some(options.market.unwrap_or(Market::FromToken)),
+                Some(options.page_size(LIBRARY_ITEMS_LIMIT as u32)),
+                None,
+            )
+            .await?;
+        let tracks = self
+            .paging_items(first_page, &market_query_for(options.market), options)
             .await?;
-        let tracks = self.all_paging_items(first_page, &market_query()).await?;
         Ok(tracks
             .into_iter()
             .filter_map(|t| Track::try_from_full_track(t.track))
             .collect())
     }
 
-    /// Get the recently played tracks of the current user
+    /// Get the saved (liked) tracks of the current user along with the dates of their saving,
+    /// newest first
+    pub async fn current_user_saved_tracks_detailed(&self) -> Result<Vec<SavedTrack>> {
+        let first_page = self
+            .current_user_saved_tracks_manual(Some(Market::FromToken), Some(50), None)
+            .await?;
+        let tracks = self.all_paging_items(first_page, &market_query()).await?;
+        Ok(tracks
+            .into_iter()
+            .filter_map(SavedTrack::try_from_saved_track)
+            .collect())
+    }
+
+    /// Get the tracks saved by the current user since `since` (inclusive), newest first.
+    ///
+    /// As the saved tracks are listed newest first, the pagination stops at the first track
+    /// saved before `since`, which makes incremental syncs of the library cheap.
+    ///
+    /// Under a deadline (see `Client::with_deadline`), the listing fails with
+    /// `ClientError::DeadlineExceeded` instead of returning the tracks fetched so far,
+    /// which would silently miss the older tracks saved since `since`.
+    pub async fn saved_tracks_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<SavedTrack>> {
+        let first_page = self
+            .current_user_saved_tracks_manual(Some(Market::FromToken), Some(50), None)
+            .await?;
+        let payload = &market_query();
+        let tracks = operation::complete(paging::collect_pages_while(
+            first_page.items,
+            first_page.next,
+            |url| async move {
+                let page = self
+                    .http_get::<Page<rspotify_model::SavedTrack>>(&url, payload)
+                    .await?;
+                anyhow::Ok((page.items, page.next))
+            },
+            |track: &rspotify_model::SavedTrack| track.added_at >= since,
+        ))
+        .await?;
+        Ok(tracks
+            .into_iter()
+            .filter_map(SavedTrack::try_from_saved_track)
+            .collect())
+    }
+
+    /// Save a track if it is not saved, remove it otherwise,
+    /// returning whether the track is saved afterwards. See `toggle_state`.
+    pub async fn toggle_saved_track(&self, track_id: TrackId<'_>) -> Result<bool> {
+        let track_id = &track_id;
+        toggle_state(
+            || async {
+                let contains = self
+                    .current_user_saved_tracks_contains([track_id.as_ref()])
+                    .await?;
+                anyhow::Ok(contains)
+            },
+            |save| async move {
+                if save {
+                    self.current_user_saved_tracks_add([track_id.as_ref()])
+                        .await?;
+                } else {
+                    self.current_user_saved_tracks_delete([track_id.as_ref()])
+                        .await?;
+                }
+                anyhow::Ok(())
+            },
+        )
+        .await
+    }
+
+    /// Stream the saved (liked) tracks of the current user, fetching their pages lazily
+    /// as the tracks are consumed. See `paging::page_stream`.
+    ///
+    /// Unavailable tracks are skipped.
+    pub fn saved_tracks_stream(&self) -> impl Stream<Item = Result<Track>> + '_ {
+        let first_url = format!("{SPOTIFY_API_ENDPOINT}/me/tracks?limit=50");
+        paging::page_stream(vec![], Some(first_url), move |url| async move {
+            let page = self
+                .http_get::<Page<rspotify_model::SavedTrack>>(&url, &market_query())
+                .await?;
+            let tracks = page
+                .items
+                .into_iter()
+                .filter_map(|t| Track::try_from_full_track(t.track))
+                .collect();
+            anyhow::Ok((tracks, page.next))
+        })
+    }
+
+    /// Stream the pages of a paginated collection starting from a pagination object
+    /// of its first page, fetching the next pages with the `payload` query parameters
+    /// lazily as the pages are consumed. See `paging::pages_stream`.
+    ///
+    /// Dropping the stream stops the pagination, e.g. once the wanted items are found.
+    pub fn page_stream<'a, T>(
+        &'a self,
+        first_page: Page<T>,
+        payload: Query<'a>,
+    ) -> impl Stream<Item = Result<Vec<T>>> + 'a
+    where
+        T: serde::de::DeserializeOwned + 'a,
+    {
+        paging::pages_stream(first_page.items, first_page.next, move |url| {
+            let payload = payload.clone();
+            async move {
+                let page = self.http_get::<Page<T>>(&url, &payload).await?;
+                anyhow::Ok((page.items, page.next))
+            }
+        })
+    }
+
+    /// Stream the pages of a paginated collection like `Client::page_stream`, prefetching up to
+    /// `prefetch` pages ahead of the consumer in a background task, e.g. to process a page while
+    /// the next pages are fetched. See `paging::prefetched_pages_stream`.
+    ///
+    /// The next pages are fetched with the query parameters of the next page's URL.
+    /// Dropping the stream aborts the outstanding requests.
+    pub fn page_stream_prefetched<T>(
+        self: &Arc<Self>,
+        first_page: Page<T>,
+        prefetch: usize,
+    ) -> impl Stream<Item = Result<Vec<T>>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let client = self.clone();
+        paging::prefetched_pages_stream(
+            first_page.items,
+            first_page.next,
+            first_page.total as usize,
+            prefetch,
+            move |url| {
+                let client = client.clone();
+                async move {
+                    let page = client.http_get::<Page<T>>(&url, &Query::new()).await?;
+                    anyhow::Ok((page.items, page.next))
+                }
+            },
+        )
+    }
+
+    /// Stream the pages of a cursor-based paginated collection starting from a pagination
+    /// object of its first page, fetching the next pages lazily as the pages are consumed.
+    /// See `Client::page_stream`.
+    pub fn cursor_page_stream<'a, T>(
+        &'a self,
+        first_page: rspotify_model::CursorBasedPage<T>,
+    ) -> impl Stream<Item = Result<Vec<T>>> + 'a
+    where
+        T: serde::de::DeserializeOwned + 'a,
+    {
+        paging::pages_stream(first_page.items, first_page.next, move |url| async move {
+            let page = self
+                .http_get::<rspotify_model::CursorBasedPage<T>>(&url, &Query::new())
+                .await?;
+            anyhow::Ok((page.items, page.next))
+        })
+    }
+
+    /// Get the number of saved (liked) tracks of the current user, without fetching them
+    pub async fn current_user_saved_tracks_count(&self) -> Result<usize> {
+        self.collection_total(&format!("{SPOTIFY_API_ENDPOINT}/me/tracks"))
+            .await
+    }
+
+    /// Get a windowed handle to the saved (liked) tracks of the current user,
+    /// whose pages of `page_size` tracks (at most 50) are fetched on demand.
+    ///
+    /// The windows are positioned by the saved tracks, including the unavailable ones,
+    /// which are skipped: a window may contain fewer tracks than its length.
+    pub fn saved_tracks_windowed(&self, page_size: usize) -> WindowedCollection<'_, Track> {
+        WindowedCollection::with_gaps(page_size.clamp(1, 50), move |offset, limit| {
+            Box::pin(async move {
+                let page = self
+                    .current_user_saved_tracks_manual(
+                        Some(Market::FromToken),
+                        Some(limit as u32),
+                        Some(offset as u32),
+                    )
+                    .await?;
+                let tracks = page
+                    .items
+                    .into_iter()
+                    .map(|t| Track::try_from_full_track(t.track))
+                    .collect();
+                anyhow::Ok((tracks, page.total as usize))
+            })
+        })
+    }
+
+    /// Get the recently played tracks of the current user
     pub async fn current_user_recently_played_tracks(&self) -> Result<Vec<Track>> {
         let first_page = self.current_user_recently_played(Some(50), None).await?;
 
         let play_histories = self.all_cursor_based_paging_items(first_page).await?;
 
-        // de-duplicate the tracks returned from the recently-played API
-        let mut tracks = Vec::<Track>::new();
-        for history in play_histories {
-            if !tracks.iter().any(|t| t.name == history.track.name) {
-                if let Some(track) = Track::try_from_full_track(history.track) {
-                    tracks.push(track);
-                }
-            }
-        }
-        Ok(tracks)
+        // de-duplicate the tracks returned from the recently-played API, keeping the most
+        // recent plays. Local files have no id, so they are compared by their names.
+        let play_histories = crate::utils::dedup_by_key(play_histories, |history| {
+            history
+                .track
+                .id
+                .clone()
+                .ok_or_else(|| history.track.name.clone())
+        });
+        Ok(play_histories
+            .into_iter()
+            .filter_map(|history| Track::try_from_full_track(history.track))
+            .collect())
     }
 
-    /// Get the top tracks of the current user
-    pub async fn current_user_top_tracks(&self) -> Result<Vec<Track>> {
-        let first_page = self
-            .current_user_top_tracks_manual(None, Some(50), None)
+    /// Get a page of at most `limit` (default and maximum: 50) plays of the current user's
+    /// listening history, newest first, along with the cursor of the next page.
+    ///
+    /// Without `cursor`, the most recent plays are returned. See `RecentCursor::next`
+    /// for the returned cursor, which is `None` if there is no older play.
+    pub async fn recently_played(
+        &self,
+        limit: Option<usize>,
+        cursor: Option<RecentCursor>,
+    ) -> Result<(Vec<PlayHistory>, Option<RecentCursor>)> {
+        let timestamp = |ms: i64| {
+            chrono::DateTime::from_timestamp_millis(ms)
+                .with_context(|| format!("invalid cursor timestamp: {ms}"))
+        };
+        let time_limit = match cursor {
+            None => None,
+            Some(RecentCursor::Before(ms)) => Some(TimeLimits::Before(timestamp(ms)?)),
+            Some(RecentCursor::After(ms)) => Some(TimeLimits::After(timestamp(ms)?)),
+        };
+        let limit = limit
+            .unwrap_or(LIBRARY_ITEMS_LIMIT)
+            .clamp(1, LIBRARY_ITEMS_LIMIT);
+
+        let page = self
+            .current_user_recently_played(Some(limit as u32), time_limit)
             .await?;
+        let played_at = page.items.iter().map(|h| h.played_at).collect::<Vec<_>>();
+        let next = RecentCursor::next(cursor, &played_at, page.next.is_some());
+        let plays = page
+            .items
+            .into_iter()
+            .filter_map(PlayHistory::try_from_play_history)
+            .collect();
+        Ok((plays, next))
+    }
 
-        let tracks = self.all_paging_items(first_page, &Query::new()).await?;
+    /// Get the top tracks of the current user, computed over the time range `range`.
+    ///
+    /// Only the first `limit` tracks are fetched if `limit` is specified,
+    /// otherwise all top tracks (at most 99) are fetched.
+    pub async fn current_user_top_tracks(
+        &self,
+        range: TimeRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<Track>> {
+        let tracks = paging::collect_offsets(
+            limit,
+            TOP_ITEMS_LIMIT,
+            TOP_ITEMS_MAX_OFFSET,
+            |offset, limit| async move {
+                let page = self
+                    .current_user_top_tracks_manual(
+                        Some(range),
+                        Some(limit as u32),
+                        Some(offset as u32),
+                    )
+                    .await?;
+                anyhow::Ok((page.items, page.total as usize))
+            },
+        )
+        .await?;
         Ok(tracks
             .into_iter()
             .filter_map(Track::try_from_full_track)
             .collect())
     }
 
+    /// Get the top artists of the current user, computed over the time range `range`.
+    ///
+    /// Only the first `limit` artists are fetched if `limit` is specified,
+    /// otherwise all top artists (at most 99) are fetched.
+    pub async fn current_user_top_artists(
+        &self,
+        range: TimeRange,
+        limit: Option<usize>,
+    ) -> Result<Vec<Artist>> {
+        let artists = paging::collect_offsets(
+            limit,
+            TOP_ITEMS_LIMIT,
+            TOP_ITEMS_MAX_OFFSET,
+            |offset, limit| async move {
+                let page = self
+                    .current_user_top_artists_manual(
+                        Some(range),
+                        Some(limit as u32),
+                        Some(offset as u32),
+                    )
+                    .await?;
+                anyhow::Ok((page.items, page.total as usize))
+            },
+        )
+        .await?;
+        Ok(artists.into_iter().map(Artist::from).collect())
+    }
+
+    /// Get the number of playlists of the current user, without fetching them
+    pub async fn playlists_count(&self) -> Result<usize> {
+        self.collection_total(&format!("{SPOTIFY_API_ENDPOINT}/me/playlists"))
+            .await
+    }
+
     /// Get all playlists of the current user
     pub async fn current_user_playlists(&self) -> Result<Vec<Playlist>> {
+        self.current_user_playlists_with(&FetchOptions::default())
+            .await
+    }
+
+    /// Get the playlists of the current user, paginated with the fetch options
+    pub async fn current_user_playlists_with(
+        &self,
+        options: &FetchOptions,
+    ) -> Result<Vec<Playlist>> {
+        let page_size = options.page_size(LIBRARY_ITEMS_LIMIT as u32);
+        let limit = page_size.to_string();
         // TODO: this should use `rspotify::current_user_playlists_manual` API instead of `internal_call`
         // See: https://github.com/ramsayleung/rspotify/issues/459
-        let first_page = self
-            .http_get::<Page<SimplifiedPlaylist>>(
+        let first_page = if self.is_quirk_enabled(Quirk::CurrentUserPlaylistsRequest) {
+            self.http_get::<Page<SimplifiedPlaylist>>(
                 &format!("{SPOTIFY_API_ENDPOINT}/me/playlists"),
-                &Query::from([("limit", "50")]),
+                &Query::from([("limit", limit.as_str())]),
             )
+            .await?
+        } else {
+            self.current_user_playlists_manual(Some(page_size), None)
+                .await?
+        };
+
+        let playlists = self
+            .paging_items(first_page, &Query::new(), options)
             .await?;
-        // let first_page = self
-        //     .current_user_playlists_manual(Some(50), None)
-        //     .await?;
+        Ok(playlists.into_iter().map(|p| p.into()).collect())
+    }
+
+    /// Get the public playlists of a user.
+    ///
+    /// The user's private playlists are not listed.
+    pub async fn user_playlists(&self, user_id: UserId<'_>) -> Result<Vec<Playlist>> {
+        let first_page = if self.is_quirk_enabled(Quirk::UserPlaylistsRequest) {
+            self.http_get::<Page<SimplifiedPlaylist>>(
+                &format!("{SPOTIFY_API_ENDPOINT}/users/{}/playlists", user_id.id()),
+                &Query::from([("limit", "50")]),
+            )
+            .await?
+        } else {
+            self.user_playlists_manual(user_id, Some(50), None).await?
+        };
 
         let playlists = self.all_paging_items(first_page, &Query::new()).await?;
         Ok(playlists.into_iter().map(|p| p.into()).collect())
     }
 
-    /// Get all followed artists of the current user
-    pub async fn current_user_followed_artists(&self) -> Result<Vec<Artist>> {
+    /// Get the playlists of the current user matching a filter
+    pub async fn current_user_playlists_filtered(
+        &self,
+        filter: PlaylistFilter,
+    ) -> Result<Vec<Playlist>> {
+        let playlists = self.current_user_playlists().await?;
+        if filter == PlaylistFilter::All {
+            return Ok(playlists);
+        }
+
+        let user_id = self.current_user_id().await?;
+        Ok(playlists
+            .into_iter()
+            .filter(|p| filter.matches(p, &user_id))
+            .collect())
+    }
+
+    /// Follow artists as the current user
+    pub async fn follow_artists(&self, artist_ids: Vec<ArtistId<'_>>) -> Result<()> {
+        let ids = artist_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.update_following("artist", &ids, true).await
+    }
+
+    /// Unfollow artists as the current user.
+    /// Unfollowing an artist which is not followed succeeds.
+    pub async fn unfollow_artists(&self, artist_ids: Vec<ArtistId<'_>>) -> Result<()> {
+        let ids = artist_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.update_following("artist", &ids, false).await
+    }
+
+    /// Check if the current user follows artists,
+    /// returning a flag for each artist in the same order
+    pub async fn is_following_artists(&self, artist_ids: &[ArtistId<'_>]) -> Result<Vec<bool>> {
+        let ids = artist_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.check_following("artist", &ids).await
+    }
+
+    /// Follow an artist if it is not followed, unfollow it otherwise,
+    /// returning whether the artist is followed afterwards. See `toggle_state`.
+    pub async fn toggle_follow_artist(&self, artist_id: ArtistId<'_>) -> Result<bool> {
+        let ids = [artist_id.id()];
+        toggle_state(
+            || self.check_following("artist", &ids),
+            |follow| self.update_following("artist", &ids, follow),
+        )
+        .await
+    }
+
+    /// Follow users as the current user
+    pub async fn follow_users(&self, user_ids: Vec<UserId<'_>>) -> Result<()> {
+        let ids = user_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.update_following("user", &ids, true).await
+    }
+
+    /// Unfollow users as the current user.
+    /// Unfollowing a user who is not followed succeeds.
+    pub async fn unfollow_users(&self, user_ids: Vec<UserId<'_>>) -> Result<()> {
+        let ids = user_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.update_following("user", &ids, false).await
+    }
+
+    /// Check if the current user follows users,
+    /// returning a flag for each user in the same order
+    pub async fn is_following_users(&self, user_ids: &[UserId<'_>]) -> Result<Vec<bool>> {
+        let ids = user_ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+        self.check_following("user", &ids).await
+    }
+
+    /// Get the followed artists of the current user, in the API order or sorted by `sort`.
+    ///
+    /// Only the first `limit` artists (in the API order) are fetched if `limit` is specified,
+    /// then they are sorted.
+    pub async fn current_user_followed_artists(
+        &self,
+        limit: Option<usize>,
+        sort: Option<ArtistSort>,
+    ) -> Result<Vec<Artist>> {
+        let artists = self.current_user_followed_full_artists(limit).await?;
+
+        // converts `rspotify_model::FullArtist` into `state::Artist`
+        let mut artists = artists.into_iter().map(Artist::from).collect::<Vec<_>>();
+        if let Some(sort) = sort {
+            sort.sort(&mut artists);
+        }
+        Ok(artists)
+    }
+
+    /// Get the first `limit` (all if `None`) followed artists of the current user
+    /// as `rspotify_model::FullArtist`, which includes the artists' statistics
+    async fn current_user_followed_full_artists(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Vec<rspotify_model::FullArtist>> {
+        let page_size = limit.map_or(LIBRARY_ITEMS_LIMIT, |limit| {
+            limit.clamp(1, LIBRARY_ITEMS_LIMIT)
+        });
         let first_page = self
             .spotify
-            .current_user_followed_artists(None, None)
+            .current_user_followed_artists(None, Some(page_size as u32))
             .await?;
 
         // followed artists pagination is handled different from
         // other paginations. The endpoint uses cursor-based pagination.
-        let mut artists = first_page.items;
-        let mut maybe_next = first_page.next;
-        while let Some(url) = maybe_next {
-            let mut next_page = self
+        paging::collect_pages_limited(first_page.items, first_page.next, limit, |url| async move {
+            let page = self
                 .http_get::<rspotify_model::CursorPageFullArtists>(&url, &Query::new())
                 .await?
                 .artists;
-            artists.append(&mut next_page.items);
-            maybe_next = next_page.next;
+            anyhow::Ok((page.items, page.next))
+        })
+        .await
+    }
+
+    /// Append a snapshot of the statistics (followers, popularity) of all followed artists
+    /// to the time-series store at `store_path`, returning the number of appended samples.
+    ///
+    /// Artists unfollowed since the previous snapshot keep their history
+    /// and are recorded as inactive.
+    pub async fn snapshot_followed_artist_stats(
+        &self,
+        store_path: impl AsRef<Path>,
+    ) -> Result<usize> {
+        let store = TimeSeries::<ArtistStats>::new(store_path);
+        let artists = self.current_user_followed_full_artists(None).await?;
+
+        let timestamp = chrono::Utc::now();
+        let mut samples = artists
+            .into_iter()
+            .map(|a| Sample {
+                timestamp,
+                key: a.id.uri(),
+                value: ArtistStats {
+                    followers: a.followers.total,
+                    popularity: a.popularity,
+                    active: true,
+                },
+            })
+            .collect::<Vec<_>>();
+        for latest in store.latest()? {
+            if latest.value.active && !samples.iter().any(|s| s.key == latest.key) {
+                samples.push(Sample {
+                    timestamp,
+                    key: latest.key,
+                    value: ArtistStats {
+                        active: false,
+                        ..latest.value
+                    },
+                });
+            }
         }
 
-        // converts `rspotify_model::FullArtist` into `state::Artist`
-        Ok(artists.into_iter().map(|a| a.into()).collect())
+        store.append(&samples)?;
+        Ok(samples.len())
+    }
+
+    /// Compute the growth of an artist's statistics over a time window ending now,
+    /// based on the snapshots appended by `snapshot_followed_artist_stats` to the store at `store_path`.
+    ///
+    /// Returns `None` if the artist has no snapshot within the window.
+    pub fn artist_growth(
+        &self,
+        store_path: impl AsRef<Path>,
+        artist_id: ArtistId<'_>,
+        window: chrono::Duration,
+    ) -> Result<Option<ArtistGrowth>> {
+        let store = TimeSeries::<ArtistStats>::new(store_path);
+        let samples = store.history(&artist_id.uri(), chrono::Utc::now() - window)?;
+        Ok(ArtistGrowth::from_samples(&samples))
     }
 
     /// Get all saved albums of the current user
     pub async fn current_user_saved_albums(&self) -> Result<Vec<Album>> {
+        self.current_user_saved_albums_with(&FetchOptions::default())
+            .await
+    }
+
+    /// Get the saved albums of the current user in the options' market,
+    /// paginated with the fetch options
+    pub async fn current_user_saved_albums_with(
+        &self,
+        options: &FetchOptions,
+    ) -> Result<Vec<Album>> {
         let first_page = self
-            .current_user_saved_albums_manual(Some(Market::FromToken), Some(50), None)
+            .current_user_saved_albums_manual(
+                Some(options.market.unwrap_or(Market::FromToken)),
+                Some(options.page_size(LIBRARY_ITEMS_LIMIT as u32)),
+                None,
+            )
             .await?;
 
-        let albums = self.all_paging_items(first_page, &Query::new()).await?;
+        let albums = self
+            .paging_items(first_page, &market_query_for(options.market), options)
+            .await?;
 
         // converts `rspotify_model::SavedAlbum` into `state::Album`
         Ok(albums.into_iter().map(|a| a.album.into()).collect())
     }
 
-    /// Get all albums of an artist
-    pub async fn artist_albums(&self, artist_id: ArtistId<'_>) -> Result<Vec<Album>> {
-        let payload = market_query();
+    /// Get the number of saved albums of the current user, without fetching them
+    pub async fn saved_albums_count(&self) -> Result<usize> {
+        self.collection_total(&format!("{SPOTIFY_API_ENDPOINT}/me/albums"))
+            .await
+    }
 
-        let mut singles = {
-            let first_page = self
-                .artist_albums_manual(
-                    artist_id.as_ref(),
-                    Some(rspotify_model::AlbumType::Single),
-                    Some(Market::FromToken),
-                    Some(50),
-                    None,
-                )
-                .await?;
-            self.all_paging_items(first_page, &payload).await
-        }?;
-        let mut albums = {
-            let first_page = self
-                .artist_albums_manual(
-                    artist_id.as_ref(),
-                    Some(rspotify_model::AlbumType::Album),
-                    Some(Market::FromToken),
-                    Some(50),
-                    None,
-                )
+    /// Stream the saved albums of the current user, fetching their pages lazily
+    /// as the albums are consumed. See `paging::page_stream`.
+    pub fn saved_albums_stream(&self) -> impl Stream<Item = Result<Album>> + '_ {
+        let first_url = format!("{SPOTIFY_API_ENDPOINT}/me/albums?limit=50");
+        paging::page_stream(vec![], Some(first_url), move |url| async move {
+            let page = self
+                .http_get::<Page<rspotify_model::SavedAlbum>>(&url, &market_query())
                 .await?;
-            self.all_paging_items(first_page, &payload).await
-        }?;
-        albums.append(&mut singles);
+            let albums = page.items.into_iter().map(|a| a.album.into()).collect();
+            anyhow::Ok((albums, page.next))
+        })
+    }
 
-        // converts `rspotify_model::SimplifiedAlbum` into `state::Album`
-        let albums = albums
-            .into_iter()
-            .filter_map(Album::try_from_simplified_album)
-            .collect();
-        Ok(self.process_artist_albums(albums))
+    /// Save albums into the current user's library
+    pub async fn save_albums(&self, album_ids: &[AlbumId<'_>]) -> Result<()> {
+        paging::map_chunks(album_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            self.current_user_saved_albums_add(chunk).await?;
+            anyhow::Ok(Vec::<()>::new())
+        })
+        .await?;
+        Ok(())
     }
 
-    /// Get recommendation (radio) tracks based on a seed
-    pub async fn radio_tracks(&self, seed_uri: String) -> Result<Vec<Track>> {
-        let session = self.session().await;
+    /// Remove albums from the current user's library
+    pub async fn remove_saved_albums(&self, album_ids: &[AlbumId<'_>]) -> Result<()> {
+        paging::map_chunks(album_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            self.current_user_saved_albums_delete(chunk).await?;
+            anyhow::Ok(Vec::<()>::new())
+        })
+        .await?;
+        Ok(())
+    }
 
-        // Get an autoplay URI from the seed URI.
-        // The return URI is a Spotify station's URI
-        let autoplay_query_url = format!("hm://autoplay-enabled/query?uri={seed_uri}");
-        let response = session
-            .mercury()
-            .get(autoplay_query_url)
-            .await
-            .map_err(|_| anyhow::anyhow!("Failed to get autoplay URI: got a Mercury error"))?;
-        if response.status_code != 200 {
-            anyhow::bail!(
-                "Failed to get autoplay URI: got non-OK status code: {}",
-                response.status_code
-            );
-        }
-        let autoplay_uri = String::from_utf8(response.payload[0].to_vec())?;
+    /// Save an album if it is not saved, remove it otherwise,
+    /// returning whether the album is saved afterwards. See `toggle_state`.
+    pub async fn toggle_saved_album(&self, album_id: AlbumId<'_>) -> Result<bool> {
+        let ids = &[album_id];
+        toggle_state(
+            || self.saved_albums_contains(ids),
+            |save| async move {
+                if save {
+                    self.save_albums(ids).await
+                } else {
+                    self.remove_saved_albums(ids).await
+                }
+            },
+        )
+        .await
+    }
 
-        // Retrieve radio's data based on the autoplay URI
-        let radio_query_url = format!("hm://radio-apollo/v3/stations/{autoplay_uri}");
-        let response = session.mercury().get(radio_query_url).await.map_err(|_| {
-            anyhow::anyhow!("Failed to get radio data of {autoplay_uri}: got a Mercury error")
-        })?;
-        if response.status_code != 200 {
-            anyhow::bail!(
-                "Failed to get radio data of {autoplay_uri}: got non-OK status code: {}",
-                response.status_code
-            );
-        }
+    /// Check if albums are saved in the current user's library,
+    /// returning a flag for each album in the same order
+    pub async fn saved_albums_contains(&self, album_ids: &[AlbumId<'_>]) -> Result<Vec<bool>> {
+        paging::map_chunks(album_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            let contains = self.current_user_saved_albums_contains(chunk).await?;
+            anyhow::Ok(contains)
+        })
+        .await
+    }
 
-        #[derive(Debug, Deserialize)]
-        struct TrackData {
-            original_gid: String,
-        }
-        #[derive(Debug, Deserialize)]
-        struct RadioStationResponse {
-            tracks: Vec<TrackData>,
+    /// Check if items of different types are saved in the current user's library,
+    /// returning a flag for each item keyed by the item's URI.
+    ///
+    /// The items of each type are checked concurrently. The ids of a batch rejected
+    /// by Spotify (e.g. because of an unknown id) are considered unsaved.
+    pub async fn library_contains(
+        &self,
+        items: Vec<LibraryItemId<'_>>,
+    ) -> Result<HashMap<String, bool>> {
+        let (mut tracks, mut albums, mut shows) = (vec![], vec![], vec![]);
+        for item in &items {
+            match item {
+                LibraryItemId::Track(id) => tracks.push(id.as_ref()),
+                LibraryItemId::Album(id) => albums.push(id.as_ref()),
+                LibraryItemId::Show(id) => shows.push(id.as_ref()),
+            }
         }
-        // Parse a list consisting of IDs of tracks inside the radio station
-        let track_ids = serde_json::from_slice::<RadioStationResponse>(&response.payload[0])?
-            .tracks
-            .into_iter()
-            .filter_map(|t| TrackId::from_id(t.original_gid).ok());
 
-        // Retrieve tracks based on IDs
-        let tracks = self.tracks(track_ids, Some(Market::FromToken)).await?;
-        let tracks = tracks
-            .into_iter()
-            .filter_map(Track::try_from_full_track)
-            .collect();
+        let (tracks_saved, albums_saved, shows_saved) = tokio::try_join!(
+            contains_or_unsaved(&tracks, |chunk| async move {
+                anyhow::Ok(self.current_user_saved_tracks_contains(chunk).await?)
+            }),
+            contains_or_unsaved(&albums, |chunk| async move {
+                anyhow::Ok(self.current_user_saved_albums_contains(chunk).await?)
+            }),
+            contains_or_unsaved(&shows, |chunk| async move {
+                anyhow::Ok(self.check_users_saved_shows(chunk).await?)
+            }),
+        )?;
 
-        Ok(tracks)
+        // the items missing from Spotify's responses are considered unsaved
+        let mut saved = items
+            .iter()
+            .map(|item| (item.uri(), false))
+            .collect::<HashMap<_, _>>();
+        let uris = tracks
+            .iter()
+            .map(|id| id.uri())
+            .zip(tracks_saved)
+            .chain(albums.iter().map(|id| id.uri()).zip(albums_saved))
+            .chain(shows.iter().map(|id| id.uri()).zip(shows_saved));
+        saved.extend(uris);
+        Ok(saved)
     }
 
-    /// Search for items (tracks, artists, albums, playlists) matching a given query
-    pub async fn search(&self, query: &str) -> Result<SearchResults> {
-        let (track_result, artist_result, album_result, playlist_result) = tokio::try_join!(
-            self.search_specific_type(query, rspotify_model::SearchType::Track),
-            self.search_specific_type(query, rspotify_model::SearchType::Artist),
-            self.search_specific_type(query, rspotify_model::SearchType::Album),
-            self.search_specific_type(query, rspotify_model::SearchType::Playlist)
+    /// Export the current user's library (saved tracks and albums, followed artists
+    /// and playlists with their items) into a single serializable document, e.g. for backups.
+    ///
+    /// The categories are fetched concurrently, as are the items of at most
+    /// `MAX_CONCURRENT_REQUESTS` playlists at a time. Under a deadline, the export fails
+    /// with `ClientError::DeadlineExceeded` instead of silently missing items.
+    pub async fn export_library(&self) -> Result<LibraryExport> {
+        let export_playlists = async {
+            let playlists = self.current_user_playlists().await?;
+            paging::map_concurrently(playlists, MAX_CONCURRENT_REQUESTS, |playlist| async move {
+                let track_uris = self
+                    .playlist_item_uris(playlist.id.as_ref())
+                    .await?
+                    .into_iter()
+                    .flatten()
+                    .map(|item| item.stored_uri().to_string())
+                    .collect();
+                anyhow::Ok(PlaylistExport {
+                    playlist,
+                    track_uris,
+                })
+            })
+            .await
+        };
+
+        let (saved_tracks, saved_albums, followed_artists, playlists) = tokio::try_join!(
+            self.current_user_saved_tracks_detailed(),
+            self.current_user_saved_albums(),
+            self.current_user_followed_artists(None, None),
+            export_playlists,
         )?;
+        // never return a partial backup
+        operation::check()?;
+        tracing::info!(
+            "Exported a library of {} tracks, {} albums, {} artists and {} playlists",
+            saved_tracks.len(),
+            saved_albums.len(),
+            followed_artists.len(),
+            playlists.len()
+        );
 
-        let (tracks, artists, albums, playlists) = (
-            match track_result {
-                rspotify_model::SearchResult::Tracks(p) => p
-                    .items
-                    .into_iter()
-                    .filter_map(Track::try_from_full_track)
-                    .collect(),
-                _ => anyhow::bail!("expect a track search result"),
-            },
-            match artist_result {
-                rspotify_model::SearchResult::Artists(p) => {
-                    p.items.into_iter().map(|a| a.into()).collect()
-                }
-                _ => anyhow::bail!("expect an artist search result"),
-            },
-            match album_result {
-                rspotify_model::SearchResult::Albums(p) => p
-                    .items
-                    .into_iter()
-                    .filter_map(Album::try_from_simplified_album)
-                    .collect(),
-                _ => anyhow::bail!("expect an album search result"),
+        Ok(LibraryExport {
+            version: LIBRARY_EXPORT_VERSION,
+            saved_tracks,
+            saved_albums,
+            followed_artists,
+            playlists,
+        })
+    }
+
+    /// Restore a library export into the current user's library, skipping the items
+    /// already in the library. See `restore::plan_restore` for the planned writes.
+    ///
+    /// The restore is idempotent: restoring an export again has nothing left to write.
+    ///
+    /// With `RestoreMode::DryRun`, the writes are only planned and returned in the report.
+    pub async fn restore_library(
+        &self,
+        export: LibraryExport,
+        mode: RestoreMode,
+    ) -> Result<RestoreReport> {
+        let (tracks, albums, artists, playlists) = tokio::try_join!(
+            self.current_user_saved_tracks(),
+            self.current_user_saved_albums(),
+            self.current_user_followed_artists(None, None),
+            self.current_user_playlists(),
+        )?;
+        // never restore the items missing from a partially fetched library
+        operation::check()?;
+        let existing = tracks
+            .iter()
+            .map(|t| t.id.uri())
+            .chain(albums.iter().map(|a| a.id.uri()))
+            .chain(artists.iter().map(|a| a.id.uri()))
+            .collect();
+
+        let user_id = &self.current_user_id().await?;
+        let actions = restore::plan_restore(&export, user_id, &existing, &playlists);
+        restore::run_restore(actions, mode, |action| {
+            self.execute_restore_action(user_id, action)
+        })
+        .await
+    }
+
+    /// Execute a write planned by `restore::plan_restore`
+    async fn execute_restore_action(
+        &self,
+        user_id: &UserId<'static>,
+        action: &RestoreAction,
+    ) -> Result<()> {
+        match action {
+            RestoreAction::SaveTracks(ids) => {
+                paging::map_chunks(ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+                    self.current_user_saved_tracks_add(chunk).await?;
+                    anyhow::Ok(Vec::<()>::new())
+                })
+                .await?;
+            }
+            RestoreAction::SaveAlbums(ids) => self.save_albums(ids).await?,
+            RestoreAction::FollowArtists(ids) => self.follow_artists(ids.clone()).await?,
+            RestoreAction::CreatePlaylist {
+                name,
+                desc,
+                collaborative,
+                uris,
+            } => {
+                // the export doesn't record the playlists' visibility, they are restored as private
+                let playlist = self
+                    .create_new_playlist(user_id.clone(), name, false, *collaborative, desc)
+                    .await?;
+                // local files cannot be added to a playlist
+                let ids = uris
+                    .iter()
+                    .filter_map(|uri| playable_id_from_uri(uri))
+                    .collect::<Vec<_>>();
+                for chunk in ids.chunks(PLAYLIST_ITEMS_LIMIT) {
+                    operation::check()?;
+                    self.playlist_add_items(
+                        playlist.id.as_ref(),
+                        chunk.iter().map(|id| id.as_ref()),
+                        None,
+                    )
+                    .await?;
+                }
+            }
+            RestoreAction::FollowPlaylist(id) => self.follow_playlist(id.as_ref(), true).await?,
+        }
+        Ok(())
+    }
+
+    /// Get all saved shows of the current user
+    pub async fn current_user_saved_shows(&self) -> Result<Vec<Show>> {
+        #[derive(Debug, Deserialize)]
+        struct SavedShow {
+            show: Option<PodcastShow>,
+        }
+
+        let payload = &Query::new();
+        let first_page = self
+            .http_get::<PodcastPage<SavedShow>>(
+                &format!("{SPOTIFY_API_ENDPOINT}/me/shows?limit=50"),
+                payload,
+            )
+            .await?;
+
+        let shows = self.all_podcast_items(first_page, payload).await?;
+        Ok(shows
+            .into_iter()
+            .filter_map(|s| s.show)
+            .map(Show::from)
+            .collect())
+    }
+
+    /// Get the codes (ISO 3166-1 alpha-2) of the countries where Spotify is available
+    pub async fn available_markets(&self) -> Result<Vec<String>> {
+        #[derive(Debug, Deserialize)]
+        struct MarketsResponse {
+            markets: Vec<String>,
+        }
+
+        let response = self
+            .http_get::<MarketsResponse>(&format!("{SPOTIFY_API_ENDPOINT}/markets"), &Query::new())
+            .await?;
+        Ok(response.markets)
+    }
+
+    /// Check in which of the given markets (ISO 3166-1 alpha-2 country codes) a track is
+    /// available, returning a flag for each market.
+    ///
+    /// The availability is read from the track's available markets in a single request.
+    /// If Spotify omits them (e.g. for a relinked track), the track is requested
+    /// in each market to check if it is playable.
+    pub async fn track_availability(
+        &self,
+        track_id: TrackId<'_>,
+        markets: &[&str],
+    ) -> Result<HashMap<String, bool>> {
+        #[derive(Debug, Deserialize)]
+        struct PlayableTrack {
+            is_playable: Option<bool>,
+        }
+
+        let track = self.spotify.track(track_id.as_ref(), None).await?;
+        if !track.available_markets.is_empty() {
+            return Ok(markets
+                .iter()
+                .map(|&market| {
+                    let available = track
+                        .available_markets
+                        .iter()
+                        .any(|m| m.eq_ignore_ascii_case(market));
+                    (market.to_string(), available)
+                })
+                .collect());
+        }
+
+        let url = &format!("{SPOTIFY_API_ENDPOINT}/tracks/{}", track_id.id());
+        let available = paging::map_concurrently(
+            markets.to_vec(),
+            MAX_CONCURRENT_REQUESTS,
+            |market| async move {
+                let track = self
+                    .http_get::<PlayableTrack>(url, &Query::from([("market", market)]))
+                    .await?;
+                anyhow::Ok(track.is_playable.unwrap_or(false))
+            },
+        )
+        .await?;
+        Ok(markets
+            .iter()
+            .map(|market| market.to_string())
+            .zip(available)
+            .collect())
+    }
+
+    /// Get a show, available in the current user's market
+    pub async fn show(&self, show_id: ShowId<'_>) -> Result<Show> {
+        Ok(self.podcast_show(show_id).await?.into())
+    }
+
+    /// Get all episodes of a show available in the current user's market
+    pub async fn show_episodes(&self, show_id: ShowId<'_>) -> Result<Vec<Episode>> {
+        let show = self.podcast_show(show_id).await?;
+        Ok(self.show_with_episodes(show).await?.1)
+    }
+
+    /// Get an episode, available in the current user's market
+    pub async fn episode(&self, episode_id: EpisodeId<'_>) -> Result<Episode> {
+        let episode = self
+            .http_get::<PodcastEpisode>(
+                &format!("{SPOTIFY_API_ENDPOINT}/episodes/{}", episode_id.id()),
+                &market_query(),
+            )
+            .await?;
+        Ok(episode.into())
+    }
+
+    /// Get a show available in the current user's market, deserialized permissively
+    /// along with the first page of its episodes (see `PodcastShow`)
+    async fn podcast_show(&self, show_id: ShowId<'_>) -> Result<PodcastShow> {
+        self.http_get::<PodcastShow>(
+            &format!("{SPOTIFY_API_ENDPOINT}/shows/{}", show_id.id()),
+            &market_query(),
+        )
+        .await
+    }
+
+    /// Get all episodes of a show starting from the first page of the show's episodes,
+    /// converting the show along with its episodes
+    async fn show_with_episodes(&self, mut show: PodcastShow) -> Result<(Show, Vec<Episode>)> {
+        let episodes = match show.episodes.take() {
+            Some(first_page) => self.all_podcast_items(first_page, &market_query()).await?,
+            None => vec![],
+        };
+        let show = Show::from(show);
+
+        // the show's episodes don't include their show
+        let episodes = episodes
+            .into_iter()
+            .map(|e| Episode {
+                show_name: show.name.clone(),
+                ..e.into()
+            })
+            .collect();
+        Ok((show, episodes))
+    }
+
+    /// Get an episode with the current user's playback progress, which requires
+    /// the `user-read-playback-position` scope
+    pub async fn episode_with_progress(&self, episode_id: EpisodeId<'_>) -> Result<Episode> {
+        let episode = self.episode(episode_id).await?;
+        if episode.resume_point.is_none() {
+            anyhow::bail!(
+                "no playback progress for the episode {}: the user-read-playback-position scope \
+                 is required",
+                episode.id
+            );
+        }
+        Ok(episode)
+    }
+
+    /// Save shows into the current user's library
+    pub async fn save_shows(&self, show_ids: &[ShowId<'_>]) -> Result<()> {
+        paging::map_chunks(show_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            // `Client::save_shows` shadows the `OAuthClient` method
+            self.spotify.save_shows(chunk).await?;
+            anyhow::Ok(Vec::<()>::new())
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Remove shows from the current user's library
+    pub async fn remove_saved_shows(&self, show_ids: &[ShowId<'_>]) -> Result<()> {
+        paging::map_chunks(show_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            self.remove_users_saved_shows(chunk, Some(Market::FromToken))
+                .await?;
+            anyhow::Ok(Vec::<()>::new())
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Check if shows are saved in the current user's library,
+    /// returning a flag for each show in the same order
+    pub async fn saved_shows_contains(&self, show_ids: &[ShowId<'_>]) -> Result<Vec<bool>> {
+        paging::map_chunks(show_ids, LIBRARY_ITEMS_LIMIT, |chunk| async move {
+            let contains = self.check_users_saved_shows(chunk).await?;
+            anyhow::Ok(contains)
+        })
+        .await
+    }
+
+    /// Get all saved episodes of the current user
+    pub async fn current_user_saved_episodes(&self) -> Result<Vec<Episode>> {
+        #[derive(Debug, Deserialize)]
+        struct SavedEpisode {
+            episode: Option<PodcastEpisode>,
+        }
+
+        // TODO: this should use a `rspotify` API once saved episodes are supported
+        let first_page = self
+            .http_get::<PodcastPage<SavedEpisode>>(
+                &format!("{SPOTIFY_API_ENDPOINT}/me/episodes"),
+                &Query::from([("limit", "50"), ("market", "from_token")]),
+            )
+            .await?;
+
+        let episodes = self.all_podcast_items(first_page, &market_query()).await?;
+        Ok(episodes
+            .into_iter()
+            .filter_map(|e| e.episode)
+            .map(Episode::from)
+            .collect())
+    }
+
+    /// Save episodes into the current user's library
+    pub async fn save_episodes(&self, episode_ids: &[EpisodeId<'_>]) -> Result<()> {
+        self.update_saved_episodes(episode_ids, reqwest::Method::PUT)
+            .await
+    }
+
+    /// Remove episodes from the current user's library
+    pub async fn remove_saved_episodes(&self, episode_ids: &[EpisodeId<'_>]) -> Result<()> {
+        self.update_saved_episodes(episode_ids, reqwest::Method::DELETE)
+            .await
+    }
+
+    /// Check if episodes are saved in the current user's library,
+    /// returning a flag for each episode in the same order
+    pub async fn saved_episodes_contains(
+        &self,
+        episode_ids: &[EpisodeId<'_>],
+    ) -> Result<Vec<bool>> {
+        let url = format!("{SPOTIFY_API_ENDPOINT}/me/episodes/contains");
+        paging::map_chunks(episode_ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+            let url = &url;
+            async move {
+                let ids = chunk.iter().map(|id| id.id()).collect::<Vec<_>>().join(",");
+                self.http_get::<Vec<bool>>(url, &Query::from([("ids", ids.as_str())]))
+                    .await
+            }
+        })
+        .await
+    }
+
+    /// Save (`PUT`) or remove (`DELETE`) episodes of the current user's library, in batches
+    async fn update_saved_episodes(
+        &self,
+        episode_ids: &[EpisodeId<'_>],
+        method: reqwest::Method,
+    ) -> Result<()> {
+        let url = format!("{SPOTIFY_API_ENDPOINT}/me/episodes");
+        paging::map_chunks(episode_ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+            let (method, url) = (method.clone(), &url);
+            async move {
+                let ids = chunk.iter().map(|id| id.id()).collect::<Vec<_>>().join(",");
+                self.http_send(method, url, &Query::from([("ids", ids.as_str())]))
+                    .await?;
+                anyhow::Ok(Vec::<()>::new())
+            }
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Get all saved audiobooks of the current user.
+    ///
+    /// Fails with `ClientError::FeatureUnavailableInMarket` if audiobooks are unavailable
+    /// in the user's market, as do the other audiobooks methods.
+    pub async fn current_user_saved_audiobooks(&self) -> Result<Vec<Audiobook>> {
+        #[derive(Debug, Deserialize)]
+        struct Person {
+            name: String,
+        }
+        #[derive(Debug, Deserialize)]
+        struct SimplifiedAudiobook {
+            id: String,
+            name: String,
+            authors: Vec<Person>,
+            narrators: Vec<Person>,
+            total_chapters: u32,
+        }
+
+        let fetch_page = |url: String| async move {
+            let text = self
+                .audiobooks_request(reqwest::Method::GET, &url, &Query::new())
+                .await?;
+            let page = serde_json::from_str::<Page<SimplifiedAudiobook>>(&text)?;
+            anyhow::Ok((page.items, page.next))
+        };
+        let (first_items, first_next) =
+            fetch_page(format!("{SPOTIFY_API_ENDPOINT}/me/audiobooks?limit=50")).await?;
+        let audiobooks = paging::collect_pages(first_items, first_next, fetch_page).await?;
+
+        let names = |people: Vec<Person>| people.into_iter().map(|p| p.name).collect();
+        Ok(audiobooks
+            .into_iter()
+            .map(|a| Audiobook {
+                id: a.id,
+                name: a.name,
+                authors: names(a.authors),
+                narrators: names(a.narrators),
+                total_chapters: a.total_chapters,
+            })
+            .collect())
+    }
+
+    /// Save audiobooks, given by their Spotify ids, into the current user's library
+    pub async fn save_audiobooks(&self, audiobook_ids: &[&str]) -> Result<()> {
+        self.update_saved_audiobooks(audiobook_ids, reqwest::Method::PUT)
+            .await
+    }
+
+    /// Remove audiobooks, given by their Spotify ids, from the current user's library
+    pub async fn remove_saved_audiobooks(&self, audiobook_ids: &[&str]) -> Result<()> {
+        self.update_saved_audiobooks(audiobook_ids, reqwest::Method::DELETE)
+            .await
+    }
+
+    /// Save (`PUT`) or remove (`DELETE`) audiobooks of the current user's library, in batches
+    async fn update_saved_audiobooks(
+        &self,
+        audiobook_ids: &[&str],
+        method: reqwest::Method,
+    ) -> Result<()> {
+        let url = format!("{SPOTIFY_API_ENDPOINT}/me/audiobooks");
+        paging::map_chunks(audiobook_ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+            let (method, url) = (method.clone(), &url);
+            async move {
+                let ids = chunk.join(",");
+                self.audiobooks_request(method, url, &Query::from([("ids", ids.as_str())]))
+                    .await?;
+                anyhow::Ok(Vec::<()>::new())
+            }
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Get all albums and singles of an artist
+    pub async fn artist_albums(&self, artist_id: ArtistId<'_>) -> Result<Vec<Album>> {
+        self.artist_albums_with(artist_id, &ArtistAlbumsOptions::default())
+            .await
+    }
+
+    /// Get the albums of an artist in the groups of the options, each album tagged with
+    /// its group, newest first. At most `limit_per_group` (all if unset) albums are fetched
+    /// for each group, which bounds the often long lists of albums an artist appears on.
+    pub async fn artist_albums_with(
+        &self,
+        artist_id: ArtistId<'_>,
+        options: &ArtistAlbumsOptions,
+    ) -> Result<Vec<Album>> {
+        let payload = &market_query_for(options.market);
+        let limit_per_group = options.limit_per_group;
+        let page_size = limit_per_group.map_or(50, |limit| limit.clamp(1, 50));
+
+        let mut albums = vec![];
+        for group in options.groups.groups() {
+            let first_page = self
+                .artist_albums_manual(
+                    artist_id.as_ref(),
+                    Some(group.album_type()),
+                    Some(options.market.unwrap_or(Market::FromToken)),
+                    Some(page_size as u32),
+                    None,
+                )
+                .await?;
+            let items = paging::collect_pages_limited(
+                first_page.items,
+                first_page.next,
+                limit_per_group,
+                |url| async move {
+                    let page = self
+                        .http_get::<Page<rspotify_model::SimplifiedAlbum>>(&url, payload)
+                        .await?;
+                    anyhow::Ok((page.items, page.next))
+                },
+            )
+            .await?;
+
+            // converts `rspotify_model::SimplifiedAlbum` into `state::Album`
+            albums.extend(
+                items
+                    .into_iter()
+                    .filter_map(Album::try_from_simplified_album)
+                    .map(|album| Album {
+                        group: Some(group),
+                        ..album
+                    }),
+            );
+        }
+
+        Ok(Self::process_artist_albums(albums, options))
+    }
+
+    /// Get the albums of the current user's followed artists released after `since`,
+    /// newest first. An album of multiple followed artists is listed once.
+    ///
+    /// The albums of at most `ARTIST_ALBUMS_CONCURRENCY` artists are fetched concurrently,
+    /// the rate-limited requests are retried after a backoff.
+    pub async fn new_releases_from_followed(&self, since: chrono::NaiveDate) -> Result<Vec<Album>> {
+        let artists = self.current_user_followed_artists(None, None).await?;
+        let albums =
+            paging::map_concurrently(artists, ARTIST_ALBUMS_CONCURRENCY, |artist| async move {
+                self.artist_albums(artist.id.as_ref()).await
+            })
+            .await?;
+
+        let mut albums = albums
+            .into_iter()
+            .flatten()
+            .filter(|a| a.released_after(since))
+            .collect::<Vec<_>>();
+        albums = crate::utils::dedup_by_key(albums, |a| a.id.clone());
+        albums.sort_by_key(|a| std::cmp::Reverse(a.release_day()));
+        Ok(albums)
+    }
+
+    /// Get recommendation (radio) tracks based on a seed
+    pub async fn radio_tracks(&self, seed_uri: String) -> Result<Vec<Track>> {
+        operation::check()?;
+        let session = self.session().await;
+
+        // Get an autoplay URI from the seed URI.
+        // The return URI is a Spotify station's URI
+        let autoplay_query_url = format!("hm://autoplay-enabled/query?uri={seed_uri}");
+        let response = session
+            .mercury()
+            .get(autoplay_query_url)
+            .await
+            .map_err(|_| anyhow::anyhow!("Failed to get autoplay URI: got a Mercury error"))?;
+        if response.status_code != 200 {
+            anyhow::bail!(
+                "Failed to get autoplay URI: got non-OK status code: {}",
+                response.status_code
+            );
+        }
+        let autoplay_uri = String::from_utf8(response.payload[0].to_vec())?;
+
+        // Retrieve radio's data based on the autoplay URI
+        operation::check()?;
+        let radio_query_url = format!("hm://radio-apollo/v3/stations/{autoplay_uri}");
+        let response = session.mercury().get(radio_query_url).await.map_err(|_| {
+            anyhow::anyhow!("Failed to get radio data of {autoplay_uri}: got a Mercury error")
+        })?;
+        if response.status_code != 200 {
+            anyhow::bail!(
+                "Failed to get radio data of {autoplay_uri}: got non-OK status code: {}",
+                response.status_code
+            );
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct TrackData {
+            original_gid: String,
+        }
+        #[derive(Debug, Deserialize)]
+        struct RadioStationResponse {
+            tracks: Vec<TrackData>,
+        }
+        // Parse a list consisting of IDs of tracks inside the radio station
+        let track_ids = serde_json::from_slice::<RadioStationResponse>(&response.payload[0])?
+            .tracks
+            .into_iter()
+            .filter_map(|t| TrackId::from_id(t.original_gid).ok())
+            .collect();
+
+        // Retrieve tracks based on IDs
+        self.tracks_batch(track_ids, Some(Market::FromToken)).await
+    }
+
+    /// Get multiple tracks by their ids, fetched in batches and listed in the ids' order
+    pub async fn tracks_batch(
+        &self,
+        ids: Vec<TrackId<'_>>,
+        market: Option<Market>,
+    ) -> Result<Vec<Track>> {
+        let tracks = paging::fetch_batches(&ids, TRACKS_BATCH_LIMIT, |batch| async move {
+            let tracks = self.spotify.tracks(batch, market).await?;
+            anyhow::Ok(tracks)
+        })
+        .await?;
+        Ok(tracks
+            .into_iter()
+            .filter_map(Track::try_from_full_track)
+            .collect())
+    }
+
+    /// Search for items (tracks, artists, albums, playlists) matching a given query,
+    /// either a plain string or a `SearchQuery` with field filters
+    pub async fn search(&self, query: impl Into<SearchQuery>) -> Result<SearchResults> {
+        let query = query.into().to_string();
+        self.search_with(&query, &SearchOptions::default()).await
+    }
+
+    /// Search for items (tracks, artists, albums, playlists) matching a given query,
+    /// with the options applying to each type of items
+    pub async fn search_with(&self, query: &str, options: &SearchOptions) -> Result<SearchResults> {
+        let (track_result, artist_result, album_result, playlist_result) = tokio::try_join!(
+            self.search_specific_type_with(query, rspotify_model::SearchType::Track, options),
+            self.search_specific_type_with(query, rspotify_model::SearchType::Artist, options),
+            self.search_specific_type_with(query, rspotify_model::SearchType::Album, options),
+            self.search_specific_type_with(query, rspotify_model::SearchType::Playlist, options)
+        )?;
+
+        let (tracks, artists, albums, playlists) = (
+            match track_result {
+                rspotify_model::SearchResult::Tracks(p) => p
+                    .items
+                    .into_iter()
+                    .filter_map(if options.include_external_audio {
+                        Track::try_from_full_track_unfiltered
+                    } else {
+                        Track::try_from_full_track
+                    })
+                    .collect(),
+                _ => anyhow::bail!("expect a track search result"),
+            },
+            match artist_result {
+                rspotify_model::SearchResult::Artists(p) => {
+                    p.items.into_iter().map(|a| a.into()).collect()
+                }
+                _ => anyhow::bail!("expect an artist search result"),
+            },
+            match album_result {
+                rspotify_model::SearchResult::Albums(p) => p
+                    .items
+                    .into_iter()
+                    .filter_map(Album::try_from_simplified_album)
+                    .collect(),
+                _ => anyhow::bail!("expect an album search result"),
             },
             match playlist_result {
                 rspotify_model::SearchResult::Playlists(p) => {
                     p.items.into_iter().map(|i| i.into()).collect()
                 }
-                _ => anyhow::bail!("expect a playlist search result"),
-            },
+                _ => anyhow::bail!("expect a playlist search result"),
+            },
+        );
+
+        Ok(SearchResults {
+            tracks,
+            artists,
+            albums,
+            playlists,
+            ..Default::default()
+        })
+    }
+
+    /// Search for items of the given types matching a given query.
+    ///
+    /// All types are searched in a single request, the results of unrequested types are empty.
+    pub async fn search_types(&self, query: &str, types: SearchTypeSet) -> Result<SearchResults> {
+        if types.is_empty() {
+            return Ok(SearchResults::default());
+        }
+
+        let type_param = types.type_param();
+        let response = self
+            .http_get::<SearchResponse>(
+                &format!("{SPOTIFY_API_ENDPOINT}/search"),
+                &Query::from([("q", query), ("type", type_param.as_str())]),
+            )
+            .await?;
+
+        Ok(response.into())
+    }
+
+    /// Find the track with a given International Standard Recording Code (ISRC),
+    /// the most popular one if multiple tracks share the code (e.g. a single and its album).
+    ///
+    /// The code's case and hyphens are normalized, an invalid code fails without a request.
+    pub async fn track_by_isrc(&self, isrc: &str) -> Result<Option<Track>> {
+        let Some(isrc) = crate::utils::normalize_isrc(isrc) else {
+            anyhow::bail!("invalid ISRC: {isrc}");
+        };
+
+        let query = SearchQuery::default().isrc(&isrc).to_string();
+        let result = self
+            .search_specific_type_with(
+                &query,
+                rspotify_model::SearchType::Track,
+                &SearchOptions::new().limit(LIBRARY_ITEMS_LIMIT as u32),
+            )
+            .await?;
+        let (results, _) = into_search_results(result)?;
+
+        // `rev` keeps the first of the equally popular tracks, as `max_by_key` returns the last
+        Ok(results
+            .tracks
+            .into_iter()
+            .filter(|t| {
+                t.isrc
+                    .as_deref()
+                    .map_or(true, |i| i.eq_ignore_ascii_case(&isrc))
+            })
+            .rev()
+            .max_by_key(|t| t.popularity))
+    }
+
+    /// Find the album with a given Universal Product Code (UPC)
+    pub async fn album_by_upc(&self, upc: &str) -> Result<Option<Album>> {
+        let Some(upc) = crate::utils::normalize_upc(upc) else {
+            anyhow::bail!("invalid UPC: {upc}");
+        };
+
+        let query = SearchQuery::default().upc(&upc).to_string();
+        let result = self
+            .search_specific_type_with(
+                &query,
+                rspotify_model::SearchType::Album,
+                &SearchOptions::new().limit(1),
+            )
+            .await?;
+        let (results, _) = into_search_results(result)?;
+        Ok(results.albums.into_iter().next())
+    }
+
+    /// Search for items of a specific type matching a given query, page by page.
+    ///
+    /// Only the first 1000 results can be fetched, see `SearchPage::next`.
+    pub async fn search_paged(
+        &self,
+        query: &str,
+        _type: rspotify_model::SearchType,
+        page_size: u32,
+    ) -> Result<SearchPage<'_>> {
+        let query = query.to_string();
+        SearchPage::new(
+            page_size as usize,
+            SEARCH_MAX_RESULTS,
+            move |offset, limit| {
+                let query = query.clone();
+                Box::pin(async move {
+                    // the podcast results are deserialized permissively, see `PodcastPage`
+                    let (offset, limit) = (offset.to_string(), limit.to_string());
+                    let response = self
+                        .http_get::<SearchResponse>(
+                            &format!("{SPOTIFY_API_ENDPOINT}/search"),
+                            &Query::from([
+                                ("q", query.as_str()),
+                                ("type", _type.into()),
+                                ("offset", offset.as_str()),
+                                ("limit", limit.as_str()),
+                            ]),
+                        )
+                        .await?;
+                    let total = response.total();
+                    anyhow::Ok((response.into(), total))
+                })
+            },
+        )
+        .await
+    }
+
+    /// Search for items of a specific type matching a given query
+    pub async fn search_specific_type(
+        &self,
+        query: &str,
+        _type: rspotify_model::SearchType,
+    ) -> Result<rspotify_model::SearchResult> {
+        self.search_specific_type_with(query, _type, &SearchOptions::default())
+            .await
+    }
+
+    /// Search for items of a specific type matching a given query, with options
+    pub async fn search_specific_type_with(
+        &self,
+        query: &str,
+        _type: rspotify_model::SearchType,
+        options: &SearchOptions,
+    ) -> Result<rspotify_model::SearchResult> {
+        Ok(self
+            .spotify
+            .search(
+                query,
+                _type,
+                options.market,
+                options
+                    .include_external_audio
+                    .then_some(rspotify_model::IncludeExternal::Audio),
+                options.limit,
+                options.offset,
+            )
+            .await?)
+    }
+
+    /// Check whether a playlist contains the given tracks.
+    ///
+    /// A relinked track in the playlist matches both its original and its relinked ids.
+    pub async fn playlist_contains(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_ids: &[TrackId<'_>],
+    ) -> Result<Vec<bool>> {
+        let ids = self
+            .playlist_item_uris(playlist_id)
+            .await?
+            .iter()
+            .flatten()
+            .flat_map(|u| u.track_ids())
+            .collect::<std::collections::HashSet<_>>();
+
+        Ok(track_ids
+            .iter()
+            .map(|id| ids.contains(&id.id().to_lowercase()))
+            .collect())
+    }
+
+    /// Get the ids of a playlist's tracks ordered by their positions, without the tracks' metadata.
+    ///
+    /// The items are requested with a `fields` filter, which makes it much cheaper than
+    /// `Client::playlist_context` for large playlists. Unavailable items, local files
+    /// and episodes are skipped. A relinked track has its id as stored in the playlist.
+    pub async fn playlist_track_ids(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<Vec<TrackId<'static>>> {
+        Ok(self
+            .playlist_item_uris(playlist_id)
+            .await?
+            .iter()
+            .flatten()
+            .filter_map(|u| TrackId::from_uri(u.stored_uri()).ok())
+            .map(|id| id.into_static())
+            .collect())
+    }
+
+    /// Get a playlist's metadata if the playlist was modified since the version identified
+    /// by `known_snapshot`, `None` if it is unchanged.
+    ///
+    /// The playlist's items are not fetched, so that checking an unchanged playlist is cheap.
+    pub async fn playlist_changed_since(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        known_snapshot: &str,
+    ) -> Result<Option<Playlist>> {
+        #[derive(Debug, Deserialize)]
+        struct Owner {
+            id: UserId<'static>,
+            display_name: Option<String>,
+        }
+        #[derive(Debug, Deserialize)]
+        struct PlaylistMetadata {
+            name: String,
+            collaborative: bool,
+            owner: Owner,
+            description: Option<String>,
+            snapshot_id: String,
+        }
+
+        let metadata = self
+            .http_get::<PlaylistMetadata>(
+                &format!("{SPOTIFY_API_ENDPOINT}/playlists/{}", playlist_id.id()),
+                &Query::from([(
+                    "fields",
+                    "name,collaborative,owner(id,display_name),description,snapshot_id",
+                )]),
+            )
+            .await?;
+        if metadata.snapshot_id == known_snapshot {
+            return Ok(None);
+        }
+
+        Ok(Some(Playlist {
+            id: playlist_id.into_static(),
+            collaborative: metadata.collaborative,
+            name: metadata.name,
+            owner: (
+                metadata.owner.display_name.unwrap_or_default(),
+                metadata.owner.id,
+            ),
+            desc: crate::utils::html_to_text(&metadata.description.unwrap_or_default()),
+            snapshot_id: metadata.snapshot_id,
+        }))
+    }
+
+    /// Get the metadata of the playlists modified since their known snapshot ids,
+    /// see `Client::playlist_changed_since`.
+    ///
+    /// The playlists are checked concurrently, with a bounded number of concurrent requests.
+    pub async fn playlists_changed_since(
+        &self,
+        known_snapshots: HashMap<PlaylistId<'_>, String>,
+    ) -> Result<Vec<Playlist>> {
+        let playlists = paging::map_concurrently(
+            known_snapshots.into_iter().collect(),
+            MAX_CONCURRENT_REQUESTS,
+            |(playlist_id, snapshot_id)| async move {
+                self.playlist_changed_since(playlist_id, &snapshot_id).await
+            },
+        )
+        .await?;
+        Ok(playlists.into_iter().flatten().collect())
+    }
+
+    /// Add a track to a playlist, returning whether the track was added.
+    ///
+    /// See `AddTrackOptions` for the interaction between deduplication and the insert position.
+    pub async fn add_track_to_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_id: TrackId<'_>,
+        options: AddTrackOptions,
+    ) -> Result<bool> {
+        if options.dedup {
+            // check the track's existence instead of removing its occurrences,
+            // so that the playlist (and its snapshot) isn't modified if the track exists
+            let uri = track_id.uri();
+            let uris = operation::complete(self.playlist_item_uris(playlist_id.as_ref())).await?;
+            let exists = uris.iter().flatten().any(|u| u.uris().any(|x| x == uri));
+            if !options.should_add(exists) {
+                tracing::info!("{uri} already exists in the playlist, skipped adding it");
+                return Ok(false);
+            }
+        }
+
+        self.playlist_add_items(
+            playlist_id.as_ref(),
+            [PlayableId::Track(track_id.as_ref())],
+            options.position,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Update a playlist's details (name, visibility, collaborative state and description)
+    ///
+    /// At least one of the details should be specified.
+    pub async fn update_playlist_details(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        name: Option<&str>,
+        public: Option<bool>,
+        collaborative: Option<bool>,
+        description: Option<&str>,
+    ) -> Result<()> {
+        if name.is_none() && public.is_none() && collaborative.is_none() && description.is_none() {
+            anyhow::bail!("at least one playlist detail should be specified");
+        }
+        // Spotify only allows private playlists to be collaborative
+        if collaborative == Some(true) && public == Some(true) {
+            anyhow::bail!("a collaborative playlist cannot be public");
+        }
+
+        self.playlist_change_detail(playlist_id, name, public, description, collaborative)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the cover images of a playlist
+    pub async fn playlist_cover_images(&self, playlist_id: PlaylistId<'_>) -> Result<Vec<Image>> {
+        // the endpoint may return `null` instead of an empty array
+        // for playlists without any cover image
+        let images = self
+            .http_get::<Option<Vec<rspotify_model::Image>>>(
+                &format!("{SPOTIFY_API_ENDPOINT}/playlists/{}/images", playlist_id.id()),
+                &Query::new(),
+            )
+            .await?;
+
+        Ok(images
+            .unwrap_or_default()
+            .into_iter()
+            .map(Image::from)
+            .collect())
+    }
+
+    /// Upload a custom cover image (JPEG) for a playlist
+    pub async fn set_playlist_cover(&self, playlist_id: PlaylistId<'_>, image: &[u8]) -> Result<()> {
+        let encoded = encode_playlist_cover(image)?;
+
+        self.http_put_raw(
+            &format!("{SPOTIFY_API_ENDPOINT}/playlists/{}/images", playlist_id.id()),
+            "image/jpeg",
+            encoded.into_bytes(),
+        )
+        .await
+    }
+
+    /// Add multiple tracks to the end of a playlist, preserving their order,
+    /// returning the playlist's new snapshot id.
+    ///
+    /// If `snapshot_id` is specified, the tracks are only added if the playlist's current
+    /// snapshot id matches it. Otherwise, a `ClientError::SnapshotMismatch` is returned.
+    ///
+    /// The tracks are added in chunks, a rate-limited chunk is retried with a backoff.
+    pub async fn add_tracks_to_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_ids: Vec<TrackId<'_>>,
+        snapshot_id: Option<&str>,
+    ) -> Result<String> {
+        // Spotify's endpoint for adding items doesn't accept a snapshot id,
+        // so the playlist's version is checked beforehand
+        if let Some(expected) = snapshot_id {
+            self.check_snapshot(playlist_id.as_ref(), expected).await?;
+        }
+
+        let playlist_id = &playlist_id;
+        let snapshot_id = chain_snapshots(
+            track_ids.chunks(PLAYLIST_ITEMS_LIMIT),
+            snapshot_id.map(str::to_string),
+            |chunk, _| async move {
+                let result = with_rate_limit_backoff(|| async {
+                    let result = self
+                        .playlist_add_items(
+                            playlist_id.as_ref(),
+                            chunk.iter().map(|id| PlayableId::Track(id.as_ref())),
+                            None,
+                        )
+                        .await?;
+                    anyhow::Ok(result)
+                })
+                .await?;
+                anyhow::Ok(result.snapshot_id)
+            },
+        )
+        .await?;
+
+        match snapshot_id {
+            Some(snapshot_id) => Ok(snapshot_id),
+            None => self.refresh_snapshot(playlist_id.as_ref()).await,
+        }
+    }
+
+    /// Import a playlist from an exported playlist (see `Client::export_playlist`)
+    /// into a new private playlist of the current user.
+    ///
+    /// An entry without a valid URI is matched with the top result of a search
+    /// by its name and artists. The unmatched entries are listed in the returned report.
+    pub async fn import_playlist(&self, name: &str, source: ImportSource) -> Result<ImportReport> {
+        let (text, format) = source.read()?;
+        let rows = crate::export::parse_import(&text, format)?;
+
+        let mut track_ids = vec![];
+        let mut unmatched = vec![];
+        for row in rows {
+            let id = match row
+                .uri
+                .as_deref()
+                .and_then(|uri| TrackId::from_id_or_uri(uri).ok())
+            {
+                Some(id) => Some(id.into_static()),
+                None => self.search_track(&row.name, &row.artists).await?,
+            };
+            match id {
+                Some(id) => track_ids.push(id),
+                None => unmatched.push(row),
+            }
+        }
+
+        let user_id = self.current_user_id().await?;
+        let mut playlist = self
+            .create_new_playlist(user_id, name, false, false, "")
+            .await?;
+        let imported = track_ids.len();
+        playlist.snapshot_id = self
+            .add_tracks_to_playlist(playlist.id.as_ref(), track_ids, None)
+            .await?;
+        if !unmatched.is_empty() {
+            tracing::warn!(
+                "{} entries couldn't be matched when importing {}",
+                unmatched.len(),
+                playlist.id.uri()
+            );
+        }
+
+        Ok(ImportReport {
+            playlist,
+            imported,
+            unmatched,
+        })
+    }
+
+    /// Search a track by its name and artists, returning the top result's id (if any)
+    async fn search_track(&self, name: &str, artists: &str) -> Result<Option<TrackId<'static>>> {
+        operation::check()?;
+        let query = track_search_query(name, artists).to_string();
+        let result = with_rate_limit_backoff(|| async {
+            let result = self
+                .spotify
+                .search(
+                    &query,
+                    rspotify_model::SearchType::Track,
+                    Some(Market::FromToken),
+                    None,
+                    Some(1),
+                    None,
+                )
+                .await?;
+            anyhow::Ok(result)
+        })
+        .await?;
+
+        match result {
+            rspotify_model::SearchResult::Tracks(page) => Ok(page
+                .items
+                .into_iter()
+                .find_map(|t| t.linked_from.map(|l| l.id).or(t.id))),
+            _ => anyhow::bail!("expect a track search result"),
+        }
+    }
+
+    /// Clone a playlist into the current user's library, returning the new playlist.
+    ///
+    /// The new playlist is named `new_name` (default to the source playlist's name).
+    /// Items that cannot be added to a playlist, i.e. local files, episodes
+    /// and tracks unavailable in the user's market, are skipped.
+    pub async fn clone_playlist(
+        &self,
+        source: PlaylistId<'_>,
+        new_name: Option<&str>,
+        public: bool,
+    ) -> Result<Playlist> {
+        let (context, skipped) = self.playlist_context_with_skipped(source.as_ref()).await?;
+        let Context::Playlist { playlist, tracks } = context else {
+            unreachable!("a playlist context should be returned");
+        };
+
+        let user_id = self.current_user_id().await?;
+        let mut new_playlist = self
+            .create_new_playlist(
+                user_id,
+                new_name.unwrap_or(&playlist.name),
+                public,
+                false,
+                &playlist.desc,
+            )
+            .await?;
+
+        new_playlist.snapshot_id = self
+            .add_tracks_to_playlist(
+                new_playlist.id.as_ref(),
+                tracks.into_iter().map(|t| t.id).collect(),
+                None,
+            )
+            .await?;
+        if skipped > 0 {
+            tracing::info!(
+                "skipped {skipped} unavailable items or local files when cloning {}",
+                source.uri()
+            );
+        }
+
+        Ok(new_playlist)
+    }
+
+    /// Merge the tracks of multiple playlists into a target playlist, replacing its items.
+    ///
+    /// The sources' order defines the tracks' order, duplicated tracks are dropped
+    /// according to the merge strategy.
+    pub async fn merge_playlists(
+        &self,
+        sources: Vec<PlaylistId<'_>>,
+        target: PlaylistId<'_>,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport> {
+        let mut source_tracks = vec![];
+        for source in sources {
+            match operation::complete(self.playlist_context(source)).await? {
+                Context::Playlist { tracks, .. } => source_tracks.push(tracks),
+                _ => unreachable!("a playlist context should be returned"),
+            }
+        }
+
+        let (tracks, report) = strategy.merge(source_tracks);
+        tracing::info!(
+            "merging {} tracks into {} ({} duplicates dropped)",
+            tracks.len(),
+            target.uri(),
+            report.duplicates
+        );
+
+        let items = tracks
+            .iter()
+            .map(|t| PlayableId::Track(t.id.as_ref()))
+            .collect::<Vec<_>>();
+        let snapshot_id = self.replace_playlist_items(target, &items).await?;
+
+        Ok(MergeReport {
+            snapshot_id,
+            ..report
+        })
+    }
+
+    /// Generate a new private playlist of the current user from the candidate tracks
+    /// matching the criteria's audio feature filters, see `generate::select_tracks`.
+    ///
+    /// The returned report includes the number of candidates and the reasons
+    /// of their exclusions.
+    pub async fn generate_playlist(
+        &self,
+        criteria: GenerationCriteria,
+    ) -> Result<GenerationReport> {
+        let tracks = match &criteria.source {
+            GenerationSource::LikedSongs => self.current_user_saved_tracks().await?,
+            GenerationSource::Playlists(playlist_ids) => {
+                let mut tracks = vec![];
+                for playlist_id in playlist_ids {
+                    match self.playlist_context(playlist_id.as_ref()).await? {
+                        Context::Playlist {
+                            tracks: mut playlist_tracks,
+                            ..
+                        } => tracks.append(&mut playlist_tracks),
+                        _ => unreachable!("a playlist context should be returned"),
+                    }
+                }
+                tracks
+            }
+            GenerationSource::Recommendations {
+                artists,
+                tracks,
+                genres,
+            } => {
+                self.recommended_tracks(artists, tracks, genres, &criteria)
+                    .await?
+            }
+        };
+
+        let track_ids = tracks.iter().map(|t| t.id.as_ref()).collect::<Vec<_>>();
+        let features = self.tracks_audio_features(&track_ids).await?;
+        // never generate a playlist from partially fetched candidates
+        operation::check()?;
+        let candidates = tracks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| generate::Candidate {
+                id: t.id.id(),
+                duration: t.duration,
+                features: features.get(i).copied().flatten(),
+            })
+            .collect::<Vec<_>>();
+        let selection = generate::select_tracks(&candidates, &criteria);
+        tracing::info!(
+            "generating a playlist of {} tracks out of {} candidates (exclusions: {:?})",
+            selection.indices.len(),
+            candidates.len(),
+            selection.exclusions
         );
 
-        Ok(SearchResults {
-            tracks,
-            artists,
-            albums,
-            playlists,
+        let user_id = self.current_user_id().await?;
+        let mut playlist = self
+            .create_new_playlist(user_id, &criteria.name, false, false, "")
+            .await?;
+        playlist.snapshot_id = self
+            .add_tracks_to_playlist(
+                playlist.id.as_ref(),
+                selection
+                    .indices
+                    .iter()
+                    .map(|&i| tracks[i].id.as_ref())
+                    .collect(),
+                None,
+            )
+            .await?;
+
+        Ok(GenerationReport {
+            playlist,
+            considered: candidates.len(),
+            selected: selection.indices.len(),
+            exclusions: selection.exclusions,
         })
     }
 
-    /// Search for items of a specific type matching a given query
-    pub async fn search_specific_type(
+    /// Get the recommended tracks from seeds, narrowed by the criteria's audio feature filters
+    async fn recommended_tracks(
         &self,
-        query: &str,
-        _type: rspotify_model::SearchType,
-    ) -> Result<rspotify_model::SearchResult> {
+        artists: &[ArtistId<'_>],
+        tracks: &[TrackId<'_>],
+        genres: &[String],
+        criteria: &GenerationCriteria,
+    ) -> Result<Vec<Track>> {
+        let seeds = RecommendationSeeds {
+            artists: artists.iter().map(|id| id.clone_static()).collect(),
+            tracks: tracks.iter().map(|id| id.clone_static()).collect(),
+            genres: genres.to_vec(),
+        };
+        let mut params = RecommendationParams::new();
+        if let Some(tempo) = &criteria.tempo {
+            params = params
+                .min(TunableAttribute::Tempo, *tempo.start())
+                .max(TunableAttribute::Tempo, *tempo.end());
+        }
+        if let Some(energy) = criteria.min_energy {
+            params = params.min(TunableAttribute::Energy, energy);
+        }
+        if let Some(acousticness) = criteria.max_acousticness {
+            params = params.max(TunableAttribute::Acousticness, acousticness);
+        }
+
+        self.recommendations(seeds, params, Some(100)).await
+    }
+
+    /// Get the audio features of tracks (e.g. their tempo), fetched in batches
+    /// and listed in the tracks' order.
+    ///
+    /// The features of a track are `None` if they are unavailable, e.g. for a track
+    /// without analysis. The call fails if the current operation's deadline is reached,
+    /// as the features of the remaining tracks would be missing.
+    pub async fn audio_features(
+        &self,
+        ids: Vec<TrackId<'_>>,
+    ) -> Result<Vec<Option<AudioFeatures>>> {
+        #[derive(Debug, Deserialize)]
+        struct FeaturesResponse {
+            audio_features: Vec<Option<AudioFeatures>>,
+        }
+
+        paging::map_chunks(&ids, AUDIO_FEATURES_BATCH_LIMIT, |batch| async move {
+            let ids = batch.iter().map(|id| id.id()).collect::<Vec<_>>().join(",");
+            let response = self
+                .http_get::<FeaturesResponse>(
+                    &format!("{SPOTIFY_API_ENDPOINT}/audio-features"),
+                    &Query::from([("ids", ids.as_str())]),
+                )
+                .await?;
+            // the features are listed in the requested tracks' order
+            anyhow::Ok(response.audio_features)
+        })
+        .await
+    }
+
+    /// Get the audio analysis of a track: its bars, beats, tatums, sections and segments,
+    /// e.g. to synchronize visualizations with the playback's progress
+    pub async fn audio_analysis(&self, id: TrackId<'_>) -> Result<AudioAnalysis> {
+        self.http_get::<AudioAnalysis>(
+            &format!("{SPOTIFY_API_ENDPOINT}/audio-analysis/{}", id.id()),
+            &Query::new(),
+        )
+        .await
+    }
+
+    /// Get the sections of a track's audio analysis.
+    ///
+    /// Only the sections are deserialized, the other (much larger) parts of the analysis
+    /// like the segments are skipped.
+    pub async fn audio_analysis_sections(&self, id: TrackId<'_>) -> Result<Vec<AnalysisSection>> {
+        #[derive(Debug, Deserialize)]
+        struct SectionsResponse {
+            sections: Vec<AnalysisSection>,
+        }
+
+        let response = self
+            .http_get::<SectionsResponse>(
+                &format!("{SPOTIFY_API_ENDPOINT}/audio-analysis/{}", id.id()),
+                &Query::new(),
+            )
+            .await?;
+        Ok(response.sections)
+    }
+
+    /// Get the available genre seeds of recommendations.
+    ///
+    /// The genres are fetched on the first call and cached for the client's lifetime.
+    pub async fn available_genre_seeds(&self) -> Result<Vec<String>> {
+        #[derive(Debug, Deserialize)]
+        struct GenreSeedsResponse {
+            genres: Vec<String>,
+        }
+
+        let genres = self
+            .genre_seeds
+            .get_or_try_init(|| async {
+                let response = self
+                    .http_get::<GenreSeedsResponse>(
+                        &format!("{SPOTIFY_API_ENDPOINT}/recommendations/available-genre-seeds"),
+                        &Query::new(),
+                    )
+                    .await?;
+                anyhow::Ok(response.genres)
+            })
+            .await?;
+        Ok(genres.clone())
+    }
+
+    /// Check if a genre is an available genre seed of recommendations
+    pub async fn is_valid_genre_seed(&self, genre: &str) -> Result<bool> {
         Ok(self
-            .spotify
-            .search(query, _type, None, None, None, None)
-            .await?)
+            .available_genre_seeds()
+            .await?
+            .iter()
+            .any(|seed| seed == genre))
     }
 
-    /// Add a track to a playlist
-    pub async fn add_track_to_playlist(
+    /// Get recommended tracks based on seed artists, tracks and genres,
+    /// tuned by audio attributes (e.g. a minimum energy or a target tempo).
+    ///
+    /// The seeds (including the genres, see `Client::is_valid_genre_seed`), the attributes
+    /// and the `limit` (between 1 and 100, defaults to 20) are validated before requesting
+    /// the recommendations.
+    pub async fn recommendations(
+        &self,
+        seeds: RecommendationSeeds,
+        tunables: RecommendationParams,
+        limit: Option<u32>,
+    ) -> Result<Vec<Track>> {
+        seeds.validate()?;
+        for genre in &seeds.genres {
+            if !self.is_valid_genre_seed(genre).await? {
+                anyhow::bail!("invalid genre seed `{genre}`, see `Client::available_genre_seeds`");
+            }
+        }
+        let tunables = tunables.query()?;
+        if let Some(limit) = limit.filter(|limit| !(1..=100).contains(limit)) {
+            anyhow::bail!("invalid recommendations limit {limit}: expected 1 to 100");
+        }
+
+        let seed_artists = seeds.artists.iter().map(|id| id.id()).collect::<Vec<_>>();
+        let seed_tracks = seeds.tracks.iter().map(|id| id.id()).collect::<Vec<_>>();
+        let (seed_artists, seed_tracks, seed_genres, limit) = (
+            seed_artists.join(","),
+            seed_tracks.join(","),
+            seeds.genres.join(","),
+            limit.map(|limit| limit.to_string()),
+        );
+        let mut payload = market_query();
+        for (key, value) in [
+            ("seed_artists", &seed_artists),
+            ("seed_tracks", &seed_tracks),
+            ("seed_genres", &seed_genres),
+        ] {
+            if !value.is_empty() {
+                payload.insert(key, value);
+            }
+        }
+        if let Some(limit) = &limit {
+            payload.insert("limit", limit);
+        }
+        for (key, value) in &tunables {
+            payload.insert(key, value);
+        }
+
+        let recommendations = self
+            .http_get::<rspotify_model::Recommendations>(
+                &format!("{SPOTIFY_API_ENDPOINT}/recommendations"),
+                &payload,
+            )
+            .await?;
+        Ok(recommendations
+            .tracks
+            .into_iter()
+            .filter_map(Track::try_from_simplified_track)
+            .collect())
+    }
+
+    /// Remove a track from a playlist
+    pub async fn delete_track_from_playlist(
         &self,
         playlist_id: PlaylistId<'_>,
         track_id: TrackId<'_>,
@@ -358,109 +3319,665 @@ impl Client {
         )
             .await?;
 
-        self.playlist_add_items(
-            playlist_id.as_ref(),
-            [PlayableId::Track(track_id.as_ref())],
-            None,
+        Ok(())
+    }
+
+    /// Remove all the occurrences of multiple tracks from a playlist,
+    /// returning the playlist's new snapshot id.
+    ///
+    /// If `snapshot_id` is specified, the tracks are removed from the playlist's version
+    /// identified by the snapshot id.
+    /// An empty list of tracks doesn't modify the playlist and returns its current snapshot id.
+    pub async fn delete_tracks_from_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        track_ids: Vec<TrackId<'_>>,
+        snapshot_id: Option<&str>,
+    ) -> Result<String> {
+        if track_ids.is_empty() {
+            return self.refresh_snapshot(playlist_id).await;
+        }
+
+        let playlist_id = &playlist_id;
+        let snapshot_id = chain_snapshots(
+            track_ids.chunks(PLAYLIST_ITEMS_LIMIT),
+            snapshot_id.map(str::to_string),
+            |chunk, snapshot_id| async move {
+                let result = self
+                    .playlist_remove_all_occurrences_of_items(
+                        playlist_id.as_ref(),
+                        chunk.iter().map(|id| PlayableId::Track(id.as_ref())),
+                        snapshot_id.as_deref(),
+                    )
+                    .await?;
+                anyhow::Ok(result.snapshot_id)
+            },
         )
+        .await?;
+
+        Ok(snapshot_id.unwrap_or_default())
+    }
+
+    /// Remove the items at the given positions from a playlist, returning the playlist's new snapshot id.
+    ///
+    /// Unlike `delete_track_from_playlist`, only the specified occurrences of a track are removed.
+    /// The positions are relative to the playlist's version identified by `snapshot_id`.
+    /// If the playlist was modified since, a `ClientError::SnapshotMismatch` is returned
+    /// without removing any item.
+    pub async fn remove_playlist_items_at(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        positions: Vec<usize>,
+        snapshot_id: &str,
+    ) -> Result<String> {
+        // the items at the positions are only known for the playlist's current version,
+        // which must be the expected version while the items are read
+        self.check_snapshot(playlist_id.as_ref(), snapshot_id)
+            .await?;
+        let uris = operation::complete(self.playlist_item_uris(playlist_id.as_ref())).await?;
+        self.check_snapshot(playlist_id.as_ref(), snapshot_id)
             .await?;
 
-        Ok(())
+        let mut snapshot_id = snapshot_id.to_string();
+        for chunk in playlist_removals(&uris, positions)? {
+            operation::check()?;
+            let items = chunk
+                .iter()
+                .map(|(uri, positions)| {
+                    Ok(rspotify_model::ItemPositions {
+                        id: playable_id_from_uri(uri).with_context(|| {
+                            format!("cannot remove the unsupported item {uri} (e.g. a local file)")
+                        })?,
+                        positions,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            snapshot_id = self
+                .playlist_remove_specific_occurrences_of_items(
+                    playlist_id.as_ref(),
+                    items,
+                    Some(&snapshot_id),
+                )
+                .await?
+                .snapshot_id;
+        }
+
+        Ok(snapshot_id)
     }
 
-    pub async fn add_tracks_to_playlist(
-        &self
-    ) -> Result<()> {
+    /// Synchronize a playlist's items with a desired list of tracks.
+    ///
+    /// Unlike replacing all the playlist's items, only the minimal set of items is added,
+    /// removed or moved, so that the kept items preserve their metadata (e.g. the date added).
+    /// Unavailable items cannot be removed and are kept next to their current neighbours.
+    pub async fn sync_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        desired: Vec<TrackId<'_>>,
+    ) -> Result<SyncReport> {
+        let mut snapshot_id = self.refresh_snapshot(playlist_id.as_ref()).await?;
+        let current = operation::complete(self.playlist_item_uris(playlist_id.as_ref()))
+            .await?
+            .into_iter()
+            .map(|item| item.map(|item| item.stored_uri().to_string()))
+            .collect::<Vec<_>>();
+        let desired_uris = desired.iter().map(|id| id.uri()).collect::<Vec<_>>();
+        let diff = sync::diff_playlist(&current, &desired_uris);
 
-        Ok(())
+        let mut report = SyncReport::default();
+        if !diff.removals.is_empty() {
+            snapshot_id = self
+                .remove_playlist_items_at(playlist_id.as_ref(), diff.removals.clone(), &snapshot_id)
+                .await?;
+            report.removed = diff
+                .removals
+                .iter()
+                .filter_map(|&i| current[i].clone())
+                .collect();
+        }
+
+        for m in diff.moves {
+            operation::check()?;
+            snapshot_id = self
+                .reorder_playlist(
+                    playlist_id.as_ref(),
+                    m.range_start,
+                    m.insert_index,
+                    None,
+                    Some(&snapshot_id),
+                )
+                .await?;
+            report.moved.push(desired[m.index].clone_static());
+        }
+
+        for (position, range) in diff.insertions {
+            let tracks = &desired[range];
+            for (i, chunk) in tracks.chunks(PLAYLIST_ITEMS_LIMIT).enumerate() {
+                operation::check()?;
+                self.playlist_add_items(
+                    playlist_id.as_ref(),
+                    chunk.iter().map(|id| PlayableId::Track(id.as_ref())),
+                    Some((position + i * PLAYLIST_ITEMS_LIMIT) as u32),
+                )
+                .await?;
+            }
+            report
+                .added
+                .extend(tracks.iter().map(|id| id.clone_static()));
+        }
+
+        Ok(report)
+    }
+
+    /// Remove the duplicated tracks of a playlist in place, keeping the first occurrence
+    /// of each track identified by `key`.
+    ///
+    /// The duplicates are removed by their positions, so that the other occurrences of
+    /// the same tracks are kept. Unavailable items and local files are never removed.
+    pub async fn dedup_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        key: DedupKey,
+    ) -> Result<DedupReport> {
+        let (playlist, mut tracks) =
+            operation::complete(self.playlist_tracks_by_position(playlist_id.as_ref())).await?;
+
+        let positions = key.duplicates(&tracks);
+        if positions.is_empty() {
+            return Ok(DedupReport {
+                removed: vec![],
+                snapshot_id: playlist.snapshot_id,
+            });
+        }
+        tracing::info!(
+            "removing {} duplicated tracks from {}",
+            positions.len(),
+            playlist_id.uri()
+        );
+
+        let snapshot_id = self
+            .remove_playlist_items_at(playlist_id, positions.clone(), &playlist.snapshot_id)
+            .await?;
+        let removed = positions
+            .into_iter()
+            .filter_map(|i| tracks[i].take().map(|t| (t, i)))
+            .collect();
+
+        Ok(DedupReport {
+            removed,
+            snapshot_id,
+        })
+    }
+
+    /// Sort a playlist's tracks in place by a track order, returning the playlist's new snapshot id.
+    ///
+    /// The sorted order is computed locally (see `TrackOrder::sorted_positions`), then applied
+    /// according to the strategy. Items which are not sorted (e.g. episodes and local files)
+    /// are kept next to their neighbours when moving the items, and at their positions
+    /// when replacing the items.
+    ///
+    /// The replacement of the items is never interrupted by the operation's deadline or
+    /// cancellation, so that a playlist is never left truncated.
+    pub async fn sort_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        order: TrackOrder,
+        ascending: bool,
+        strategy: SortStrategy,
+    ) -> Result<String> {
+        let (playlist, mut paginator) = self
+            .playlist_with_paginator(playlist_id.as_ref(), None, |item| {
+                let id = match &item.track {
+                    Some(rspotify_model::PlayableItem::Track(track)) => track
+                        .linked_from
+                        .as_ref()
+                        .map(|l| l.id.clone())
+                        .or_else(|| track.id.clone())
+                        .map(PlayableId::Track),
+                    Some(rspotify_model::PlayableItem::Episode(episode)) => {
+                        Some(PlayableId::Episode(episode.id.clone()))
+                    }
+                    None => None,
+                };
+                Some((
+                    id,
+                    PlaylistTrack::try_from_playlist_item(item).map(|t| t.track),
+                ))
+            })
+            .await?;
+        // never sort a partially fetched playlist
+        let (ids, tracks): (Vec<_>, Vec<_>) = operation::complete(paginator.collect())
+            .await?
+            .into_iter()
+            .unzip();
+
+        let current = tracks
+            .iter()
+            .map(|t| t.as_ref().map(|t| t.id.uri()))
+            .collect::<Vec<_>>();
+        let positions = order.sorted_positions(&tracks, ascending);
+        let sorted = positions
+            .iter()
+            .map(|&i| {
+                current[i]
+                    .clone()
+                    .expect("sorted track should be available")
+            })
+            .collect::<Vec<_>>();
+        // the sorted playlist has the same items, so that the diff only has moves
+        let diff = sync::diff_playlist(&current, &sorted);
+
+        // the sorted items can only replace the playlist's items if all of them can be re-added
+        let replacement = sorted_items(&ids, &positions);
+        let strategy = match (strategy, &replacement) {
+            (SortStrategy::Auto, Some(_)) if diff.moves.len() > AUTO_SORT_MAX_MOVES => {
+                SortStrategy::Replace
+            }
+            (SortStrategy::Replace, Some(_)) => SortStrategy::Replace,
+            _ => SortStrategy::Moves,
+        };
+        tracing::info!(
+            "sorting {} by {order:?} ({} moves, strategy={strategy:?})",
+            playlist_id.uri(),
+            diff.moves.len()
+        );
+
+        let mut snapshot_id = playlist.snapshot_id;
+        match replacement {
+            Some(items) if strategy == SortStrategy::Replace => {
+                snapshot_id = self
+                    .replace_playlist_items(playlist_id.as_ref(), &items)
+                    .await?;
+            }
+            _ => {
+                for m in diff.moves {
+                    operation::check()?;
+                    snapshot_id = self
+                        .reorder_playlist(
+                            playlist_id.as_ref(),
+                            m.range_start,
+                            m.insert_index,
+                            None,
+                            Some(&snapshot_id),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        Ok(snapshot_id)
+    }
+
+    /// Replace all the items of a playlist, returning the playlist's new snapshot id.
+    ///
+    /// The first chunk of items replaces the playlist's items and the next chunks are appended,
+    /// chaining their snapshots. The writes aren't interrupted by the current operation's
+    /// deadline or cancellation, so that the playlist is never left with only some of its items.
+    async fn replace_playlist_items(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        items: &[PlayableId<'_>],
+    ) -> Result<String> {
+        let (first_chunk, rest) = items.split_at(std::cmp::min(items.len(), PLAYLIST_ITEMS_LIMIT));
+        let playlist_id = &playlist_id;
+        let replaced = operation::uninterruptible(async {
+            let snapshot_id = self
+                .playlist_replace_items(playlist_id.as_ref(), first_chunk.to_vec())
+                .await?
+                .snapshot_id;
+            chain_snapshots(
+                rest.chunks(PLAYLIST_ITEMS_LIMIT),
+                Some(snapshot_id),
+                |chunk, _| async move {
+                    let result = self
+                        .playlist_add_items(playlist_id.as_ref(), chunk.to_vec(), None)
+                        .await?;
+                    anyhow::Ok(result.snapshot_id)
+                },
+            )
+            .await
+        })
+        .await?;
+        Ok(replaced.unwrap_or_default())
+    }
+
+    /// Reorder items in a playlist, returning the playlist's new snapshot id.
+    ///
+    /// Move `range_length` items (default to 1) starting at `range_start`
+    /// so that the first moved item ends up at `insert_index` in the reordered playlist.
+    /// The returned snapshot id can be passed to the next call to chain moves safely.
+    pub async fn reorder_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        range_start: usize,
+        insert_index: usize,
+        range_length: Option<usize>,
+        snapshot_id: Option<&str>,
+    ) -> Result<String> {
+        let range_length = range_length.unwrap_or(1);
+        let insert_before = reorder_insert_before(range_start, insert_index, range_length);
+
+        let result = self
+            .playlist_reorder_items(
+                playlist_id,
+                Some(range_start as i32),
+                Some(insert_before as i32),
+                Some(range_length as u32),
+                snapshot_id,
+            )
+            .await?;
+
+        Ok(result.snapshot_id)
+    }
+
+    /// Get a playlist context data
+    pub async fn playlist_context(&self, playlist_id: PlaylistId<'_>) -> Result<Context> {
+        Ok(self.playlist_context_with_skipped(playlist_id).await?.0)
+    }
+
+    /// Get a playlist context data along with the number of the playlist's skipped items,
+    /// i.e. local files, episodes and unavailable tracks
+    async fn playlist_context_with_skipped(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<(Context, usize)> {
+        let playlist_uri = playlist_id.uri();
+        tracing::info!("Get playlist context: {}", playlist_uri);
+
+        let (playlist, mut paginator) = self.playlist_context_lazy(playlist_id).await?;
+        let tracks = paginator.collect().await?;
+        let skipped = paginator.fetched() - tracks.len();
+
+        Ok((Context::Playlist { playlist, tracks }, skipped))
+    }
+
+    /// Get a playlist context data with its tracks relinked for the options' market
+    /// and at most `max_items` tracks of the fetch options, read from the persisted contexts
+    /// following the options' cache policy.
+    ///
+    /// The page size doesn't apply, the first page being included in the playlist.
+    /// Local files, episodes and the tracks unavailable in the market are skipped.
+    /// The items aren't fetched if the cached playlist's snapshot id is the playlist's current one.
+    pub async fn playlist_context_with(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        options: &FetchOptions,
+    ) -> Result<Context> {
+        if !options.persists_context() {
+            return self.fetch_playlist_context(playlist_id, options).await;
+        }
+
+        let uri = playlist_id.uri();
+        let cached = match self.cached_context(&uri, options.cache) {
+            Some((cached, true)) => return Ok(cached.context),
+            Some((cached, false)) => {
+                match self.refresh_snapshot(playlist_id.as_ref()).await {
+                    Ok(snapshot_id) if cached.snapshot_id() == Some(snapshot_id.as_str()) => {
+                        tracing::info!("Use the cached playlist context: {uri}");
+                        // the cached copy is rewritten to be fresh again
+                        self.persist_context(&uri, &cached.context);
+                        return Ok(cached.context);
+                    }
+                    Ok(_) => Some(cached),
+                    Err(err) => {
+                        tracing::warn!("failed to get the snapshot id of {uri}, using the cached context: {err:#}");
+                        return Ok(cached.context);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        if cached.is_some() {
+            // the playlist changed since its cached copy, so its cached responses are stale too
+            let fetch = operation::no_cache(self.playlist_context(playlist_id));
+            self.fetch_context(&uri, cached, fetch).await
+        } else {
+            self.fetch_context(&uri, cached, self.playlist_context(playlist_id))
+                .await
+        }
+    }
+
+    /// Fetch a playlist context data with its tracks relinked for the options' market
+    /// and at most `max_items` tracks of the fetch options
+    async fn fetch_playlist_context(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        options: &FetchOptions,
+    ) -> Result<Context> {
+        tracing::info!("Get playlist context: {}", playlist_id.uri());
+
+        let (playlist, mut paginator) = self
+            .playlist_with_paginator(playlist_id, options.market, |item| {
+                PlaylistTrack::try_from_playlist_item(item).map(|t| t.track)
+            })
+            .await?;
+        let tracks = paginator.collect_limited(options.max_items).await?;
+        Ok(Context::Playlist { playlist, tracks })
+    }
+
+    /// Get a playlist along with a paginator of its tracks, whose pages are fetched on demand.
+    ///
+    /// Local files, episodes and unavailable tracks are skipped.
+    pub async fn playlist_context_lazy(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<(Playlist, Paginator<'_, Track>)> {
+        self.playlist_with_paginator(playlist_id, None, |item| {
+            PlaylistTrack::try_from_playlist_item(item).map(|t| t.track)
+        })
+        .await
     }
 
-    /// Remove a track from a playlist
-    pub async fn delete_track_from_playlist(
+    /// Get a playlist along with its items ordered by their positions, an item being `None`
+    /// if it isn't an available track (a local file, an episode or an unavailable track).
+    ///
+    /// Unlike `Client::playlist_context`, the tracks' positions in the playlist are kept,
+    /// e.g. to remove tracks with `Client::remove_playlist_items_at`.
+    pub async fn playlist_tracks_by_position(
         &self,
         playlist_id: PlaylistId<'_>,
-        track_id: TrackId<'_>,
-    ) -> Result<()> {
-        // remove all the occurrences of the track to ensure no duplication in the playlist
-        self.playlist_remove_all_occurrences_of_items(
-            playlist_id.as_ref(),
-            [PlayableId::Track(track_id.as_ref())],
-            None,
-        )
+    ) -> Result<(Playlist, Vec<Option<Track>>)> {
+        let (playlist, mut paginator) = self
+            .playlist_with_paginator(playlist_id, None, |item| {
+                Some(PlaylistTrack::try_from_playlist_item(item).map(|t| t.track))
+            })
             .await?;
+        Ok((playlist, paginator.collect().await?))
+    }
 
-        Ok(())
+    /// Get the tracks of a playlist along with the dates and the users of their additions.
+    ///
+    /// Local files, episodes and unavailable tracks are skipped.
+    pub async fn playlist_tracks_detailed(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<Vec<PlaylistTrack>> {
+        let (_, mut paginator) = self
+            .playlist_with_paginator(playlist_id, None, PlaylistTrack::try_from_playlist_item)
+            .await?;
+        paginator.collect().await
     }
 
-    /// Reorder items in a playlist
-    async fn reorder_playlist_items(
+    /// Get a playlist along with a paginator of its items relinked for a market
+    /// (the current user's market if `None`) and converted with `convert`
+    async fn playlist_with_paginator<T: Send + 'static>(
         &self,
         playlist_id: PlaylistId<'_>,
-        insert_index: usize,
-        range_start: usize,
-        range_length: Option<usize>,
-        snapshot_id: Option<&str>,
-    ) -> Result<()> {
-        let insert_before = match insert_index > range_start {
-            true => insert_index + 1,
-            false => insert_index,
-        };
+        market: Option<Market>,
+        convert: fn(rspotify_model::PlaylistItem) -> Option<T>,
+    ) -> Result<(Playlist, Paginator<'_, T>)> {
+        let mut playlist = self.full_playlist(playlist_id, market).await?;
 
-        self.playlist_reorder_items(
-            playlist_id.clone(),
-            Some(range_start as i32),
-            Some(insert_before as i32),
-            range_length.map(|range_length| range_length as u32),
-            snapshot_id,
-        )
-            .await?;
+        let first_page = std::mem::take(&mut playlist.tracks.items);
+        let paginator = Paginator::new(
+            first_page,
+            playlist.tracks.next.take(),
+            playlist.tracks.total as usize,
+            move |url| async move {
+                let page = self
+                    .http_get::<Page<rspotify_model::PlaylistItem>>(&url, &market_query_for(market))
+                    .await?;
+                anyhow::Ok((page.items, page.next))
+            },
+            convert,
+        );
 
-        Ok(())
+        Ok((playlist.into(), paginator))
     }
 
-    /// Get a playlist context data
-    pub async fn playlist_context(&self, playlist_id: PlaylistId<'_>) -> Result<Context> {
-        let playlist_uri = playlist_id.uri();
-        tracing::info!("Get playlist context: {}", playlist_uri);
-
+    /// Get a playlist including the first page of its items, relinked for a market
+    /// (the current user's market if `None`)
+    async fn full_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        market: Option<Market>,
+    ) -> Result<FullPlaylist> {
         // TODO: this should use `rspotify::playlist` API instead of `internal_call`
         // See: https://github.com/ramsayleung/rspotify/issues/459
-        // let playlist = self
-        //     .playlist(playlist_id, None, Some(Market::FromToken))
-        //     .await?;
-        let playlist = self
-            .http_get::<FullPlaylist>(
+        let playlist = if self.is_quirk_enabled(Quirk::PlaylistRequest) {
+            self.http_get::<FullPlaylist>(
                 &format!("{SPOTIFY_API_ENDPOINT}/playlists/{}", playlist_id.id()),
-                &market_query(),
+                &market_query_for(market),
             )
-            .await?;
+            .await?
+        } else {
+            self.playlist(
+                playlist_id.as_ref(),
+                None,
+                Some(market.unwrap_or(Market::FromToken)),
+            )
+            .await?
+        };
+        Ok(playlist)
+    }
+
+    /// Export a playlist and its tracks.
+    ///
+    /// The tracks are written page by page as they are fetched. Unavailable items,
+    /// local files and episodes are not exported.
+    pub async fn export_playlist(
+        &self,
+        playlist_id: PlaylistId<'_>,
+        format: ExportFormat,
+    ) -> Result<String> {
+        #[derive(Serialize)]
+        struct JsonTrack<'a> {
+            #[serde(flatten)]
+            track: &'a Track,
+            added_at: Option<chrono::DateTime<chrono::Utc>>,
+        }
+        #[derive(Serialize)]
+        struct CsvTrack<'a> {
+            name: &'a str,
+            artists: String,
+            album: String,
+            duration_ms: u128,
+            uri: String,
+            added_at: String,
+        }
 
-        // get the playlist's tracks
+        let playlist = self.full_playlist(playlist_id, None).await?;
         let first_page = playlist.tracks.clone();
-        let tracks = self
-            .all_paging_items(first_page, &market_query())
-            .await?
-            .into_iter()
-            .filter_map(|item| match item.track {
-                Some(rspotify_model::PlayableItem::Track(track)) => {
-                    Track::try_from_full_track(track)
+        let playlist: Playlist = playlist.into();
+        let mut writer =
+            ExportWriter::with_header(vec![], format, "playlist", &playlist, "tracks")?;
+
+        paging::for_each_page(
+            first_page.items,
+            first_page.next,
+            |url| async move {
+                let page = self
+                    .http_get::<Page<rspotify_model::PlaylistItem>>(&url, &market_query())
+                    .await?;
+                anyhow::Ok((page.items, page.next))
+            },
+            |items| {
+                for item in items {
+                    let track = match item.track {
+                        Some(rspotify_model::PlayableItem::Track(track)) => {
+                            Track::try_from_full_track(track)
+                        }
+                        _ => None,
+                    };
+                    let Some(track) = track else {
+                        continue;
+                    };
+                    match format {
+                        ExportFormat::Json | ExportFormat::JsonLines => {
+                            writer.write_item(&JsonTrack {
+                                track: &track,
+                                added_at: item.added_at,
+                            })?
+                        }
+                        ExportFormat::Csv => writer.write_item(&CsvTrack {
+                            name: &track.name,
+                            artists: track.artists_info(),
+                            album: track.album_info(),
+                            duration_ms: track.duration.as_millis(),
+                            uri: track.id.uri(),
+                            added_at: item.added_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                        })?,
+                    }
                 }
-                _ => None,
-            })
-            .collect::<Vec<_>>();
+                Ok(())
+            },
+        )
+        .await?;
 
-        Ok(Context::Playlist {
-            playlist: playlist.into(),
-            tracks,
-        })
+        tracing::info!(
+            "exported {} tracks of {}",
+            writer.count(),
+            playlist.id.uri()
+        );
+        Ok(String::from_utf8(writer.finish()?)?)
     }
 
     /// Get an album context data
     pub async fn album_context(&self, album_id: AlbumId<'_>) -> Result<Context> {
+        self.fetch_album_context(album_id, &FetchOptions::default())
+            .await
+    }
+
+    /// Get an album context data in the options' market with at most `max_items` tracks
+    /// of the fetch options, read from the persisted contexts following the options'
+    /// cache policy.
+    ///
+    /// The page size doesn't apply, the first page being included in the album.
+    pub async fn album_context_with(
+        &self,
+        album_id: AlbumId<'_>,
+        options: &FetchOptions,
+    ) -> Result<Context> {
+        if !options.persists_context() {
+            return self.fetch_album_context(album_id, options).await;
+        }
+
+        let uri = album_id.uri();
+        let cached = match self.cached_context(&uri, options.cache) {
+            Some((cached, true)) => return Ok(cached.context),
+            cached => cached.map(|(cached, _)| cached),
+        };
+
+        self.fetch_context(&uri, cached, self.fetch_album_context(album_id, options))
+            .await
+    }
+
+    /// Fetch an album context data in the options' market
+    /// with at most `max_items` tracks of the fetch options
+    async fn fetch_album_context(
+        &self,
+        album_id: AlbumId<'_>,
+        options: &FetchOptions,
+    ) -> Result<Context> {
         let album_uri = album_id.uri();
         tracing::info!("Get album context: {}", album_uri);
 
-        let album = self.album(album_id, Some(Market::FromToken)).await?;
+        let market = options.market;
+        let album = self
+            .album(album_id, Some(market.unwrap_or(Market::FromToken)))
+            .await?;
         let first_page = album.tracks.clone();
 
         // converts `rspotify_model::FullAlbum` into `state::Album`
@@ -468,7 +3985,7 @@ impl Client {
 
         // get the album's tracks
         let tracks = self
-            .all_paging_items(first_page, &Query::new())
+            .paging_items(first_page, &market_query_for(market), options)
             .await?
             .into_iter()
             .filter_map(|t| {
@@ -485,94 +4002,761 @@ impl Client {
         Ok(Context::Album { album, tracks })
     }
 
-    /// Get an artist context data
-    pub async fn artist_context(&self, artist_id: ArtistId<'_>) -> Result<Context> {
-        let artist_uri = artist_id.uri();
-        tracing::info!("Get artist context: {}", artist_uri);
+    /// Read the persisted copy of a context following the cache policy,
+    /// along with whether the copy is fresh, i.e. usable without checking or refreshing it
+    fn cached_context(
+        &self,
+        uri: &str,
+        policy: CachePolicy,
+    ) -> Option<(context_cache::CachedContext, bool)> {
+        let cache = self.context_cache.as_ref()?;
+        let max_age = match policy {
+            CachePolicy::FreshOnly => return None,
+            CachePolicy::CacheThenRefresh => None,
+            CachePolicy::CacheIfFresh(max_age) => Some(max_age),
+        };
+
+        let cached = cache.read(uri)?;
+        let fresh = max_age.is_some_and(|max_age| {
+            (chrono::Utc::now() - cached.cached_at)
+                .to_std()
+                .is_ok_and(|age| age <= max_age)
+        });
+        Some((cached, fresh))
+    }
+
+    /// Fetch a context and persist it, falling back to its cached copy if the fetch fails
+    async fn fetch_context(
+        &self,
+        uri: &str,
+        cached: Option<context_cache::CachedContext>,
+        fetch: impl std::future::Future<Output = Result<Context>>,
+    ) -> Result<Context> {
+        match fetch.await {
+            Ok(context) => {
+                // a partial context, e.g. stopped at the operation's deadline, isn't persisted
+                if !operation::is_incomplete() {
+                    self.persist_context(uri, &context);
+                }
+                Ok(context)
+            }
+            Err(err) => match cached {
+                Some(cached) => {
+                    tracing::warn!(
+                        "failed to fetch the context {uri}, using the cached context: {err:#}"
+                    );
+                    Ok(cached.context)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Persist a context if the contexts are persisted, see `CacheConfig::persist_contexts`
+    fn persist_context(&self, uri: &str, context: &Context) {
+        if let Some(cache) = &self.context_cache {
+            cache.write(uri, context);
+        }
+    }
+
+    /// Get the context of a Spotify URI or share link, see `context_and_track_from_uri`
+    pub async fn context_from_uri(&self, input: &str) -> Result<Context> {
+        Ok(self.context_and_track_from_uri(input).await?.0)
+    }
+
+    /// Get the context of a Spotify URI or share link (`spotify:{type}:{id}` or
+    /// `https://open.spotify.com/{type}/{id}`) of a playlist, an album, an artist or a show.
+    /// A track resolves to its album's context, returned with the track's id to highlight it.
+    pub async fn context_and_track_from_uri(
+        &self,
+        input: &str,
+    ) -> Result<(Context, Option<TrackId<'static>>)> {
+        let Some((kind, id)) = crate::utils::parse_spotify_link(input) else {
+            anyhow::bail!("invalid Spotify URI or link: {input}");
+        };
+
+        match kind {
+            rspotify_model::Type::Playlist => {
+                let context = self.playlist_context(PlaylistId::from_id(id)?).await?;
+                Ok((context, None))
+            }
+            rspotify_model::Type::Album => {
+                let context = self.album_context(AlbumId::from_id(id)?).await?;
+                Ok((context, None))
+            }
+            rspotify_model::Type::Artist => {
+                let context = self.artist_context(ArtistId::from_id(id)?).await?;
+                Ok((context, None))
+            }
+            rspotify_model::Type::Show => {
+                let context = self.show_context(ShowId::from_id(id)?).await?;
+                Ok((context, None))
+            }
+            rspotify_model::Type::Track => {
+                let track_id = TrackId::from_id(id)?.into_static();
+                let track = self
+                    .spotify
+                    .track(track_id.as_ref(), Some(Market::FromToken))
+                    .await?;
+                let Some(album_id) = track.album.id else {
+                    anyhow::bail!("the track {track_id} has no album");
+                };
+                let context = self.album_context(album_id).await?;
+                Ok((context, Some(track_id)))
+            }
+            kind => anyhow::bail!("unsupported {kind:?} URI or link: {input}"),
+        }
+    }
+
+    /// Get a show context data
+    pub async fn show_context(&self, show_id: ShowId<'_>) -> Result<Context> {
+        let show_uri = show_id.uri();
+        tracing::info!("Get show context: {}", show_uri);
+
+        let show = self.podcast_show(show_id).await?;
+        let (show, episodes) = self.show_with_episodes(show).await?;
+
+        Ok(Context::Show { show, episodes })
+    }
+
+    /// Get multiple artists by their ids, fetched in concurrent batches.
+    ///
+    /// The ids of the artists that were not found are reported in `BatchReport::missing`.
+    pub async fn artists_batch(&self, ids: Vec<ArtistId<'_>>) -> Result<BatchReport<Artist>> {
+        let ids = ids.iter().map(|id| id.id().to_string()).collect::<Vec<_>>();
+        let artists = self
+            .several_items::<rspotify_model::FullArtist>(
+                "artists",
+                &ids,
+                ARTISTS_BATCH_LIMIT,
+                &Query::new(),
+            )
+            .await?;
+        Ok(BatchReport::from_items(artists, Artist::from))
+    }
+
+    /// Get multiple albums by their ids, fetched in concurrent batches.
+    ///
+    /// The ids of the albums that were not found (e.g. unavailable in the user's market)
+    /// are reported in `BatchReport::missing`.
+    pub async fn albums_batch(&self, ids: Vec<AlbumId<'_>>) -> Result<BatchReport<Album>> {
+        let ids = ids.iter().map(|id| id.id().to_string()).collect::<Vec<_>>();
+        let albums = self
+            .several_items::<rspotify_model::FullAlbum>(
+                "albums",
+                &ids,
+                ALBUMS_BATCH_LIMIT,
+                &market_query(),
+            )
+            .await?;
+        Ok(BatchReport::from_items(albums, Album::from))
+    }
+
+    /// Get multiple albums along with their tracks.
+    ///
+    /// The albums are fetched in batches, each album including the first page of its tracks,
+    /// so that the remaining tracks are only requested for the albums with more tracks.
+    pub async fn albums_with_tracks(
+        &self,
+        album_ids: &[AlbumId<'_>],
+    ) -> Result<Vec<(Album, Vec<Track>)>> {
+        collect_albums_with_tracks(
+            album_ids,
+            |batch| async move {
+                let albums = self.albums(batch, Some(Market::FromToken)).await?;
+                anyhow::Ok(albums)
+            },
+            |first_page| async move { self.all_paging_items(first_page, &Query::new()).await },
+        )
+        .await
+    }
+
+    /// Get the top tracks of an artist in a market, the current user's market if `None`
+    pub async fn artist_top_tracks_converted(
+        &self,
+        artist_id: ArtistId<'_>,
+        market: Option<Market>,
+    ) -> Result<Vec<Track>> {
+        let tracks = self
+            .spotify
+            .artist_top_tracks(artist_id, Some(market.unwrap_or(Market::FromToken)))
+            .await?;
+        Ok(tracks
+            .into_iter()
+            .filter_map(Track::try_from_full_track)
+            .collect())
+    }
+
+    /// Get an artist context data
+    pub async fn artist_context(&self, artist_id: ArtistId<'_>) -> Result<Context> {
+        self.fetch_artist_context(artist_id, true, &FetchOptions::default())
+            .await
+    }
+
+    /// Get an artist context data, without the related artists if `related_artists` is `false`,
+    /// which saves a request to an endpoint deprecated by Spotify. The top tracks and albums
+    /// are fetched in the options' market, and the context is read from the persisted contexts
+    /// following the options' cache policy.
+    ///
+    /// The page size and `max_items` don't apply. A context without the related artists
+    /// isn't persisted.
+    pub async fn artist_context_with(
+        &self,
+        artist_id: ArtistId<'_>,
+        related_artists: bool,
+        options: &FetchOptions,
+    ) -> Result<Context> {
+        if !related_artists || !options.persists_context() {
+            return self
+                .fetch_artist_context(artist_id, related_artists, options)
+                .await;
+        }
+
+        let uri = artist_id.uri();
+        let cached = match self.cached_context(&uri, options.cache) {
+            Some((cached, true)) => return Ok(cached.context),
+            cached => cached.map(|(cached, _)| cached),
+        };
+
+        self.fetch_context(&uri, cached, self.artist_context(artist_id))
+            .await
+    }
+
+    /// Fetch an artist context data, see `Client::artist_context_with`
+    async fn fetch_artist_context(
+        &self,
+        artist_id: ArtistId<'_>,
+        related_artists: bool,
+        options: &FetchOptions,
+    ) -> Result<Context> {
+        let artist_uri = artist_id.uri();
+        tracing::info!("Get artist context: {}", artist_uri);
+
+        // get the artist's information, including top tracks, related artists, and albums
+
+        let artist = self.artist(artist_id.as_ref()).await?.into();
+
+        let top_tracks = self
+            .artist_top_tracks_converted(artist_id.as_ref(), options.market)
+            .await?;
+
+        let related_artists = if related_artists {
+            let related_artists = self.artist_related_artists(artist_id.as_ref()).await?;
+            related_artists
+                .into_iter()
+                .map(|a| a.into())
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+
+        let albums_options = ArtistAlbumsOptions {
+            market: options.market,
+            ..Default::default()
+        };
+        let albums = self
+            .artist_albums_with(artist_id.as_ref(), &albums_options)
+            .await?;
+
+        Ok(Context::Artist {
+            artist,
+            top_tracks,
+            albums,
+            related_artists,
+        })
+    }
+
+    /// Start a playback of a context's tracks in a smart shuffled order,
+    /// see `utils::smart_shuffle`
+    pub async fn play_context_smart_shuffled(
+        &self,
+        context: &Context,
+        constraints: ShuffleConstraints,
+    ) -> Result<()> {
+        let tracks = context.tracks();
+        if tracks.is_empty() {
+            anyhow::bail!("cannot play a context without tracks");
+        }
+
+        let order = crate::utils::smart_shuffle(tracks, None, constraints);
+        let ids = order
+            .into_iter()
+            .map(|i| PlayableId::Track(tracks[i].id.as_ref()))
+            .collect::<Vec<_>>();
+        tracing::info!("Start a smart shuffled playback of {} tracks", ids.len());
+
+        self.start_uris_playback(ids, None, None, None).await?;
+        Ok(())
+    }
+
+    /// Make a GET HTTP request to the Spotify server,
+    /// retrying a rate-limited or transiently failed request (see `retry::send_with_retry`).
+    ///
+    /// An unauthorized (401) request, e.g. because the access token expired during a long
+    /// pagination, is retried once after re-authenticating the session and refreshing the token.
+    ///
+    /// If enabled, the successful responses are cached, see `AppConfig::cache`.
+    /// The concurrent identical requests (e.g. from tasks sharing the client) are coalesced
+    /// into a single request.
+    async fn http_get<T>(&self, url: &str, payload: &Query<'_>) -> Result<T>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        self.http_get_with(url, payload, operation::cache_control())
+            .await
+    }
+
+    /// Make a GET HTTP request to the Spotify server (see `Client::http_get`),
+    /// using the response cache following the cache control
+    async fn http_get_with<T>(
+        &self,
+        url: &str,
+        payload: &Query<'_>,
+        cache_control: CacheControl,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        operation::check()?;
+
+        let key = cache::ResponseCache::key(url, payload);
+        if let Some(cache) = &self.cache {
+            if cache_control == CacheControl::Default {
+                if let Some(text) = cache.get(&key) {
+                    tracing::debug!("cached response of {url}");
+                    return Ok(serde_json::from_str(&text)?);
+                }
+            }
+        }
+
+        // the concurrent identical requests share a single response
+        let text = self
+            .in_flight
+            .run(key.clone(), || async {
+                let text = self.http_get_text(url, payload).await?;
+                if let Some(cache) = &self.cache {
+                    cache.insert(key, url, text.clone());
+                }
+                Ok(text)
+            })
+            .await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Make a GET HTTP request to the Spotify server (see `Client::http_get`),
+    /// returning the response's text
+    async fn http_get_text(&self, url: &str, payload: &Query<'_>) -> Result<String> {
+        let (status, text) = self
+            .http_request(reqwest::Method::GET, url, payload, None)
+            .await?;
+        if !status.is_success() {
+            return Err(api_error(status, url, &text));
+        }
+        let text = self.quirks.read().fix_response(text);
+        tracing::debug!("{text}");
+        Ok(text)
+    }
+
+    /// Make an HTTP request with a JSON body to the Spotify server, returning the response's
+    /// JSON fixed like the responses of `Client::http_get`. An empty response is read as `null`.
+    async fn http_json<T, B>(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        payload: &Query<'_>,
+        body: Option<&B>,
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        B: serde::Serialize + ?Sized,
+    {
+        let body = body.map(json_body).transpose()?;
+        let (status, text) = self.http_request(method, url, payload, body).await?;
+        if !status.is_success() {
+            return Err(api_error(status, url, &text));
+        }
+        let text = self.quirks.read().fix_response(text);
+        tracing::debug!("{text}");
+        json_response(&text)
+    }
+
+    /// Make a PUT HTTP request with a JSON body to the Spotify server, see `Client::http_json`
+    async fn http_put<T, B>(&self, url: &str, payload: &Query<'_>, body: &B) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        B: serde::Serialize + ?Sized,
+    {
+        self.http_json(reqwest::Method::PUT, url, payload, Some(body))
+            .await
+    }
+
+    /// Make a DELETE HTTP request without a body to the Spotify server, see `Client::http_json`
+    async fn http_delete<T>(&self, url: &str, payload: &Query<'_>) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.http_json::<T, ()>(reqwest::Method::DELETE, url, payload, None)
+            .await
+    }
+
+    /// Make an HTTP request without a body to the Spotify server, ignoring the response's
+    /// JSON, see `Client::http_json`
+    async fn http_send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        payload: &Query<'_>,
+    ) -> Result<()> {
+        self.http_json::<serde::de::IgnoredAny, ()>(method, url, payload, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Make an HTTP request to the Spotify server with an optional body (its content type
+    /// and bytes), returning the response's status code and text, whether the request
+    /// succeeded or not. This is the core of the HTTP helpers:
+    /// - the request is authorized with the session's access token
+    /// - the failed attempts are retried following the retry configuration,
+    ///   only the idempotent requests being retried after a transient server error
+    /// - an unauthorized request is sent again once after re-authenticating the session
+    async fn http_request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        payload: &Query<'_>,
+        body: Option<(&str, Vec<u8>)>,
+    ) -> Result<(reqwest::StatusCode, String)> {
+        operation::check()?;
+        self.wait_for_connection().await?;
+
+        let idempotent = matches!(method, reqwest::Method::GET | reqwest::Method::HEAD);
+        let mut reauthenticated = false;
+        let response = loop {
+            let access_token = self.access_token().await?;
+
+            tracing::debug!("{access_token} {method} {url}");
+
+            let response = retry::send_with_retry(&self.retry, idempotent, || {
+                let mut request = self
+                    .http
+                    .request(method.clone(), url)
+                    .query(payload)
+                    .header(
+                        reqwest::header::AUTHORIZATION,
+                        format!("Bearer {access_token}"),
+                    );
+                if let Some((content_type, body)) = &body {
+                    request = request
+                        .header(reqwest::header::CONTENT_TYPE, *content_type)
+                        .body(body.clone());
+                }
+                async move {
+                    let _permit = self.request_permit().await;
+                    request.send().await
+                }
+            })
+            .await?;
+
+            if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+                break response;
+            }
+            // the refreshed token is also rejected, re-authenticating again won't help
+            if reauthenticated {
+                return Err(ClientError::Unauthorized.into());
+            }
+            tracing::warn!("{method} request to {url} is unauthorized, re-authenticating...");
+            self.reauthenticate().await?;
+            reauthenticated = true;
+        };
+
+        let status = response.status();
+        // the text is decompressed before the response quirks are fixed. The HTTP client
+        // removes the `Content-Encoding` of the responses it decompresses, so the requested
+        // encodings are logged instead
+        let text = response.text().await?;
+        tracing::debug!(
+            "{method} {url}: {status}, {} bytes, accept-encoding: {}",
+            text.len(),
+            self.accept_encoding.unwrap_or("unknown")
+        );
+        Ok((status, text))
+    }
+
+    /// Make an HTTP request to an audiobooks endpoint, returning the response's text.
+    /// Audiobooks are unavailable in some markets, in which case the endpoints are forbidden
+    /// with a message telling so (see `ApiError::is_unavailable_in_market`).
+    async fn audiobooks_request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        payload: &Query<'_>,
+    ) -> Result<String> {
+        let (status, text) = self.http_request(method, url, payload, None).await?;
+        if !status.is_success() {
+            let err = ApiError::from_response(status.as_u16(), url, &text);
+            if err.is_unavailable_in_market() {
+                return Err(ClientError::FeatureUnavailableInMarket("audiobooks").into());
+            }
+            return Err(ClientError::Api(err).into());
+        }
+        Ok(text)
+    }
+
+    /// Make a PUT HTTP request with a raw (non-JSON) body to the Spotify server
+    async fn http_put_raw(&self, url: &str, content_type: &str, body: Vec<u8>) -> Result<()> {
+        let (status, text) = self
+            .http_request(
+                reqwest::Method::PUT,
+                url,
+                &Query::new(),
+                Some((content_type, body)),
+            )
+            .await?;
+        if !status.is_success() {
+            return Err(api_error(status, url, &text));
+        }
+        Ok(())
+    }
+
+    /// Follow or unfollow artists or users as the current user, in batches.
+    /// `kind` is the type of the ids, either `"artist"` or `"user"`.
+    async fn update_following(&self, kind: &str, ids: &[&str], follow: bool) -> Result<()> {
+        let url = format!("{SPOTIFY_API_ENDPOINT}/me/following");
+        paging::map_chunks(ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+            let url = &url;
+            async move {
+                if follow {
+                    let body = serde_json::json!({ "ids": chunk });
+                    self.http_put::<serde::de::IgnoredAny, _>(
+                        url,
+                        &Query::from([("type", kind)]),
+                        &body,
+                    )
+                    .await?;
+                } else {
+                    let ids = chunk.join(",");
+                    self.http_delete::<serde::de::IgnoredAny>(
+                        url,
+                        &Query::from([("type", kind), ("ids", ids.as_str())]),
+                    )
+                    .await?;
+                }
+                anyhow::Ok(Vec::<()>::new())
+            }
+        })
+        .await?;
+        Ok(())
+    }
 
-        // get the artist's information, including top tracks, related artists, and albums
+    /// Check if the current user follows artists or users, in batches.
+    /// `kind` is the type of the ids, either `"artist"` or `"user"`.
+    async fn check_following(&self, kind: &str, ids: &[&str]) -> Result<Vec<bool>> {
+        let url = format!("{SPOTIFY_API_ENDPOINT}/me/following/contains");
+        paging::map_chunks(ids, LIBRARY_ITEMS_LIMIT, |chunk| {
+            let url = &url;
+            async move {
+                let ids = chunk.join(",");
+                self.http_get::<Vec<bool>>(
+                    url,
+                    &Query::from([("type", kind), ("ids", ids.as_str())]),
+                )
+                .await
+            }
+        })
+        .await
+    }
 
-        let artist = self.artist(artist_id.as_ref()).await?.into();
+    /// Get several items of an endpoint (e.g. `artists`) by their ids, in batches of at most
+    /// `batch_size` ids fetched `MAX_CONCURRENT_REQUESTS` at a time.
+    ///
+    /// The ids are paired with their items in the ids' order, `None` for the items
+    /// that were not found.
+    async fn several_items<T>(
+        &self,
+        endpoint: &str,
+        ids: &[String],
+        batch_size: usize,
+        payload: &Query<'_>,
+    ) -> Result<Vec<(String, Option<T>)>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        items_by_id(ids, batch_size, |batch| async move {
+            let ids = batch.join(",");
+            let mut payload = payload.clone();
+            payload.insert("ids", &ids);
+            // the response lists the items under the endpoint's name, e.g. `{"artists": [...]}`
+            let mut response = self
+                .http_get::<HashMap<String, Vec<Option<T>>>>(
+                    &format!("{SPOTIFY_API_ENDPOINT}/{endpoint}"),
+                    &payload,
+                )
+                .await?;
+            anyhow::Ok(response.remove(endpoint).unwrap_or_default())
+        })
+        .await
+    }
 
-        let top_tracks = self
-            .artist_top_tracks(artist_id.as_ref(), Some(Market::FromToken))
+    /// Get the features used to generate playlists of tracks, see `Client::audio_features`
+    async fn tracks_audio_features(
+        &self,
+        track_ids: &[TrackId<'_>],
+    ) -> Result<Vec<Option<generate::TrackFeatures>>> {
+        let features = self
+            .audio_features(track_ids.iter().map(|id| id.as_ref()).collect())
             .await?;
-        let top_tracks = top_tracks
-            .into_iter()
-            .filter_map(Track::try_from_full_track)
-            .collect::<Vec<_>>();
-
-        let related_artists = self.artist_related_artists(artist_id.as_ref()).await?;
-        let related_artists = related_artists
+        Ok(features
             .into_iter()
-            .map(|a| a.into())
-            .collect::<Vec<_>>();
-
-        let albums = self.artist_albums(artist_id.as_ref()).await?;
+            .map(|f| {
+                f.map(|f| generate::TrackFeatures {
+                    tempo: f.tempo,
+                    energy: f.energy,
+                    acousticness: f.acousticness,
+                })
+            })
+            .collect())
+    }
 
-        Ok(Context::Artist {
-            artist,
-            top_tracks,
-            albums,
-            related_artists,
-        })
+    /// Check that a playlist's current snapshot id is the `expected` one,
+    /// returning a `ClientError::SnapshotMismatch` otherwise
+    async fn check_snapshot(&self, playlist_id: PlaylistId<'_>, expected: &str) -> Result<()> {
+        let actual = self.refresh_snapshot(playlist_id).await?;
+        ensure_snapshot(expected, actual)
     }
 
-    /// Make a GET HTTP request to the Spotify server
-    async fn http_get<T>(&self, url: &str, payload: &Query<'_>) -> Result<T>
-        where
-            T: serde::de::DeserializeOwned,
-    {
-        /// a helper function to process an API response from Spotify server
-        ///
-        /// This function is mainly used to patch upstream API bugs , resulting in
-        /// a type error when a third-party library like `rspotify` parses the response
-        fn process_spotify_api_response(text: String) -> String {
-            // See: https://github.com/ramsayleung/rspotify/issues/459
-            text.replace("\"images\":null", "\"images\":[]")
+    /// Get the current snapshot id of a playlist, identifying the playlist's version.
+    ///
+    /// A snapshot id can be passed to the playlist's mutations, so that they fail
+    /// instead of applying to a playlist modified concurrently.
+    pub async fn refresh_snapshot(&self, playlist_id: PlaylistId<'_>) -> Result<String> {
+        #[derive(Debug, Deserialize)]
+        struct PlaylistSnapshot {
+            snapshot_id: String,
         }
 
-        let access_token = self.access_token().await?;
+        // the snapshot id is never read from the response cache to detect the latest changes
+        Ok(self
+            .http_get_with::<PlaylistSnapshot>(
+                &format!("{SPOTIFY_API_ENDPOINT}/playlists/{}", playlist_id.id()),
+                &Query::from([("fields", "snapshot_id")]),
+                CacheControl::NoCache,
+            )
+            .await?
+            .snapshot_id)
+    }
 
-        tracing::debug!("{access_token} {url}");
+    /// Get the URIs of all items in a playlist ordered by their positions.
+    /// The URIs of an unavailable item are `None`.
+    async fn playlist_item_uris(
+        &self,
+        playlist_id: PlaylistId<'_>,
+    ) -> Result<Vec<Option<PlaylistItemUris>>> {
+        let payload = &playlist_item_uris_query();
+        let first_url = format!(
+            "{SPOTIFY_API_ENDPOINT}/playlists/{}/tracks?limit={PLAYLIST_ITEMS_LIMIT}",
+            playlist_id.id()
+        );
+        let items = paging::collect_pages(vec![], Some(first_url), |url| async move {
+            let page = self.http_get::<PlaylistItemUrisPage>(&url, payload).await?;
+            anyhow::Ok((page.items, page.next))
+        })
+        .await?;
 
-        let response = self
-            .http
-            .get(url)
-            .query(payload)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {access_token}"),
-            )
-            .send()
-            .await?;
+        Ok(items.into_iter().map(|i| i.track).collect())
+    }
 
-        let text = process_spotify_api_response(response.text().await?);
-        tracing::debug!("{text}");
+    /// Get the total number of items of a paginated collection by requesting a single item,
+    /// which is much faster than paginating the collection
+    async fn collection_total(&self, url: &str) -> Result<usize> {
+        // only the `total` field of the `Page` is deserialized, so that the items
+        // do not need to be valid (see `Quirk`)
+        #[derive(Debug, Deserialize)]
+        struct PageTotal {
+            total: usize,
+        }
 
-        Ok(serde_json::from_str(&text)?)
+        let page = self
+            .http_get::<PageTotal>(url, &Query::from([("limit", "1")]))
+            .await?;
+        Ok(page.total)
     }
 
-    /// Get all paging items starting from a pagination object of the first page
+    /// Get all paging items starting from a pagination object of the first page,
+    /// fetching the next pages concurrently, see `paging::collect_pages_concurrently`.
+    /// Use `Client::page_stream` to process the pages as they are fetched.
     async fn all_paging_items<T>(
         &self,
         first_page: rspotify_model::Page<T>,
         payload: &Query<'_>,
     ) -> Result<Vec<T>>
-        where
-            T: serde::de::DeserializeOwned,
+    where
+        T: serde::de::DeserializeOwned,
     {
-        let mut items = first_page.items;
-        let mut maybe_next = first_page.next;
+        paging::collect_pages_concurrently(
+            first_page.items,
+            first_page.next,
+            first_page.total as usize,
+            MAX_CONCURRENT_REQUESTS,
+            |url| async move {
+                let page = self
+                    .http_get::<rspotify_model::Page<T>>(&url, payload)
+                    .await?;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await
+    }
 
-        while let Some(url) = maybe_next {
-            let mut next_page = self
-                .http_get::<rspotify_model::Page<T>>(&url, payload)
-                .await?;
-            items.append(&mut next_page.items);
-            maybe_next = next_page.next;
+    /// Get all the items of a paginated podcast collection starting from its first page,
+    /// skipping its `null` items. See `Client::all_paging_items`.
+    async fn all_podcast_items<T>(
+        &self,
+        first_page: PodcastPage<T>,
+        payload: &Query<'_>,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let items = paging::collect_pages_concurrently(
+            first_page.items,
+            first_page.next,
+            first_page.total,
+            MAX_CONCURRENT_REQUESTS,
+            |url| async move {
+                let page = self.http_get::<PodcastPage<T>>(&url, payload).await?;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await?;
+        Ok(items.into_iter().flatten().collect())
+    }
+
+    /// Get the paging items starting from a pagination object of the first page,
+    /// at most `max_items` of the fetch options. See `Client::all_paging_items`.
+    async fn paging_items<T>(
+        &self,
+        first_page: rspotify_model::Page<T>,
+        payload: &Query<'_>,
+        options: &FetchOptions,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if options.max_items.is_none() {
+            return self.all_paging_items(first_page, payload).await;
         }
-        Ok(items)
+        paging::collect_pages_limited(
+            first_page.items,
+            first_page.next,
+            options.max_items,
+            |url| async move {
+                let page = self
+                    .http_get::<rspotify_model::Page<T>>(&url, payload)
+                    .await?;
+                anyhow::Ok((page.items, page.next))
+            },
+        )
+        .await
     }
 
     /// Get all cursor-based paging items starting from a pagination object of the first page
@@ -580,30 +4764,23 @@ impl Client {
         &self,
         first_page: rspotify_model::CursorBasedPage<T>,
     ) -> Result<Vec<T>>
-        where
-            T: serde::de::DeserializeOwned,
+    where
+        T: serde::de::DeserializeOwned,
     {
-        let mut items = first_page.items;
-        let mut maybe_next = first_page.next;
-        while let Some(url) = maybe_next {
-            let mut next_page = self
-                .http_get::<rspotify_model::CursorBasedPage<T>>(&url, &Query::new())
-                .await?;
-            items.append(&mut next_page.items);
-            maybe_next = next_page.next;
-        }
-        Ok(items)
+        use futures::TryStreamExt;
+
+        self.cursor_page_stream(first_page).try_concat().await
     }
 
     /// Create a new playlist
-    async fn create_new_playlist(
+    pub async fn create_new_playlist(
         &self,
         user_id: UserId<'static>,
         playlist_name: &str,
         public: bool,
         collab: bool,
         desc: &str,
-    ) -> Result<()> {
+    ) -> Result<Playlist> {
         let playlist: Playlist = self
             .user_playlist_create(
                 user_id,
@@ -620,27 +4797,758 @@ impl Client {
             playlist.id
         );
 
+        Ok(playlist)
+    }
+
+    /// Follow a playlist, adding it to the current user's library.
+    ///
+    /// A followed playlist is listed in the user's public profile if `public` is true.
+    pub async fn follow_playlist(&self, playlist_id: PlaylistId<'_>, public: bool) -> Result<()> {
+        self.playlist_follow(playlist_id, Some(public)).await?;
+        Ok(())
+    }
+
+    /// Unfollow a playlist, removing it from the current user's library.
+    ///
+    /// Spotify doesn't delete playlists: "deleting" a playlist owned by the user unfollows it,
+    /// so that it can be restored by following it again. Unfollowing a playlist which is
+    /// not followed succeeds.
+    pub async fn unfollow_playlist(&self, playlist_id: PlaylistId<'_>) -> Result<()> {
+        if let Err(err) = self.playlist_unfollow(playlist_id.as_ref()).await {
+            // the playlist may have been unfollowed already
+            if self.is_following_playlist(playlist_id.as_ref()).await? {
+                return Err(err.into());
+            }
+            tracing::debug!("{} is not followed: {err:#}", playlist_id.uri());
+        }
         Ok(())
     }
 
+    /// Check if the current user follows a playlist
+    pub async fn is_following_playlist(&self, playlist_id: PlaylistId<'_>) -> Result<bool> {
+        let user_id = self.current_user_id().await?;
+        let follows = self.playlist_check_follow(playlist_id, &[user_id]).await?;
+        Ok(follows.first().copied().unwrap_or_default())
+    }
 
     /// Process a list of albums, which includes
-    /// - sort albums by the release date
-    /// - remove albums with duplicated names
-    fn process_artist_albums(&self, albums: Vec<Album>) -> Vec<Album> {
-        let mut albums = albums.into_iter().collect::<Vec<_>>();
+    /// - sort albums by the release date, newest first
+    /// - remove albums with duplicated names, according to the options' dedup strategy
+    fn process_artist_albums(mut albums: Vec<Album>, options: &ArtistAlbumsOptions) -> Vec<Album> {
+        albums.sort_by(|x, y| {
+            y.release_date
+                .partial_cmp(&x.release_date)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        // the kept release of albums with the same name comes first
+        if options.keep == KeepRelease::Earliest {
+            albums.reverse();
+        }
+        let mut albums = match options.dedup {
+            DedupStrategy::None => albums,
+            DedupStrategy::ExactName => crate::utils::dedup_by_key(albums, |a| a.name.clone()),
+            DedupStrategy::NormalizedName => {
+                crate::utils::dedup_by_key(albums, |a| crate::utils::normalize_title(&a.name))
+            }
+        };
+        if options.keep == KeepRelease::Earliest {
+            albums.reverse();
+        }
+        albums
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// applies a Spotify reorder operation to a list
+    fn reorder<T: Clone>(
+        items: &[T],
+        range_start: usize,
+        insert_before: usize,
+        range_length: usize,
+    ) -> Vec<T> {
+        let range = items[range_start..range_start + range_length].to_vec();
+        let mut result = vec![];
+        for (i, item) in items.iter().enumerate() {
+            if i == insert_before {
+                result.extend(range.iter().cloned());
+            }
+            if !(range_start..range_start + range_length).contains(&i) {
+                result.push(item.clone());
+            }
+        }
+        if insert_before == items.len() {
+            result.extend(range);
+        }
+        result
+    }
+
+    #[test]
+    fn reorder_to_first_position() {
+        let items = ["a", "b", "c", "d", "e"];
+        let insert_before = reorder_insert_before(3, 0, 1);
+        assert_eq!(insert_before, 0);
+        assert_eq!(
+            reorder(&items, 3, insert_before, 1),
+            ["d", "a", "b", "c", "e"]
+        );
+    }
+
+    #[test]
+    fn reorder_to_last_position() {
+        let items = ["a", "b", "c", "d", "e"];
+        let insert_before = reorder_insert_before(1, 4, 1);
+        assert_eq!(insert_before, 5);
+        assert_eq!(
+            reorder(&items, 1, insert_before, 1),
+            ["a", "c", "d", "e", "b"]
+        );
+    }
+
+    #[test]
+    fn reorder_multi_item_range() {
+        let items = ["a", "b", "c", "d", "e", "f"];
+
+        // move `b`, `c` later so that `b` ends up at index 3
+        let insert_before = reorder_insert_before(1, 3, 2);
+        assert_eq!(
+            reorder(&items, 1, insert_before, 2),
+            ["a", "d", "e", "b", "c", "f"]
+        );
+
+        // move `d`, `e`, `f` earlier so that `d` ends up at index 1
+        let insert_before = reorder_insert_before(3, 1, 3);
+        assert_eq!(
+            reorder(&items, 3, insert_before, 3),
+            ["a", "d", "e", "f", "b", "c"]
+        );
+    }
 
-        albums.sort_by(|x, y| x.release_date.partial_cmp(&y.release_date).unwrap());
+    #[test]
+    fn podcast_search_results_with_nulls() {
+        let response = serde_json::from_str::<SearchResponse>(
+            r#"{
+                "shows": {"items": [
+                    null,
+                    {"id": "38bS44xjbVVZ3No3ByF1dJ", "name": "Show", "publisher": null,
+                     "description": "About", "images": null}
+                ]},
+                "episodes": {"items": [
+                    {"id": "512ojhOuo1ktJprKbVcKyQ", "name": "Episode", "description": null,
+                     "duration_ms": 60000, "release_date": null, "language": null},
+                    null
+                ]}
+            }"#,
+        )
+        .unwrap();
+
+        let results = SearchResults::from(response);
+        assert!(results.tracks.is_empty() && results.playlists.is_empty());
+        assert_eq!(results.shows.len(), 1);
+        assert_eq!(results.shows[0].name, "Show");
+        assert_eq!(results.shows[0].publisher, "");
+        assert_eq!(results.episodes.len(), 1);
+        assert_eq!(results.episodes[0].name, "Episode");
+        assert_eq!(
+            results.episodes[0].duration,
+            std::time::Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn saved_episodes_with_nulls() {
+        #[derive(Debug, Deserialize)]
+        struct SavedEpisode {
+            episode: Option<PodcastEpisode>,
+        }
+
+        let page = serde_json::from_str::<PodcastPage<SavedEpisode>>(
+            r#"{
+                "items": [
+                    {"added_at": "2024-05-17T00:00:00Z", "episode": {
+                        "id": "512ojhOuo1ktJprKbVcKyQ", "name": "Episode",
+                        "description": "Q&amp;A", "duration_ms": null, "release_date": "2024",
+                        "images": null, "language": null,
+                        "show": {"id": "38bS44xjbVVZ3No3ByF1dJ", "name": "Show",
+                                 "publisher": null, "images": null},
+                        "resume_point": {"fully_played": true, "resume_position_ms": null}
+                    }},
+                    {"added_at": "2024-05-17T00:00:00Z", "episode": null},
+                    null
+                ],
+                "next": "https://api.spotify.com/v1/me/episodes?offset=3&limit=3",
+                "total": 5
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(page.total, 5);
+        assert!(page.next.is_some());
+
+        let episodes = page
+            .items
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.episode)
+            .map(Episode::from)
+            .collect::<Vec<_>>();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].show_name, "Show");
+        assert_eq!(episodes[0].description, "Q&A");
+        assert_eq!(episodes[0].duration, std::time::Duration::ZERO);
+        assert_eq!(
+            episodes[0].resume_point,
+            Some(ResumePoint {
+                fully_played: true,
+                resume_position: std::time::Duration::ZERO,
+            })
+        );
+    }
+
+    #[test]
+    fn full_show_with_nulls() {
+        let mut show = serde_json::from_str::<PodcastShow>(
+            r#"{
+                "id": "38bS44xjbVVZ3No3ByF1dJ", "name": "Show", "publisher": "Publisher",
+                "description": null, "images": null, "languages": null,
+                "episodes": {
+                    "items": [
+                        {"id": "512ojhOuo1ktJprKbVcKyQ", "name": "Episode", "description": null,
+                         "duration_ms": 60000, "release_date": "2024-05-17", "images": null},
+                        null
+                    ],
+                    "next": "https://api.spotify.com/v1/shows/38bS44xjbVVZ3No3ByF1dJ/episodes",
+                    "total": 51
+                }
+            }"#,
+        )
+        .unwrap();
+
+        // the show's episodes are paginated from the show's first page of episodes
+        let first_page = show.episodes.take().unwrap();
+        assert_eq!((first_page.items.len(), first_page.total), (2, 51));
+        assert!(first_page.next.is_some());
+        let episode = Episode::from(first_page.items.into_iter().flatten().next().unwrap());
+        assert_eq!(episode.name, "Episode");
+        assert_eq!(episode.description, "");
+
+        let show = Show::from(show);
+        assert_eq!(
+            (show.name.as_str(), show.description.as_str()),
+            ("Show", "")
+        );
+    }
+
+    #[tokio::test]
+    async fn toggle_returns_resulting_state() {
+        let state = &std::sync::Mutex::new(false);
+        let checks = &std::sync::atomic::AtomicUsize::new(0);
+        let check = || async {
+            checks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            anyhow::Ok(vec![*state.lock().unwrap()])
+        };
+        let set = |target| async move {
+            *state.lock().unwrap() = target;
+            anyhow::Ok(())
+        };
+
+        assert!(toggle_state(check, set).await.unwrap());
+        assert!(*state.lock().unwrap());
+        assert!(!toggle_state(check, set).await.unwrap());
+        assert!(!*state.lock().unwrap());
+        // the state is checked before and after each write
+        assert_eq!(checks.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        // another device changes the item right after the write
+        let concurrent_set = |target: bool| async move {
+            *state.lock().unwrap() = !target;
+            anyhow::Ok(())
+        };
+        assert!(!toggle_state(check, concurrent_set).await.unwrap());
+        assert!(!*state.lock().unwrap());
+    }
+
+    #[test]
+    fn process_artist_albums_with_dedup_strategies() {
+        let album = |id: &str, name: &str, release_date: &str| Album {
+            id: AlbumId::from_id(id.to_string()).unwrap(),
+            release_date: release_date.to_string(),
+            name: name.to_string(),
+            artists: vec![],
+            group: None,
+        };
+        let albums = vec![
+            album("a1", "OK Computer", "1997-05-21"),
+            album("a2", "OK Computer OKNOTOK 1997 2017", "2017-06-23"),
+            album("a3", "OK Computer", "2009-03-24"),
+            album("a4", "OK Computer (Deluxe Edition)", "2009-03-24"),
+        ];
+        let ids = |options: ArtistAlbumsOptions| {
+            Client::process_artist_albums(albums.clone(), &options)
+                .into_iter()
+                .map(|a| a.id.id().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(ArtistAlbumsOptions::new()), vec!["a2", "a3", "a4"]);
+        assert_eq!(
+            ids(ArtistAlbumsOptions::new().keep(KeepRelease::Earliest)),
+            vec!["a2", "a4", "a1"]
+        );
+        assert_eq!(
+            ids(ArtistAlbumsOptions::new().dedup(DedupStrategy::None)),
+            vec!["a2", "a3", "a4", "a1"]
+        );
+        assert_eq!(
+            ids(ArtistAlbumsOptions::new().dedup(DedupStrategy::NormalizedName)),
+            vec!["a2", "a3"]
+        );
+    }
+
+    #[test]
+    fn validate_playlist_covers() {
+        let jpeg = [JPEG_MAGIC_BYTES, &[0; 16]].concat();
+        assert_eq!(
+            encode_playlist_cover(&jpeg).unwrap(),
+            "/9j/AAAAAAAAAAAAAAAAAAAAAA=="
+        );
+
+        let png = [PNG_MAGIC_BYTES, &[0; 16]].concat();
+        let err = encode_playlist_cover(&png).unwrap_err().to_string();
+        assert!(err.contains("PNG"), "{err}");
+        assert!(encode_playlist_cover(b"GIF89a").is_err());
+        assert!(encode_playlist_cover(&[]).is_err());
+
+        // the limit applies to the base64-encoded image, 4/3 of the image's size
+        let max_len = MAX_PLAYLIST_COVER_SIZE / 4 * 3;
+        let image =
+            |len: usize| [JPEG_MAGIC_BYTES, &vec![0; len - JPEG_MAGIC_BYTES.len()][..]].concat();
+        assert_eq!(
+            encode_playlist_cover(&image(max_len)).unwrap().len(),
+            MAX_PLAYLIST_COVER_SIZE
+        );
+        let err = encode_playlist_cover(&image(max_len + 1))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("exceeds"), "{err}");
+    }
+
+    fn playlist_item(uri: &str) -> Option<PlaylistItemUris> {
+        Some(PlaylistItemUris {
+            uri: uri.to_string(),
+            linked_from: None,
+        })
+    }
+
+    #[test]
+    fn group_playlist_removals() {
+        let uris = vec![
+            playlist_item("spotify:track:a"),
+            playlist_item("spotify:track:b"),
+            playlist_item("spotify:track:a"),
+            None,
+            Some(PlaylistItemUris {
+                uri: "spotify:track:relinked".to_string(),
+                linked_from: Some(LinkedFromUri {
+                    uri: "spotify:track:c".to_string(),
+                }),
+            }),
+        ];
+
+        // only the targeted occurrences are removed, by their stored URI
+        assert_eq!(
+            playlist_removals(&uris, vec![0, 4, 2, 0]).unwrap(),
+            vec![vec![
+                ("spotify:track:c", vec![4]),
+                ("spotify:track:a", vec![2, 0])
+            ]]
+        );
+        assert!(playlist_removals(&uris, vec![3]).is_err());
+        assert!(playlist_removals(&uris, vec![5]).is_err());
+        assert!(playlist_removals(&uris, vec![]).unwrap().is_empty());
+
+        // the chunks go from the highest positions to the lowest ones
+        let uris = (0..150)
+            .map(|i| playlist_item(&format!("spotify:track:{i}")))
+            .collect::<Vec<_>>();
+        let chunks = playlist_removals(&uris, (0..150).collect()).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), PLAYLIST_ITEMS_LIMIT);
+        assert_eq!(chunks[0][0].1, vec![149]);
+        assert_eq!(chunks[1].last().unwrap().1, vec![0]);
+    }
+
+    #[test]
+    fn match_relinked_playlist_items() {
+        // the items are only relinked in a market
+        assert_eq!(
+            playlist_item_uris_query().get("market"),
+            Some(&"from_token")
+        );
+
+        let page = serde_json::from_str::<PlaylistItemUrisPage>(
+            r#"{
+                "items": [
+                    {"track": {"uri": "spotify:track:Relinked", "linked_from": {"uri": "spotify:track:Original"}}},
+                    {"track": {"uri": "spotify:track:Kept"}},
+                    {"track": {"uri": "spotify:local:Artist:Album:Title:180"}},
+                    {"track": null}
+                ],
+                "next": null
+            }"#,
+        )
+        .unwrap();
+        assert!(page.next.is_none());
+        let items = page.items.into_iter().map(|i| i.track).collect::<Vec<_>>();
+
+        let relinked = items[0].as_ref().unwrap();
+        assert_eq!(relinked.stored_uri(), "spotify:track:Original");
+        assert_eq!(
+            relinked.track_ids().collect::<Vec<_>>(),
+            ["relinked", "original"]
+        );
+        assert_eq!(
+            items[1].as_ref().unwrap().stored_uri(),
+            "spotify:track:Kept"
+        );
+        // a local file doesn't have any track id
+        assert_eq!(items[2].as_ref().unwrap().track_ids().count(), 0);
+        assert!(items[3].is_none());
+    }
+
+    /// creates a full album of `total` tracks, including their first page of at most 50 tracks
+    fn full_album(id: &str, total: usize) -> rspotify_model::FullAlbum {
+        let track = |i: usize| {
+            serde_json::json!({
+                "artists": [],
+                "available_markets": [],
+                "disc_number": 1,
+                "duration_ms": 180000,
+                "explicit": false,
+                "external_urls": {},
+                "id": format!("{id}track{i}"),
+                "is_local": false,
+                "name": format!("track {i}"),
+                "track_number": i + 1,
+            })
+        };
+        let next = (total > 50)
+            .then(|| format!("{SPOTIFY_API_ENDPOINT}/albums/{id}/tracks?offset=50&limit=50"));
+        serde_json::from_value(serde_json::json!({
+            "artists": [],
+            "album_type": "album",
+            "available_markets": [],
+            "copyrights": [],
+            "external_ids": {},
+            "external_urls": {},
+            "genres": [],
+            "href": "",
+            "id": id,
+            "images": [],
+            "name": id,
+            "popularity": 0,
+            "release_date": "2024-01-01",
+            "release_date_precision": "day",
+            "tracks": {
+                "href": "",
+                "items": (0..std::cmp::min(total, 50)).map(track).collect::<Vec<_>>(),
+                "limit": 50,
+                "next": next,
+                "offset": 0,
+                "previous": null,
+                "total": total,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn top_up_only_albums_with_more_tracks() {
+        let album_ids = (0..40)
+            .map(|i| AlbumId::from_id(format!("album{i}")).unwrap())
+            .collect::<Vec<_>>();
+        let (mut batches, mut top_ups) = (0, 0);
+        let fetch_albums = |batch: Vec<AlbumId<'static>>| {
+            batches += 1;
+            async move {
+                assert!(batch.len() <= ALBUMS_BATCH_LIMIT);
+                // the first album has more tracks than its first page
+                let albums = batch
+                    .iter()
+                    .map(|id| full_album(id.id(), if id.id() == "album0" { 120 } else { 50 }))
+                    .collect::<Vec<_>>();
+                anyhow::Ok(albums)
+            }
+        };
+        let fetch_tracks = |page: Page<rspotify_model::SimplifiedTrack>| {
+            top_ups += 1;
+            async move {
+                let mut items = page.items;
+                while items.len() < page.total as usize {
+                    items.push(items[0].clone());
+                }
+                anyhow::Ok(items)
+            }
+        };
+
+        let albums = collect_albums_with_tracks(&album_ids, fetch_albums, fetch_tracks)
+            .await
+            .unwrap();
+        // 40 albums are fetched in 2 batches, only the album with more tracks is topped up
+        assert_eq!((batches, top_ups), (2, 1));
+        assert_eq!(albums.len(), 40);
+        assert_eq!(albums[0].1.len(), 120);
+        assert!(albums[1..].iter().all(|(_, tracks)| tracks.len() == 50));
+
+        // the tracks are converted along with their album
+        let (album, tracks) = &albums[1];
+        assert_eq!(album.id.id(), "album1");
+        assert_eq!(tracks[0].id.id(), "album1track0");
+        assert_eq!(tracks[0].album.as_ref().unwrap().id, album.id);
+    }
+
+    #[tokio::test]
+    async fn chain_snapshots_between_writes() {
+        let uris = (0..5).collect::<Vec<_>>();
+        let mut writes = vec![];
+        let snapshot_id = chain_snapshots(
+            uris.chunks(2),
+            Some("s0".to_string()),
+            |chunk, snapshot_id| {
+                writes.push((chunk.to_vec(), snapshot_id));
+                let next = format!("s{}", writes.len());
+                async move { anyhow::Ok(next) }
+            },
+        )
+        .await
+        .unwrap();
 
-        // use a HashSet to keep track albums with the same name
-        let mut seen_names = std::collections::HashSet::new();
+        // each write is made against the snapshot returned by the previous write
+        assert_eq!(
+            writes,
+            vec![
+                (vec![0, 1], Some("s0".to_string())),
+                (vec![2, 3], Some("s1".to_string())),
+                (vec![4], Some("s2".to_string())),
+            ]
+        );
+        assert_eq!(snapshot_id.as_deref(), Some("s3"));
+    }
+
+    #[tokio::test]
+    async fn keep_snapshot_without_writes() {
+        let snapshot_id = chain_snapshots(
+            std::iter::empty::<&[usize]>(),
+            Some("s0".to_string()),
+            |_, _| async { anyhow::Ok(String::new()) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(snapshot_id.as_deref(), Some("s0"));
+    }
+
+    #[tokio::test]
+    async fn stop_writes_on_cancellation() {
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let uris = (0..6).collect::<Vec<_>>();
+        let mut writes = 0;
+        let (result, _) = operation::scope(
+            None,
+            Some(token),
+            chain_snapshots(uris.chunks(2), None, |_, _| {
+                writes += 1;
+                // the operation is cancelled while the first write is in flight
+                cancel.cancel();
+                async { anyhow::Ok("s1".to_string()) }
+            }),
+        )
+        .await;
 
-        albums.into_iter().rfold(vec![], |mut acc, a| {
-            if !seen_names.contains(&a.name) {
-                seen_names.insert(a.name.clone());
-                acc.push(a);
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<ClientError>(),
+            Some(ClientError::Cancelled)
+        ));
+        assert_eq!(writes, 1);
+    }
+
+    #[test]
+    fn detect_snapshot_mismatch() {
+        ensure_snapshot("s1", "s1".to_string()).unwrap();
+
+        let err = ensure_snapshot("s1", "s2".to_string()).unwrap_err();
+        match err.downcast_ref::<ClientError>() {
+            Some(ClientError::SnapshotMismatch { expected, actual }) => {
+                assert_eq!((expected.as_str(), actual.as_str()), ("s1", "s2"));
             }
-            acc
+            _ => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn keep_unsorted_items_in_place() {
+        // an episode at 1 and an unavailable track at 3 are not sorted
+        let items = ["d", "episode", "b", "unavailable", "a", "c"].map(Some);
+        let sorted_positions = [4, 2, 5, 0];
+        assert_eq!(
+            sorted_items(&items, &sorted_positions).unwrap(),
+            ["a", "episode", "b", "unavailable", "c", "d"]
+        );
+
+        // a local file cannot be re-added
+        let items = [Some("b"), None, Some("a")];
+        assert_eq!(sorted_items(&items, &[2, 0]), None);
+    }
+
+    #[tokio::test]
+    async fn pair_batched_items_with_their_ids() {
+        let ids = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        // an item is not found in each batch
+        let items = items_by_id(&ids, 2, |batch| async move {
+            anyhow::Ok(
+                batch
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| (i == 0).then(|| id.to_uppercase()))
+                    .collect(),
+            )
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            items,
+            [
+                ("a", Some("A")),
+                ("b", None),
+                ("c", Some("C")),
+                ("d", None),
+                ("e", Some("E"))
+            ]
+            .map(|(id, item)| (id.to_string(), item.map(str::to_string)))
+        );
+
+        // the items of a short batch can't be matched to its ids
+        let err = items_by_id(&ids, 2, |batch| async move {
+            let len = if batch[0] == "c" { 1 } else { batch.len() };
+            anyhow::Ok(vec![Some(()); len])
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("expect 2 items"));
+    }
+
+    #[test]
+    fn resolve_configured_locale() {
+        let locale = |configured, lang| {
+            resolve_locale(configured, lang).map(|tag: LanguageTag| tag.as_str().to_string())
+        };
+
+        assert_eq!(
+            locale(Some("sv_SE"), Some("de_DE.UTF-8")).as_deref(),
+            Some("sv_SE")
+        );
+        // the unset locale falls back to `LANG`
+        assert_eq!(locale(None, Some("de_DE.UTF-8")).as_deref(), Some("de_DE"));
+        assert_eq!(locale(None, Some("C.UTF-8")), None);
+        assert_eq!(locale(None, None), None);
+        // an invalid configured locale falls back to Spotify's default
+        assert_eq!(locale(Some("swedish"), Some("de_DE.UTF-8")), None);
+    }
+
+    #[test]
+    fn quote_multi_word_track_search_filters() {
+        assert_eq!(
+            track_search_query("Bohemian Rhapsody", "Queen, David Bowie").to_string(),
+            r#"track:"Bohemian Rhapsody" artist:Queen"#
+        );
+        assert_eq!(
+            track_search_query("Heroes", " David Bowie ").to_string(),
+            r#"track:Heroes artist:"David Bowie""#
+        );
+        assert_eq!(track_search_query("Heroes", "").to_string(), "track:Heroes");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn never_back_off_already_retried_requests() {
+        // the rate limit errors of `Client::http_request` are returned after its own retries
+        let url = "https://api.spotify.com/v1/artists/0/albums";
+        let rate_limited = api_error(reqwest::StatusCode::TOO_MANY_REQUESTS, url, "");
+        assert_eq!(rate_limit_retry_after(&rate_limited), None);
+
+        let mut requests = 0;
+        let result = with_rate_limit_backoff(|| {
+            requests += 1;
+            async { Err::<(), _>(api_error(reqwest::StatusCode::TOO_MANY_REQUESTS, url, "")) }
         })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(requests, 1);
+    }
+
+    #[test]
+    fn serialize_requests_and_parse_responses() {
+        let (content_type, body) = json_body(&serde_json::json!({ "ids": ["a", "b"] })).unwrap();
+        assert_eq!(content_type, "application/json");
+        assert_eq!(body, br#"{"ids":["a","b"]}"#);
+
+        // an empty response (e.g. `204 No Content`) is read as `null`
+        json_response::<serde::de::IgnoredAny>("").unwrap();
+        assert_eq!(json_response::<Option<Vec<bool>>>(" \n").unwrap(), None);
+        assert_eq!(json_response::<Vec<bool>>("[true]").unwrap(), vec![true]);
+        // an ignored response may have a body, unlike a response read as `()`
+        json_response::<serde::de::IgnoredAny>(r#"{"snapshot_id":"s"}"#).unwrap();
+        assert!(json_response::<()>(r#"{"snapshot_id":"s"}"#).is_err());
+    }
+
+    /// gzips `data` in a single uncompressed (stored) deflate block
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let crc = !data.iter().fold(!0u32, |crc, &byte| {
+            (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+                (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+            })
+        });
+        let len = data.len() as u16;
+        let mut gzip = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+        gzip.extend(len.to_le_bytes());
+        gzip.extend((!len).to_le_bytes());
+        gzip.extend(data);
+        gzip.extend(crc.to_le_bytes());
+        gzip.extend((data.len() as u32).to_le_bytes());
+        gzip
+    }
+
+    #[tokio::test]
+    async fn fix_decompressed_responses() {
+        use std::io::{Read, Write};
+
+        // a mock server answering a request with a gzipped response
+        let body = gzip(br#"{"name":"a","images":null}"#);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/playlists/a", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; 4096];
+            let len = stream.read(&mut request).unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+            String::from_utf8_lossy(&request[..len]).to_lowercase()
+        });
+
+        let config = crate::config::HttpConfig::default();
+        let http = config.client_builder().no_proxy().build().unwrap();
+        let text = http.get(&url).send().await.unwrap().text().await.unwrap();
+        let request = server.join().unwrap();
+        assert!(request.contains("accept-encoding: gzip"), "{request}");
+        // the response is decompressed before its quirks are fixed
+        assert_eq!(
+            Quirks::default().fix_response(text),
+            r#"{"name":"a","images":[]}"#
+        );
     }
 }
@@ -0,0 +1,466 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio::sync::Notify;
+
+use super::{CacheControl, Client};
+use crate::error::ClientError;
+
+tokio::task_local! {
+    /// the scope of the logical operation being run by the current task
+    static OPERATION: OperationScope;
+}
+
+/// The state of a logical operation (e.g. a paginated fetch or a batch write),
+/// shared by all the requests made by the operation
+struct OperationScope {
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    cache_control: CacheControl,
+    /// whether the operation stopped early, leaving its result incomplete
+    incomplete: Cell<bool>,
+}
+
+/// A client whose operations are time-boxed by a deadline, cancellable and/or bypass
+/// the response cache, see `Client::with_deadline`, `Client::with_cancellation`
+/// and `Client::with_cache_control`
+pub struct OperationClient<'a> {
+    client: &'a Client,
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    cache_control: CacheControl,
+}
+
+/// A client whose operations are time-boxed by a deadline, see `Client::with_deadline`
+pub type DeadlineClient<'a> = OperationClient<'a>;
+
+#[derive(Debug, Clone, Default)]
+/// A token to cancel the operations run with it, see `Client::with_cancellation`
+pub struct CancellationToken {
+    state: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of an operation that may have stopped early
+pub struct Partial<T> {
+    pub value: T,
+    /// `false` if the operation stopped early at its deadline
+    pub complete: bool,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// cancels the operations run with the token
+    pub fn cancel(&self) {
+        if !self.state.cancelled.swap(true, Ordering::SeqCst) {
+            self.state.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// waits until the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // the notification is registered before checking the flag to not miss a cancellation
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Client {
+    /// Time-box the client's operations by a deadline.
+    ///
+    /// Under the deadline, every request checks the remaining budget before being sent
+    /// and long operations (pagination, batch writes) stop cleanly at the deadline,
+    /// returning partial results flagged as incomplete. A request past the deadline fails with
+    /// `ClientError::DeadlineExceeded`, wrapped in an I/O `rspotify::ClientError` for the requests
+    /// sent through rspotify. A single request is never interrupted,
+    /// so a batch write never has a half-applied mutation, and a write computed from a read
+    /// (e.g. a playlist's deduplication) fails with `ClientError::DeadlineExceeded`
+    /// instead of using the read's partial results.
+    pub fn with_deadline(&self, deadline: Instant) -> OperationClient<'_> {
+        OperationClient {
+            client: self,
+            deadline: Some(deadline),
+            cancellation: None,
+            cache_control: CacheControl::Default,
+        }
+    }
+
+    /// Make the client's operations cancellable with a token.
+    ///
+    /// Once the token is cancelled, the operations fail with `ClientError::Cancelled`
+    /// at their next cancellation point: before a request, at a page or chunk boundary,
+    /// or during a wait (e.g. for the session to reconnect). A single in-flight request
+    /// is never interrupted, so a batch write never has a half-applied mutation.
+    pub fn with_cancellation(&self, token: CancellationToken) -> OperationClient<'_> {
+        OperationClient {
+            client: self,
+            deadline: None,
+            cancellation: Some(token),
+            cache_control: CacheControl::Default,
+        }
+    }
+
+    /// Control how the client's operations use the response cache (see `AppConfig::cache`),
+    /// e.g. `CacheControl::NoCache` to fetch fresh responses
+    pub fn with_cache_control(&self, cache_control: CacheControl) -> OperationClient<'_> {
+        OperationClient {
+            client: self,
+            deadline: None,
+            cancellation: None,
+            cache_control,
+        }
+    }
+}
+
+impl<'a> OperationClient<'a> {
+    /// time-boxes the operations by a deadline, see `Client::with_deadline`
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// makes the operations cancellable with a token, see `Client::with_cancellation`
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// controls how the operations use the response cache, see `Client::with_cache_control`
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = cache_control;
+        self
+    }
+
+    /// gets the remaining time budget, `Duration::MAX` without a deadline
+    pub fn remaining(&self) -> Duration {
+        match self.deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => Duration::MAX,
+        }
+    }
+
+    /// runs an operation of the client under the deadline and/or the cancellation token.
+    ///
+    /// A cancelled operation fails with `ClientError::Cancelled`, even if it stopped
+    /// with partial results.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let tracks = client
+    ///     .with_deadline(Instant::now() + Duration::from_secs(60))
+    ///     .run(|client| client.current_user_saved_tracks())
+    ///     .await?;
+    /// if !tracks.complete {
+    ///     tracing::warn!("only got {} saved tracks before the deadline", tracks.value.len());
+    /// }
+    /// ```
+    pub async fn run<T, F, Fut>(&self, f: F) -> Result<Partial<T>>
+    where
+        F: FnOnce(&'a Client) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let (result, complete) = scope_with(
+            self.deadline,
+            self.cancellation.clone(),
+            self.cache_control,
+            f(self.client),
+        )
+        .await;
+        let cancelled = self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled);
+        if cancelled && !complete {
+            return Err(ClientError::Cancelled.into());
+        }
+        result.map(|value| Partial { value, complete })
+    }
+}
+
+/// runs a future inside a new operation scope with the given deadline and cancellation token,
+/// returning the future's output and whether the operation completed
+pub(crate) async fn scope<F: Future>(
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    f: F,
+) -> (F::Output, bool) {
+    scope_with(deadline, cancellation, CacheControl::Default, f).await
+}
+
+/// runs a future inside a new operation scope, see `scope`, whose requests use the response cache
+/// following the cache control
+async fn scope_with<F: Future>(
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    cache_control: CacheControl,
+    f: F,
+) -> (F::Output, bool) {
+    let scope = OperationScope {
+        deadline,
+        cancellation,
+        cache_control,
+        incomplete: Cell::new(false),
+    };
+    OPERATION
+        .scope(scope, async {
+            let output = f.await;
+            (output, !OPERATION.with(|o| o.incomplete.get()))
+        })
+        .await
+}
+
+impl OperationScope {
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// gets the error stopping the current operation, if it's cancelled or its deadline is reached
+pub(crate) fn stop_error() -> Option<ClientError> {
+    OPERATION
+        .try_with(|o| {
+            if o.is_cancelled() {
+                Some(ClientError::Cancelled)
+            } else if o.is_expired() {
+                Some(ClientError::DeadlineExceeded)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(None)
+}
+
+/// returns an error if the current operation is cancelled or its deadline is reached
+pub(crate) fn check() -> Result<()> {
+    match stop_error() {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// runs a read whose result feeds a write (e.g. the items of a playlist before removing some),
+/// failing with the operation's error instead of returning partial results if the read
+/// stopped early, so that the write is never made from an incomplete read
+pub(crate) async fn complete<T, F: Future<Output = Result<T>>>(read: F) -> Result<T> {
+    let value = read.await?;
+    check()?;
+    Ok(value)
+}
+
+/// checks whether a long operation should stop at a page/chunk boundary because its deadline
+/// is reached, marking the operation incomplete if so.
+///
+/// Returns `ClientError::Cancelled` if the operation is cancelled, as the partial results
+/// of a cancelled operation are discarded.
+pub(crate) fn should_stop() -> Result<bool> {
+    OPERATION
+        .try_with(|o| {
+            if o.is_cancelled() {
+                tracing::warn!("Operation is cancelled, stopping");
+                o.incomplete.set(true);
+                return Err(ClientError::Cancelled.into());
+            }
+            let stop = o.is_expired();
+            if stop {
+                tracing::warn!("Operation's deadline is reached, stopping with partial results");
+                o.incomplete.set(true);
+            }
+            Ok(stop)
+        })
+        .unwrap_or(Ok(false))
+}
+
+/// checks whether the current operation stopped early, leaving its result incomplete
+pub(crate) fn is_incomplete() -> bool {
+    OPERATION.try_with(|o| o.incomplete.get()).unwrap_or(false)
+}
+
+/// gets how the requests of the current operation use the response cache
+pub(crate) fn cache_control() -> CacheControl {
+    OPERATION.try_with(|o| o.cache_control).unwrap_or_default()
+}
+
+/// runs a future (e.g. a wait or a retry sleep) as a cancellation point of the current operation,
+/// returning `ClientError::Cancelled` as soon as the operation is cancelled
+pub(crate) async fn cancellable<F: Future>(f: F) -> Result<F::Output> {
+    let token = OPERATION
+        .try_with(|o| o.cancellation.clone())
+        .ok()
+        .flatten();
+    match token {
+        Some(token) => tokio::select! {
+            output = f => Ok(output),
+            _ = token.cancelled() => Err(ClientError::Cancelled.into()),
+        },
+        None => Ok(f.await),
+    }
+}
+
+/// runs a sequence of writes which must not stop half-way (e.g. replacing a playlist's items
+/// in several requests) outside of the current operation's deadline and cancellation,
+/// the operation stopping at its next cancellation point after the writes instead
+pub(crate) async fn uninterruptible<F: Future>(f: F) -> F::Output {
+    scope_with(None, None, cache_control(), f).await.0
+}
+
+/// runs a future (e.g. a refetch of data known to be stale) inside the current operation,
+/// its requests bypassing the cached responses (see `CacheControl::NoCache`)
+pub(crate) async fn no_cache<F: Future>(f: F) -> F::Output {
+    let (deadline, cancellation) = OPERATION
+        .try_with(|o| (o.deadline, o.cancellation.clone()))
+        .unwrap_or_default();
+    let (output, complete) = scope_with(deadline, cancellation, CacheControl::NoCache, f).await;
+    if !complete {
+        set_incomplete();
+    }
+    output
+}
+
+/// spawns a future (e.g. a prefetch of the next pages) as a task running inside the current
+/// operation, resolving to the future's output and whether the operation completed.
+///
+/// The task's stopping early isn't reported to the current operation,
+/// the caller reports it with `operation::set_incomplete`.
+pub(crate) fn spawn<F>(f: F) -> tokio::task::JoinHandle<(F::Output, bool)>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let (deadline, cancellation, cache_control) = OPERATION
+        .try_with(|o| (o.deadline, o.cancellation.clone(), o.cache_control))
+        .unwrap_or_default();
+    tokio::spawn(scope_with(deadline, cancellation, cache_control, f))
+}
+
+/// marks the current operation incomplete, e.g. when a task spawned by it stopped early
+pub(crate) fn set_incomplete() {
+    let _ = OPERATION.try_with(|o| o.incomplete.set(true));
+}
+
+/// sleeps inside the current operation, returning early with `ClientError::Cancelled`
+/// if the operation is cancelled
+pub(crate) async fn sleep(duration: Duration) -> Result<()> {
+    cancellable(tokio::time::sleep(duration)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_sleep() {
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let start = Instant::now();
+        let (result, _) = scope(None, Some(token.clone()), sleep(Duration::from_secs(10))).await;
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::Cancelled)
+        ));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // a cancelled token stays cancelled
+        assert!(token.is_cancelled());
+        token.cancelled().await;
+        let (result, _) = scope(None, Some(token), async { check() }).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn bypass_cache_inside_operation() {
+        let (output, complete) = scope(Some(Instant::now()), None, async {
+            no_cache(async { (cache_control(), should_stop().unwrap()) }).await
+        })
+        .await;
+        // the operation's deadline still applies, the stopped future leaving it incomplete
+        assert_eq!(output, (CacheControl::NoCache, true));
+        assert!(!complete);
+        assert_eq!(cache_control(), CacheControl::Default);
+    }
+
+    #[tokio::test]
+    async fn run_spawned_task_inside_operation() {
+        let ((), complete) = scope(Some(Instant::now()), None, async {
+            let (stop, task_complete) = spawn(async { should_stop().unwrap() }).await.unwrap();
+            // the spawned task stopped at the operation's deadline, without reporting it
+            assert!(stop && !task_complete);
+            assert!(!is_incomplete());
+            set_incomplete();
+        })
+        .await;
+        assert!(!complete);
+    }
+
+    #[tokio::test]
+    async fn sleep_outside_operation() {
+        sleep(Duration::from_millis(1)).await.unwrap();
+        assert!(check().is_ok());
+        assert!(!should_stop().unwrap());
+    }
+
+    #[tokio::test]
+    async fn never_interrupt_writes() {
+        let token = CancellationToken::new();
+        let mut writes = 0;
+        let (result, _) = scope(None, Some(token.clone()), async {
+            check()?;
+            uninterruptible(async {
+                for _ in 0..3 {
+                    // the operation is cancelled between the writes
+                    token.cancel();
+                    check()?;
+                    writes += 1;
+                }
+                anyhow::Ok(())
+            })
+            .await?;
+            check()
+        })
+        .await;
+
+        // all the writes are made, then the operation fails
+        assert_eq!(writes, 3);
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<ClientError>(),
+            Some(ClientError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,1413 @@
+use std::{future::Future, pin::Pin};
+
+use anyhow::Result;
+use futures::Stream;
+
+use super::operation;
+use crate::model::SearchResults;
+
+/// collects the items of a paginated collection, starting from the first page's items
+/// and the URL of the next page, fetching the next pages with `fetch_page`.
+///
+/// Pagination stops early (with partial items) if the current operation's deadline is reached,
+/// and fails with `ClientError::Cancelled` at the next page if the operation is cancelled.
+pub(crate) async fn collect_pages<T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    mut fetch_page: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>>,
+{
+    let mut items = vec![];
+    for_each_page(first_items, first_next, fetch_page, |mut page_items| {
+        items.append(&mut page_items);
+        Ok(())
+    })
+    .await?;
+    Ok(items)
+}
+
+/// collects the items of an offset-based paginated collection of `total` items, starting from
+/// the first page's items and the URL of the next page, fetching the next pages with
+/// `fetch_page` with at most `concurrency` pages fetched concurrently.
+///
+/// The URLs of the next pages are derived from the next page URL's `offset` and `limit`
+/// query parameters. If the URL has no such parameters, the pages are fetched sequentially
+/// like `collect_pages`. The pages after the `total` items (e.g. added since the first page
+/// was fetched) are then fetched sequentially, following the last page's next page URL.
+///
+/// Pagination stops early (with the items of the pages before the first skipped page)
+/// if the current operation's deadline is reached.
+pub(crate) async fn collect_pages_concurrently<T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    total: usize,
+    concurrency: usize,
+    mut fetch_page: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>>,
+{
+    let Some(urls) = first_next
+        .as_deref()
+        .and_then(|next| offset_page_urls(next, total))
+    else {
+        return collect_pages(first_items, first_next, fetch_page).await;
+    };
+
+    let pages = map_concurrently(urls, concurrency, |url| {
+        // the stop check runs before the page's request is sent
+        let page = operation::should_stop().map(|stop| (!stop).then(|| fetch_page(url)));
+        async move {
+            match page? {
+                Some(page) => anyhow::Ok(Some(page.await?)),
+                None => anyhow::Ok(None),
+            }
+        }
+    })
+    .await?;
+
+    let mut items = first_items;
+    let mut next = first_next;
+    for page in pages {
+        let Some((page_items, page_next)) = page else {
+            return Ok(items);
+        };
+        items.extend(page_items);
+        next = page_next;
+    }
+    collect_pages(items, next, fetch_page).await
+}
+
+/// gets the URLs of the pages of a collection of `total` items, starting from the URL
+/// of a page with `offset` and `limit` query parameters.
+/// Returns `None` if the URL is not offset-based.
+fn offset_page_urls(url: &str, total: usize) -> Option<Vec<String>> {
+    let (base, query) = url.split_once('?')?;
+    let param = |name: &str| {
+        query.split('&').find_map(|param| {
+            param
+                .strip_prefix(name)?
+                .strip_prefix('=')?
+                .parse::<usize>()
+                .ok()
+        })
+    };
+    let offset = param("offset")?;
+    let limit = param("limit").filter(|&limit| limit > 0)?;
+
+    let urls = (offset..total)
+        .step_by(limit)
+        .map(|offset| {
+            let query = query
+                .split('&')
+                .map(|param| {
+                    if param.starts_with("offset=") {
+                        format!("offset={offset}")
+                    } else {
+                        param.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            format!("{base}?{query}")
+        })
+        .collect();
+    Some(urls)
+}
+
+/// visits the items of a paginated collection page by page, so that the collection
+/// never needs to be materialized in memory. See `collect_pages`.
+pub(crate) async fn for_each_page<T, F, Fut, V>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    mut fetch_page: F,
+    mut visit: V,
+) -> Result<()>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>>,
+    V: FnMut(Vec<T>) -> Result<()>,
+{
+    visit(first_items)?;
+    let mut maybe_next = first_next;
+
+    while let Some(url) = maybe_next {
+        if operation::should_stop()? {
+            break;
+        }
+        let (next_items, next) = fetch_page(url).await?;
+        visit(next_items)?;
+        maybe_next = next;
+    }
+    Ok(())
+}
+
+/// collects the first `limit` items (all items if `None`) of a paginated collection,
+/// without fetching the pages after the `limit`-th item. See `collect_pages`.
+pub(crate) async fn collect_pages_limited<T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    limit: Option<usize>,
+    mut fetch_page: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>>,
+{
+    let limit = limit.unwrap_or(usize::MAX);
+    let (mut items, mut maybe_next) = (first_items, first_next);
+    while items.len() < limit {
+        let Some(url) = maybe_next else {
+            break;
+        };
+        if operation::should_stop()? {
+            break;
+        }
+        let (mut next_items, next) = fetch_page(url).await?;
+        items.append(&mut next_items);
+        maybe_next = next;
+    }
+    items.truncate(limit);
+    Ok(items)
+}
+
+/// collects the items of a paginated collection up to the first item not satisfying `keep`,
+/// without fetching the pages after it. See `collect_pages`.
+///
+/// This suits collections sorted by the kept property, e.g. the newest items first.
+pub(crate) async fn collect_pages_while<T, F, Fut, P>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    mut fetch_page: F,
+    mut keep: P,
+) -> Result<Vec<T>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>>,
+    P: FnMut(&T) -> bool,
+{
+    let mut items = vec![];
+    let (mut page_items, mut maybe_next) = (first_items, first_next);
+    loop {
+        let count = page_items.len();
+        let mut kept = page_items
+            .into_iter()
+            .take_while(|item| keep(item))
+            .collect::<Vec<_>>();
+        // the next pages are only needed if the whole page was kept
+        let complete = kept.len() == count;
+        items.append(&mut kept);
+
+        let Some(url) = maybe_next.filter(|_| complete) else {
+            break;
+        };
+        if operation::should_stop()? {
+            break;
+        }
+        (page_items, maybe_next) = fetch_page(url).await?;
+    }
+    Ok(items)
+}
+
+/// collects the first `limit` items (all items if `None`) of an offset-based collection,
+/// fetching pages of at most `page_size` items with `fetch_page(offset, limit)`,
+/// which returns the page's items and the collection's total.
+///
+/// Some collections reject offsets above `max_offset` while serving more items than that,
+/// in which case the last pages are fetched at `max_offset` and their already-collected
+/// items are skipped.
+///
+/// Pagination stops early (with partial items) if the current operation's deadline is reached.
+pub(crate) async fn collect_offsets<T, F, Fut>(
+    limit: Option<usize>,
+    page_size: usize,
+    max_offset: usize,
+    mut fetch_page: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(usize, usize) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, usize)>>,
+{
+    let mut items = vec![];
+    let mut wanted = limit.unwrap_or(usize::MAX);
+    while items.len() < wanted {
+        if !items.is_empty() && operation::should_stop()? {
+            break;
+        }
+        let offset = std::cmp::min(items.len(), max_offset);
+        let (page_items, total) =
+            fetch_page(offset, std::cmp::min(page_size, wanted - offset)).await?;
+        wanted = std::cmp::min(wanted, total);
+
+        let collected = items.len() - offset;
+        if page_items.len() <= collected {
+            // the page has no new items
+            break;
+        }
+        items.extend(page_items.into_iter().skip(collected));
+    }
+    items.truncate(wanted);
+    Ok(items)
+}
+
+/// streams the pages of a paginated collection, starting from the first page's items
+/// and the URL of the next page, fetching the next pages with `fetch_page`.
+///
+/// A page is only fetched once the previous page is consumed. A failed page
+/// yields its error and ends the stream, the pages yielded before stay valid.
+/// The stream also ends (with partial pages) if the current operation's deadline is reached.
+pub(crate) fn pages_stream<'a, T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    fetch_page: F,
+) -> impl Stream<Item = Result<Vec<T>>> + 'a
+where
+    T: 'a,
+    F: FnMut(String) -> Fut + 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>> + 'a,
+{
+    struct State<T, F> {
+        first_items: Option<Vec<T>>,
+        next: Option<String>,
+        fetch_page: F,
+    }
+
+    let state = State {
+        first_items: Some(first_items),
+        next: first_next,
+        fetch_page,
+    };
+    futures::stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        if let Some(items) = state.first_items.take() {
+            return Some((Ok(items), Some(state)));
+        }
+        let url = state.next.take()?;
+        match operation::should_stop() {
+            Ok(false) => {}
+            Ok(true) => return None,
+            Err(err) => return Some((Err(err), None)),
+        }
+        match (state.fetch_page)(url).await {
+            Ok((items, next)) => {
+                state.next = next;
+                Some((Ok(items), Some(state)))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+/// streams the items of a paginated collection, starting from the first page's items
+/// and the URL of the next page, fetching the next pages with `fetch_page`.
+///
+/// A page is only fetched once the previous pages' items are consumed. A failed page
+/// yields its error and ends the stream, the items yielded before stay valid.
+/// The stream also ends (with partial items) if the current operation's deadline is reached.
+pub(crate) fn page_stream<'a, T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    fetch_page: F,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: 'a,
+    F: FnMut(String) -> Fut + 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>> + 'a,
+{
+    use futures::StreamExt;
+
+    pages_stream(first_items, first_next, fetch_page).flat_map(|page| {
+        let items = match page {
+            Ok(items) => items.into_iter().map(Ok).collect(),
+            Err(err) => vec![Err(err)],
+        };
+        futures::stream::iter(items)
+    })
+}
+
+/// streams the pages of a paginated collection like `pages_stream`, prefetching up to `prefetch`
+/// pages ahead of the consumer, e.g. to process a page while the next pages are fetched.
+///
+/// The pages are derived from the offset of the next page's URL and the collection's `total`,
+/// and fetched concurrently by a task running inside the current operation, which sends them
+/// through a channel of `prefetch` pages: at most `prefetch` pages are fetched or waiting
+/// to be consumed. A collection whose pages can't be derived (e.g. cursor-based) is streamed
+/// without prefetching. Dropping the stream aborts the task and its outstanding requests.
+pub(crate) fn prefetched_pages_stream<T, F, Fut>(
+    first_items: Vec<T>,
+    first_next: Option<String>,
+    total: usize,
+    prefetch: usize,
+    mut fetch_page: F,
+) -> impl Stream<Item = Result<Vec<T>>>
+where
+    T: Send + 'static,
+    F: FnMut(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>> + Send + 'static,
+{
+    use futures::StreamExt;
+
+    let urls = first_next
+        .as_deref()
+        .and_then(|next| offset_page_urls(next, total));
+    let Some(urls) = urls.filter(|_| prefetch > 0) else {
+        return pages_stream(first_items, first_next, fetch_page).left_stream();
+    };
+
+    let (sender, receiver) = tokio::sync::mpsc::channel(prefetch);
+    let task = operation::spawn(async move {
+        let pages = futures::stream::iter(urls)
+            // a page is fetched once there is room for it in the channel
+            .then(move |url| {
+                let permit = sender.clone().reserve_owned();
+                async move { Some((permit.await.ok()?, url)) }
+            })
+            // the stop check runs before the page's request is sent
+            .map(|page| {
+                let (permit, url) = page?;
+                match operation::should_stop() {
+                    Ok(true) => None,
+                    stop => Some((permit, stop.map(|_| url))),
+                }
+            })
+            .take_while(|page| std::future::ready(page.is_some()))
+            .filter_map(std::future::ready)
+            .map(move |(permit, url)| {
+                let page = url.map(&mut fetch_page);
+                async move { (permit, async move { page?.await }.await) }
+            })
+            .buffered(prefetch);
+        futures::pin_mut!(pages);
+        while let Some((permit, page)) = pages.next().await {
+            // a failed page yields its error and ends the stream
+            let failed = page.is_err();
+            permit.send(page.map(|(items, _)| items));
+            if failed {
+                break;
+            }
+        }
+    });
+
+    let pages = futures::stream::unfold(
+        (receiver, AbortOnDrop(task)),
+        |(mut receiver, mut task)| async move {
+            if let Some(page) = receiver.recv().await {
+                return Some((page, (receiver, task)));
+            }
+            // the channel is closed once the task ended, which may have stopped early
+            let (_, complete) = (&mut task.0)
+                .await
+                .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+            if !complete {
+                operation::set_incomplete();
+            }
+            None
+        },
+    );
+    futures::stream::once(std::future::ready(Ok(first_items)))
+        .chain(pages)
+        .right_stream()
+}
+
+/// A spawned task aborted once its handle is dropped
+struct AbortOnDrop<T>(tokio::task::JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// fetches the items of multiple ids in batches of at most `batch_size` ids with `fetch_batch`.
+///
+/// Fetching stops early (with partial items) if the current operation's deadline is reached.
+pub(crate) async fn fetch_batches<I, T, F, Fut>(
+    ids: &[I],
+    batch_size: usize,
+    mut fetch_batch: F,
+) -> Result<Vec<T>>
+where
+    I: Clone,
+    F: FnMut(Vec<I>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut items = Vec::with_capacity(ids.len());
+    for batch in ids.chunks(batch_size) {
+        if operation::should_stop()? {
+            break;
+        }
+        items.append(&mut fetch_batch(batch.to_vec()).await?);
+    }
+    Ok(items)
+}
+
+/// calls `f` with the ids in chunks of at most `chunk_size` ids, preserving their order,
+/// and concatenates the outputs.
+///
+/// Unlike `fetch_batches`, the call fails if the current operation's deadline is reached,
+/// as a partial result would silently miss some ids (e.g. when saving items).
+pub(crate) async fn map_chunks<I, T, F, Fut>(
+    ids: &[I],
+    chunk_size: usize,
+    mut f: F,
+) -> Result<Vec<T>>
+where
+    I: Clone,
+    F: FnMut(Vec<I>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut outputs = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(chunk_size) {
+        operation::check()?;
+        outputs.append(&mut f(chunk.to_vec()).await?);
+    }
+    Ok(outputs)
+}
+
+/// maps inputs with the async function `f`, running at most `concurrency` calls concurrently.
+///
+/// The outputs are returned in the inputs' order. The first error fails the whole mapping,
+/// dropping the running calls.
+pub(crate) async fn map_concurrently<I, T, F, Fut>(
+    inputs: Vec<I>,
+    concurrency: usize,
+    f: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(I) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    use futures::{StreamExt, TryStreamExt};
+
+    futures::stream::iter(inputs)
+        .map(f)
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+/// A future fetching a page of a paginator, resolving to the page's converted items,
+/// the number of the page's items before the conversion and the URL of the next page
+type PaginatorFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<(Vec<T>, usize, Option<String>)>> + Send + 'a>>;
+
+/// A handle to a paginated collection whose pages are fetched on demand,
+/// e.g. to render the first items of a large collection before fetching the next ones.
+///
+/// The items of the collection are converted when a page is fetched,
+/// the items that cannot be converted (e.g. unavailable tracks) are skipped.
+pub struct Paginator<'a, T> {
+    first_items: Option<Vec<T>>,
+    next: Option<String>,
+    total: usize,
+    fetched: usize,
+    fetch_page: Box<dyn Fn(String) -> PaginatorFuture<'a, T> + Send + 'a>,
+}
+
+impl<'a, T: Send + 'a> Paginator<'a, T> {
+    /// creates a paginator starting from the first page's items and the URL of the next page,
+    /// fetching the next pages with `fetch_page` and converting the items with `convert`
+    pub(crate) fn new<R, F, Fut>(
+        first_items: Vec<R>,
+        first_next: Option<String>,
+        total: usize,
+        fetch_page: F,
+        convert: fn(R) -> Option<T>,
+    ) -> Self
+    where
+        R: 'a,
+        F: Fn(String) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<(Vec<R>, Option<String>)>> + Send + 'a,
+    {
+        let fetched = first_items.len();
+        Self {
+            first_items: Some(first_items.into_iter().filter_map(convert).collect()),
+            next: first_next,
+            total,
+            fetched,
+            fetch_page: Box::new(move |url| {
+                let page = fetch_page(url);
+                Box::pin(async move {
+                    let (items, next) = page.await?;
+                    let len = items.len();
+                    Ok((items.into_iter().filter_map(convert).collect(), len, next))
+                })
+            }),
+        }
+    }
+
+    /// gets the items of the next page, `None` if all the pages were returned.
+    ///
+    /// A page failing to be fetched can be requested again.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>> {
+        if let Some(items) = self.first_items.take() {
+            return Ok(Some(items));
+        }
+        let Some(url) = self.next.take() else {
+            return Ok(None);
+        };
+
+        match (self.fetch_page)(url.clone()).await {
+            Ok((items, len, next)) => {
+                self.fetched += len;
+                self.next = next;
+                Ok(Some(items))
+            }
+            Err(err) => {
+                self.next = Some(url);
+                Err(err)
+            }
+        }
+    }
+
+    /// collects the items of the remaining pages.
+    ///
+    /// Pagination stops early (with partial items) if the current operation's deadline is reached.
+    pub async fn collect(&mut self) -> Result<Vec<T>> {
+        self.collect_limited(None).await
+    }
+
+    /// collects the first `limit` items (all items if `None`) of the remaining pages,
+    /// without fetching the pages after the `limit`-th item. See `Paginator::collect`.
+    pub async fn collect_limited(&mut self, limit: Option<usize>) -> Result<Vec<T>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut items = self.first_items.take().unwrap_or_default();
+        while self.next.is_some() && items.len() < limit {
+            if operation::should_stop()? {
+                break;
+            }
+            if let Some(mut page_items) = self.next_page().await? {
+                items.append(&mut page_items);
+            }
+        }
+        items.truncate(limit);
+        Ok(items)
+    }
+
+    /// gets the total number of items in the collection, including the skipped items
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// gets the number of items fetched so far, including the skipped items
+    pub fn fetched(&self) -> usize {
+        self.fetched
+    }
+}
+
+type SearchPageFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(SearchResults, usize)>> + Send + 'a>>;
+
+/// A page of search results with a handle to fetch the following pages,
+/// e.g. to load more results while scrolling through them.
+pub struct SearchPage<'a> {
+    /// the results of the current page
+    pub items: SearchResults,
+    /// the total number of matching items
+    pub total: usize,
+    offset: usize,
+    page_size: usize,
+    max_results: usize,
+    fetch_page: Box<dyn Fn(usize, usize) -> SearchPageFuture<'a> + Send + 'a>,
+}
+
+impl<'a> SearchPage<'a> {
+    /// fetches the first page of `page_size` results (clamped between 1 and `MAX_PAGE_SIZE`)
+    /// with `fetch_page(offset, limit)`, only the first `max_results` results being reachable
+    pub(crate) async fn new<F>(page_size: usize, max_results: usize, fetch_page: F) -> Result<Self>
+    where
+        F: Fn(usize, usize) -> SearchPageFuture<'a> + Send + 'a,
+    {
+        let page_size = page_size.clamp(1, super::MAX_PAGE_SIZE);
+        let (items, total) = fetch_page(0, page_size.min(max_results)).await?;
+        Ok(Self {
+            items,
+            total,
+            offset: 0,
+            page_size,
+            max_results,
+            fetch_page: Box::new(fetch_page),
+        })
+    }
+
+    /// fetches the next page of results, replacing the current page's items.
+    ///
+    /// Returns `None` if all the results were fetched or if the next page is beyond
+    /// the reachable results, without sending a request.
+    pub async fn next(&mut self) -> Result<Option<&SearchResults>> {
+        let offset = self.offset + self.page_size;
+        if offset >= self.total.min(self.max_results) {
+            return Ok(None);
+        }
+
+        let limit = self.page_size.min(self.max_results - offset);
+        let (items, total) = (self.fetch_page)(offset, limit).await?;
+        self.items = items;
+        self.total = total;
+        self.offset = offset;
+        Ok(Some(&self.items))
+    }
+
+    /// gets the offset of the current page's first result
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// a fake pager serving `total` items in pages of `limit` items, with a delay per page
+    pub(crate) async fn fake_page(
+        url: String,
+        total: usize,
+        limit: usize,
+        delay: Duration,
+    ) -> Result<(Vec<usize>, Option<String>)> {
+        tokio::time::sleep(delay).await;
+        let offset = url.parse::<usize>()?;
+        let items = (offset..std::cmp::min(offset + limit, total)).collect();
+        let next = (offset + limit < total).then(|| (offset + limit).to_string());
+        Ok((items, next))
+    }
+
+    #[tokio::test]
+    async fn collect_all_pages() {
+        let items = collect_pages(vec![0, 1], Some("2".to_string()), |url| {
+            fake_page(url, 7, 2, Duration::ZERO)
+        })
+        .await
+        .unwrap();
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn derive_offset_page_urls() {
+        let urls = offset_page_urls(
+            "https://api.spotify.com/v1/playlists/x/tracks?offset=100&limit=100&market=from_token",
+            350,
+        )
+        .unwrap();
+        assert_eq!(
+            urls,
+            vec![
+                "https://api.spotify.com/v1/playlists/x/tracks?offset=100&limit=100&market=from_token",
+                "https://api.spotify.com/v1/playlists/x/tracks?offset=200&limit=100&market=from_token",
+                "https://api.spotify.com/v1/playlists/x/tracks?offset=300&limit=100&market=from_token",
+            ]
+        );
+        assert_eq!(
+            offset_page_urls("https://x/items?offset=50&limit=50", 50),
+            Some(vec![])
+        );
+
+        // cursor-based URLs are not offset-based
+        assert_eq!(
+            offset_page_urls("https://x/me/following?after=abc&limit=50", 100),
+            None
+        );
+        assert_eq!(
+            offset_page_urls("https://x/items?offset=50&limit=0", 100),
+            None
+        );
+        assert_eq!(offset_page_urls("https://x/items", 100), None);
+    }
+
+    #[tokio::test]
+    async fn collect_pages_with_bounded_concurrency() {
+        let running = &std::sync::atomic::AtomicUsize::new(0);
+        let max_running = &std::sync::atomic::AtomicUsize::new(0);
+        let items = collect_pages_concurrently(
+            (0..10).collect(),
+            Some("https://x/items?offset=10&limit=10".to_string()),
+            95,
+            4,
+            |url| async move {
+                let n = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                max_running.fetch_max(n, std::sync::atomic::Ordering::SeqCst);
+                let offset = url
+                    .split_once("offset=")
+                    .and_then(|(_, rest)| rest.split('&').next())
+                    .unwrap()
+                    .parse::<usize>()?;
+                // the pages complete out of order
+                let (items, _) = fake_page(
+                    offset.to_string(),
+                    95,
+                    10,
+                    Duration::from_millis(offset as u64 % 7),
+                )
+                .await?;
+                running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                anyhow::Ok((items, None))
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(items, (0..95).collect::<Vec<_>>());
+        assert_eq!(max_running.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        // the pages are fetched sequentially if the next page URL is not offset-based
+        let items = collect_pages_concurrently(vec![0, 1], Some("2".to_string()), 7, 4, |url| {
+            fake_page(url, 7, 2, Duration::ZERO)
+        })
+        .await
+        .unwrap();
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn collect_pages_added_during_pagination() {
+        // the collection had 30 items when its first page was fetched, 45 items afterwards
+        let items = collect_pages_concurrently(
+            (0..10).collect(),
+            Some("https://x/items?offset=10&limit=10".to_string()),
+            30,
+            4,
+            |url| async move {
+                let offset = url
+                    .split_once("offset=")
+                    .unwrap()
+                    .1
+                    .split('&')
+                    .next()
+                    .unwrap();
+                let (items, next) = fake_page(offset.to_string(), 45, 10, Duration::ZERO).await?;
+                let next = next.map(|offset| format!("https://x/items?offset={offset}&limit=10"));
+                anyhow::Ok((items, next))
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(items, (0..45).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn collect_limited_pages() {
+        let fetched = &std::cell::Cell::new(0);
+        let collect = move |first_items: Vec<usize>, limit: Option<usize>| {
+            fetched.set(0);
+            let next = first_items.len().to_string();
+            collect_pages_limited(first_items, Some(next), limit, move |url| {
+                fetched.set(fetched.get() + 1);
+                fake_page(url, 100, 10, Duration::ZERO)
+            })
+        };
+
+        // a limit within the first page needs no other request
+        let items = collect((0..10).collect(), Some(10)).await.unwrap();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+        assert_eq!(fetched.get(), 0);
+
+        let items = collect((0..10).collect(), Some(25)).await.unwrap();
+        assert_eq!(items, (0..25).collect::<Vec<_>>());
+        assert_eq!(fetched.get(), 2);
+
+        let items = collect((0..10).collect(), None).await.unwrap();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+        assert_eq!(fetched.get(), 9);
+    }
+
+    #[tokio::test]
+    async fn collect_pages_until_rejected_item() {
+        let fetched = &std::cell::RefCell::new(vec![]);
+        let collect = move |limit: usize| {
+            fetched.borrow_mut().clear();
+            collect_pages_while(
+                vec![0, 1],
+                Some("2".to_string()),
+                move |url| {
+                    fetched.borrow_mut().push(url.clone());
+                    fake_page(url, 10, 2, Duration::ZERO)
+                },
+                move |&item| item < limit,
+            )
+        };
+
+        // the rejected item starts a page: the items of the previous page are all kept
+        assert_eq!(collect(4).await.unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(*fetched.borrow(), vec!["2", "4"]);
+
+        // the rejected item is within a page
+        assert_eq!(collect(5).await.unwrap(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(*fetched.borrow(), vec!["2", "4"]);
+
+        // no page is fetched after a rejected first item, and all items may be kept
+        assert!(collect(0).await.unwrap().is_empty());
+        assert!(fetched.borrow().is_empty());
+        assert_eq!(collect(10).await.unwrap(), (0..10).collect::<Vec<_>>());
+        assert_eq!(fetched.borrow().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn fail_collect_pages_while_at_deadline() {
+        let deadline = Instant::now() + Duration::from_millis(250);
+        let (result, complete) = operation::scope(
+            Some(deadline),
+            None,
+            operation::complete(collect_pages_while(
+                vec![],
+                Some("0".to_string()),
+                |url| fake_page(url, 100, 2, Duration::from_millis(100)),
+                |&item| item < 50,
+            )),
+        )
+        .await;
+
+        // the items before the rejected item are never returned partially
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::error::ClientError>(),
+            Some(crate::error::ClientError::DeadlineExceeded)
+        ));
+        assert!(!complete);
+    }
+
+    #[tokio::test]
+    async fn collect_offsets_above_max_offset() {
+        let requests = &std::cell::RefCell::new(vec![]);
+        // a fake pager of `total` items, rejecting offsets above 49 like the top items endpoint
+        let collect = move |limit: Option<usize>, total: usize| {
+            requests.borrow_mut().clear();
+            collect_offsets(limit, 50, 49, move |offset, limit| {
+                requests.borrow_mut().push((offset, limit));
+                async move {
+                    anyhow::ensure!(offset <= 49 && limit <= 50, "invalid request");
+                    let items = (offset..std::cmp::min(offset + limit, total)).collect();
+                    anyhow::Ok((items, total))
+                }
+            })
+        };
+
+        assert_eq!(
+            collect(None, 99).await.unwrap(),
+            (0..99).collect::<Vec<_>>()
+        );
+        assert_eq!(*requests.borrow(), vec![(0, 50), (49, 50)]);
+
+        assert_eq!(
+            collect(None, 30).await.unwrap(),
+            (0..30).collect::<Vec<_>>()
+        );
+        assert_eq!(*requests.borrow(), vec![(0, 50)]);
+
+        // a limit avoids fetching the next pages
+        assert_eq!(
+            collect(Some(10), 99).await.unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(*requests.borrow(), vec![(0, 10)]);
+        assert_eq!(
+            collect(Some(70), 99).await.unwrap(),
+            (0..70).collect::<Vec<_>>()
+        );
+        assert_eq!(*requests.borrow(), vec![(0, 50), (49, 21)]);
+
+        // a total above the reachable items stops at the last reachable item
+        assert_eq!(
+            collect(None, 150).await.unwrap(),
+            (0..99).collect::<Vec<_>>()
+        );
+        assert!(collect(None, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stream_pages_lazily() {
+        use futures::StreamExt;
+
+        let fetched = &std::cell::Cell::new(0);
+        let stream = page_stream(vec![0, 1], Some("2".to_string()), move |url| {
+            fetched.set(fetched.get() + 1);
+            async move {
+                anyhow::ensure!(url != "6", "failed page");
+                fake_page(url, 10, 2, Duration::ZERO).await
+            }
+        });
+        futures::pin_mut!(stream);
+
+        // the next page is fetched once the first page's items are consumed
+        assert_eq!(stream.next().await.unwrap().unwrap(), 0);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        assert_eq!(fetched.get(), 0);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+        assert_eq!(fetched.get(), 1);
+
+        // a failed page ends the stream after the items of the previous pages
+        let rest = stream.collect::<Vec<_>>().await;
+        assert_eq!(rest.len(), 4);
+        assert!(rest[..3].iter().all(|item| item.is_ok()));
+        assert!(rest[3].is_err());
+        assert_eq!(fetched.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn stream_whole_pages() {
+        use futures::StreamExt;
+
+        let fetched = &std::cell::Cell::new(0);
+        let stream = pages_stream(vec![0, 1], Some("2".to_string()), move |url| {
+            fetched.set(fetched.get() + 1);
+            fake_page(url, 7, 2, Duration::ZERO)
+        });
+        futures::pin_mut!(stream);
+
+        // the first page is yielded without a request, the next pages once the previous is consumed
+        assert_eq!(stream.next().await.unwrap().unwrap(), vec![0, 1]);
+        assert_eq!(stream.next().await.unwrap().unwrap(), vec![2, 3]);
+        assert_eq!(fetched.get(), 1);
+
+        let rest = stream.collect::<Vec<_>>().await;
+        assert_eq!(
+            rest.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            vec![vec![4, 5], vec![6]]
+        );
+        assert_eq!(fetched.get(), 3);
+    }
+
+    /// a mock server serving 12 items in pages of 2 items, answering a page in 4 chunks of 10ms,
+    /// a chunk being only read while the request is polled (like a response's body)
+    async fn mock_page(url: String) -> Result<(Vec<usize>, Option<String>)> {
+        let offset = url
+            .split_once("offset=")
+            .and_then(|(_, rest)| rest.split('&').next())
+            .unwrap();
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (items, next) = fake_page(offset.to_string(), 12, 2, Duration::from_millis(10)).await?;
+        let next = next.map(|offset| format!("https://x/items?offset={offset}&limit=2"));
+        Ok((items, next))
+    }
+
+    /// scans the pages of the mock server with `prefetch` pages fetched ahead, processing
+    /// a page in 40ms without polling the stream, and recording the most pages requested
+    /// and not yet received
+    async fn scan_pages(prefetch: usize) -> (Vec<usize>, Duration, usize) {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+        use std::sync::Arc;
+
+        let requested = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(AtomicUsize::new(0));
+        let max_ahead = Arc::new(AtomicUsize::new(0));
+        let started = tokio::time::Instant::now();
+        let stream = prefetched_pages_stream(
+            vec![0, 1],
+            Some("https://x/items?offset=2&limit=2".to_string()),
+            12,
+            prefetch,
+            {
+                let (requested, received, max_ahead) =
+                    (requested.clone(), received.clone(), max_ahead.clone());
+                move |url| {
+                    let ahead = requested.fetch_add(1, SeqCst) + 1 - received.load(SeqCst);
+                    max_ahead.fetch_max(ahead, SeqCst);
+                    mock_page(url)
+                }
+            },
+        );
+        futures::pin_mut!(stream);
+
+        let mut items = vec![];
+        while let Some(page) = stream.next().await {
+            // the first page is received without a request
+            if !items.is_empty() {
+                received.fetch_add(1, SeqCst);
+            }
+            items.extend(page.unwrap());
+            tokio::time::sleep(Duration::from_millis(40)).await;
+        }
+        (items, started.elapsed(), max_ahead.load(SeqCst))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn prefetch_pages_while_consuming() {
+        let (items, sequential, max_ahead) = scan_pages(0).await;
+        assert_eq!(items, (0..12).collect::<Vec<_>>());
+        assert_eq!(max_ahead, 1);
+
+        // the next pages are fetched while the previous pages are processed
+        let (items, prefetched, max_ahead) = scan_pages(2).await;
+        assert_eq!(items, (0..12).collect::<Vec<_>>());
+        assert_eq!(max_ahead, 2);
+        assert!(
+            prefetched < sequential.mul_f64(0.6),
+            "{prefetched:?} vs {sequential:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn abort_prefetch_on_drop() {
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+        use std::sync::Arc;
+
+        /// a request counting its drops before completion
+        struct Request(Arc<AtomicUsize>);
+        impl Drop for Request {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let completed = Arc::new(AtomicUsize::new(0));
+        let aborted = Arc::new(AtomicUsize::new(0));
+        let stream = prefetched_pages_stream(
+            vec![0, 1],
+            Some("https://x/items?offset=2&limit=2".to_string()),
+            12,
+            2,
+            {
+                let (completed, aborted) = (completed.clone(), aborted.clone());
+                move |url| {
+                    let request = Request(aborted.clone());
+                    let completed = completed.clone();
+                    async move {
+                        let page = mock_page(url).await;
+                        std::mem::forget(request);
+                        completed.fetch_add(1, SeqCst);
+                        page
+                    }
+                }
+            },
+        );
+        futures::pin_mut!(stream);
+
+        // the first page is yielded without a request, the next 2 pages are fetched ahead
+        assert_eq!(stream.next().await.unwrap().unwrap(), vec![0, 1]);
+        assert_eq!(stream.next().await.unwrap().unwrap(), vec![2, 3]);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // dropping the stream aborts the outstanding request of the 4th page
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(completed.load(SeqCst), 2);
+        assert_eq!(aborted.load(SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stop_pagination_at_deadline() {
+        let deadline = Instant::now() + Duration::from_millis(250);
+        let (items, complete) = operation::scope(
+            Some(deadline),
+            None,
+            collect_pages(vec![0, 1], Some("2".to_string()), |url| {
+                fake_page(url, 100, 2, Duration::from_millis(100))
+            }),
+        )
+        .await;
+
+        // the pages fetched before the deadline are returned in full
+        let items = items.unwrap();
+        assert!(!complete);
+        assert!(!items.is_empty() && items.len() < 100);
+        assert_eq!(items.len() % 2, 0);
+        assert_eq!(items, (0..items.len()).collect::<Vec<_>>());
+
+        // requests made after the deadline fail
+        let (result, _) =
+            operation::scope(Some(deadline), None, async { operation::check() }).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn never_write_after_partial_read() {
+        use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+        let writes = AtomicUsize::new(0);
+        let deadline = Instant::now() + Duration::from_millis(250);
+        let (result, complete) = operation::scope(Some(deadline), None, async {
+            let items = operation::complete(collect_pages(vec![], Some("0".to_string()), |url| {
+                fake_page(url, 100, 2, Duration::from_millis(100))
+            }))
+            .await?;
+            writes.fetch_add(items.len(), SeqCst);
+            anyhow::Ok(())
+        })
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::error::ClientError>(),
+            Some(crate::error::ClientError::DeadlineExceeded)
+        ));
+        assert!(!complete);
+        assert_eq!(writes.load(SeqCst), 0);
+
+        // a complete read is returned as is
+        let (items, complete) = operation::scope(
+            Some(Instant::now() + Duration::from_secs(60)),
+            None,
+            operation::complete(collect_pages(vec![], Some("0".to_string()), |url| {
+                fake_page(url, 7, 2, Duration::ZERO)
+            })),
+        )
+        .await;
+        assert_eq!(items.unwrap(), (0..7).collect::<Vec<_>>());
+        assert!(complete);
+    }
+
+    #[tokio::test]
+    async fn paginate_on_demand() {
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = requests.clone();
+        // odd items are skipped
+        let mut paginator = Paginator::new(
+            (0..3).collect(),
+            Some("3".to_string()),
+            10,
+            move |url| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                fake_page(url, 10, 3, Duration::ZERO)
+            },
+            |i: usize| (i % 2 == 0).then_some(i),
+        );
+        assert_eq!(paginator.total(), 10);
+
+        // the first page is available without any request
+        assert_eq!(paginator.next_page().await.unwrap(), Some(vec![0, 2]));
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        assert_eq!(paginator.next_page().await.unwrap(), Some(vec![4]));
+        assert_eq!(paginator.fetched(), 6);
+        assert_eq!(paginator.collect().await.unwrap(), vec![6, 8]);
+        assert_eq!(paginator.fetched(), 10);
+        assert_eq!(paginator.next_page().await.unwrap(), None);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn paginate_up_to_limit() {
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = requests.clone();
+        let mut paginator = Paginator::new(
+            (0..3).collect(),
+            Some("3".to_string()),
+            10,
+            move |url| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                fake_page(url, 10, 3, Duration::ZERO)
+            },
+            Some,
+        );
+
+        // the limit is reached mid-page, the next pages are not fetched
+        assert_eq!(
+            paginator.collect_limited(Some(5)).await.unwrap(),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn map_ids_in_chunks() {
+        let mut chunk_sizes = vec![];
+        let ids = (0..51).collect::<Vec<usize>>();
+        let outputs = map_chunks(&ids, 50, |chunk| {
+            chunk_sizes.push(chunk.len());
+            async move { anyhow::Ok(chunk.into_iter().map(|id| id % 2 == 0).collect()) }
+        })
+        .await
+        .unwrap();
+
+        // the 51st id is sent in its own chunk, the outputs keep the ids' order
+        assert_eq!(chunk_sizes, vec![50, 1]);
+        assert_eq!(
+            outputs,
+            ids.iter().map(|id| id % 2 == 0).collect::<Vec<_>>()
+        );
+
+        assert!(
+            map_chunks(&[] as &[usize], 50, |_| async { anyhow::Ok(vec![()]) })
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn map_with_bounded_concurrency() {
+        let running = std::sync::atomic::AtomicUsize::new(0);
+        let max_running = std::sync::atomic::AtomicUsize::new(0);
+        let outputs = map_concurrently((0..20).collect(), 4, |i: u64| {
+            let running = &running;
+            let max_running = &max_running;
+            async move {
+                let n = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                max_running.fetch_max(n, std::sync::atomic::Ordering::SeqCst);
+                // the calls complete out of order
+                tokio::time::sleep(Duration::from_millis(5 * (i % 3))).await;
+                running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                anyhow::Ok(i * 2)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(outputs, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(max_running.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        let result = map_concurrently(vec![1, 2, 3], 2, |i| async move {
+            anyhow::ensure!(i != 2, "failed call");
+            Ok(i)
+        })
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_tracks_in_batches() {
+        let track_ids = (0..120).collect::<Vec<usize>>();
+        let mut batch_sizes = vec![];
+        let tracks = fetch_batches(&track_ids, 50, |batch| {
+            batch_sizes.push(batch.len());
+            async move { anyhow::Ok(batch) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(batch_sizes, vec![50, 50, 20]);
+        assert_eq!(tracks, track_ids);
+    }
+
+    #[tokio::test]
+    async fn fetch_albums_in_batches() {
+        // 40 albums of at most 50 tracks, whose first page of tracks is complete
+        let album_ids = (0..40).collect::<Vec<usize>>();
+        let mut requests = 0;
+        let albums = fetch_batches(&album_ids, 20, |batch| {
+            requests += 1;
+            async move {
+                assert!(batch.len() <= 20);
+                anyhow::Ok(batch.into_iter().map(|id| (id, None::<String>)).collect())
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(albums.len(), 40);
+        assert_eq!(requests, 2);
+
+        // no album needs a top-up request for its remaining tracks
+        for (_, next) in albums {
+            let tracks = collect_pages(vec![0; 50], next, |url| {
+                requests += 1;
+                fake_page(url, 50, 50, Duration::ZERO)
+            })
+            .await
+            .unwrap();
+            assert_eq!(tracks.len(), 50);
+        }
+        assert_eq!(requests, 2);
+
+        // an album with more tracks is topped up
+        let tracks = collect_pages((0..50).collect(), Some("50".to_string()), |url| {
+            requests += 1;
+            fake_page(url, 120, 50, Duration::ZERO)
+        })
+        .await
+        .unwrap();
+        assert_eq!(tracks, (0..120).collect::<Vec<_>>());
+        assert_eq!(requests, 4);
+    }
+
+    #[tokio::test]
+    async fn stop_pagination_on_cancellation() {
+        let token = operation::CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            cancel.cancel();
+        });
+
+        let start = Instant::now();
+        let requests = std::sync::atomic::AtomicUsize::new(0);
+        let (items, complete) = operation::scope(
+            None,
+            Some(token),
+            collect_pages(vec![0, 1], Some("2".to_string()), |url| {
+                requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                fake_page(url, 100, 2, Duration::from_millis(100))
+            }),
+        )
+        .await;
+
+        // the pagination fails at the page following the in-flight request,
+        // instead of returning partial items
+        let err = items.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::error::ClientError>(),
+            Some(crate::error::ClientError::Cancelled)
+        ));
+        assert!(!complete);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn fail_batches_on_cancellation() {
+        let token = operation::CancellationToken::new();
+        let cancel = token.clone();
+        let ids = (0..10).collect::<Vec<usize>>();
+        let mut batches = 0;
+        let (items, _) = operation::scope(
+            None,
+            Some(token),
+            fetch_batches(&ids, 2, |batch| {
+                batches += 1;
+                // the operation is cancelled while the second batch is in flight
+                if batches == 2 {
+                    cancel.cancel();
+                }
+                async move { anyhow::Ok(batch) }
+            }),
+        )
+        .await;
+
+        let err = items.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::error::ClientError>(),
+            Some(crate::error::ClientError::Cancelled)
+        ));
+        assert_eq!(batches, 2);
+    }
+
+    #[tokio::test]
+    async fn search_pages_up_to_max_results() {
+        let requests = std::sync::Mutex::new(vec![]);
+        let requests_ref = &requests;
+        let mut page = SearchPage::new(40, 100, move |offset, limit| {
+            requests_ref.lock().unwrap().push((offset, limit));
+            Box::pin(async { anyhow::Ok((SearchResults::default(), 5000)) })
+        })
+        .await
+        .unwrap();
+        assert_eq!(page.total, 5000);
+
+        while page.next().await.unwrap().is_some() {}
+        assert_eq!(page.offset(), 80);
+        // the last page is truncated to the reachable results
+        assert_eq!(*requests.lock().unwrap(), vec![(0, 40), (40, 40), (80, 20)]);
+        assert!(page.next().await.unwrap().is_none());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn clamp_search_page_size() {
+        for (page_size, limit) in [(0, 1), (300, 50)] {
+            let requests = std::sync::Mutex::new(vec![]);
+            let requests_ref = &requests;
+            SearchPage::new(page_size, 1000, move |offset, limit| {
+                requests_ref.lock().unwrap().push((offset, limit));
+                Box::pin(async { anyhow::Ok((SearchResults::default(), 5000)) })
+            })
+            .await
+            .unwrap();
+            assert_eq!(*requests.lock().unwrap(), vec![(0, limit)]);
+        }
+    }
+}
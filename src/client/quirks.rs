@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A known upstream issue (Spotify API or `rspotify`) worked around by the client
+pub enum Quirk {
+    /// Patch `"images":null` into `"images":[]` in API responses.
+    /// See: https://github.com/ramsayleung/rspotify/issues/459
+    NullImages,
+    /// Get the current user's playlists with a raw HTTP request
+    /// instead of the `rspotify` API, which fails to parse some responses
+    CurrentUserPlaylistsRequest,
+    /// Get a user's playlists with a raw HTTP request
+    /// instead of the `rspotify` API, which fails to parse some responses
+    UserPlaylistsRequest,
+    /// Get a playlist with a raw HTTP request
+    /// instead of the `rspotify` API, which fails to parse some responses
+    PlaylistRequest,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The registry of the client's quirk workarounds.
+///
+/// All quirks are enabled by default. A quirk can be disabled if its workaround misfires,
+/// e.g. a legitimate `"images":null` inside a string value.
+pub struct Quirks {
+    disabled: HashSet<Quirk>,
+}
+
+impl Quirk {
+    pub const ALL: [Quirk; 4] = [
+        Quirk::NullImages,
+        Quirk::CurrentUserPlaylistsRequest,
+        Quirk::UserPlaylistsRequest,
+        Quirk::PlaylistRequest,
+    ];
+
+    /// gets the quirk's stable name, used in configurations
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NullImages => "null_images",
+            Self::CurrentUserPlaylistsRequest => "current_user_playlists_request",
+            Self::UserPlaylistsRequest => "user_playlists_request",
+            Self::PlaylistRequest => "playlist_request",
+        }
+    }
+
+    /// gets a quirk from its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match Self::ALL.into_iter().find(|q| q.name() == name) {
+            Some(quirk) => Ok(quirk),
+            None => anyhow::bail!(
+                "unknown quirk `{name}`, expected one of: {}",
+                Self::ALL.map(|q| q.name()).join(", ")
+            ),
+        }
+    }
+
+    /// gets the quirk's response fixup (if any)
+    fn response_fixup(&self) -> Option<fn(String) -> String> {
+        match self {
+            Self::NullImages => Some(|text| text.replace("\"images\":null", "\"images\":[]")),
+            Self::CurrentUserPlaylistsRequest
+            | Self::UserPlaylistsRequest
+            | Self::PlaylistRequest => None,
+        }
+    }
+}
+
+impl Quirks {
+    /// creates a quirk registry with the quirks of the given names disabled
+    pub fn with_disabled<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let mut quirks = Self::default();
+        for name in names {
+            quirks.set(name.as_ref(), false)?;
+        }
+        Ok(quirks)
+    }
+
+    /// creates a quirk registry with the quirks of the given names disabled,
+    /// ignoring (with a warning) the unknown names, e.g. for the configured quirks
+    pub(crate) fn with_disabled_ignoring_unknown<S: AsRef<str>>(names: &[S]) -> Self {
+        let mut quirks = Self::default();
+        for name in names {
+            if let Err(err) = quirks.set(name.as_ref(), false) {
+                tracing::warn!("ignored a disabled quirk: {err:#}");
+            }
+        }
+        quirks
+    }
+
+    pub fn is_enabled(&self, quirk: Quirk) -> bool {
+        !self.disabled.contains(&quirk)
+    }
+
+    /// enables or disables a quirk by its name
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let quirk = Quirk::from_name(name)?;
+        if enabled {
+            self.disabled.remove(&quirk);
+        } else {
+            self.disabled.insert(quirk);
+        }
+        Ok(())
+    }
+
+    /// applies the enabled response fixups to an API response
+    ///
+    /// The fixups are mainly used to patch upstream API bugs, resulting in
+    /// a type error when a third-party library like `rspotify` parses the response
+    pub fn fix_response(&self, text: String) -> String {
+        Quirk::ALL
+            .into_iter()
+            .filter(|q| self.is_enabled(*q))
+            .filter_map(|q| q.response_fixup())
+            .fold(text, |text, fixup| fixup(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{"name":"a","images":null}"#;
+
+    #[test]
+    fn quirks_enabled_by_default() {
+        let quirks = Quirks::default();
+        assert!(Quirk::ALL.into_iter().all(|q| quirks.is_enabled(q)));
+        assert_eq!(
+            quirks.fix_response(RESPONSE.to_string()),
+            r#"{"name":"a","images":[]}"#
+        );
+    }
+
+    #[test]
+    fn disable_and_enable_quirk() {
+        let mut quirks = Quirks::with_disabled(&["null_images"]).unwrap();
+        assert!(!quirks.is_enabled(Quirk::NullImages));
+        assert!(quirks.is_enabled(Quirk::PlaylistRequest));
+        assert_eq!(quirks.fix_response(RESPONSE.to_string()), RESPONSE);
+
+        quirks.set("null_images", true).unwrap();
+        assert!(quirks.is_enabled(Quirk::NullImages));
+        assert_eq!(quirks, Quirks::default());
+    }
+
+    #[test]
+    fn unknown_quirk_name() {
+        let err = Quirks::default().set("images_null", false).unwrap_err();
+        assert!(err.to_string().contains("unknown quirk `images_null`"));
+        assert!(Quirks::with_disabled(&["null_images", "foo"]).is_err());
+
+        // only the unknown names are ignored
+        let quirks = Quirks::with_disabled_ignoring_unknown(&["foo", "null_images"]);
+        assert!(!quirks.is_enabled(Quirk::NullImages));
+        assert!(quirks.is_enabled(Quirk::PlaylistRequest));
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::HashSet, future::Future};
+
+use anyhow::Result;
+
+use crate::model::{
+    Id, LibraryExport, Playlist, RestoreAction, RestoreMode, RestoreReport, UserId,
+};
+
+/// plans the writes restoring a library export for the user `user_id`,
+/// skipping the items already in the user's library: the tracks, albums and artists
+/// given by their URIs in `existing` and the user's `playlists`.
+///
+/// The saved tracks and albums are saved from the oldest, so that the restored library
+/// lists them in the exported order. The playlists owned by the user are recreated,
+/// the other playlists are followed. As a recreated playlist has a new id, an owned playlist
+/// is also matched by its name, so that restoring an export twice recreates it once.
+pub(crate) fn plan_restore(
+    export: &LibraryExport,
+    user_id: &UserId<'_>,
+    existing: &HashSet<String>,
+    playlists: &[Playlist],
+) -> Vec<RestoreAction> {
+    let mut actions = vec![];
+
+    let tracks = export
+        .saved_tracks
+        .iter()
+        .rev()
+        .map(|t| &t.track.id)
+        .filter(|id| !existing.contains(&id.uri()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !tracks.is_empty() {
+        actions.push(RestoreAction::SaveTracks(tracks));
+    }
+    let albums = export
+        .saved_albums
+        .iter()
+        .rev()
+        .map(|a| &a.id)
+        .filter(|id| !existing.contains(&id.uri()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !albums.is_empty() {
+        actions.push(RestoreAction::SaveAlbums(albums));
+    }
+    let artists = export
+        .followed_artists
+        .iter()
+        .map(|a| &a.id)
+        .filter(|id| !existing.contains(&id.uri()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !artists.is_empty() {
+        actions.push(RestoreAction::FollowArtists(artists));
+    }
+
+    for export in &export.playlists {
+        let playlist = &export.playlist;
+        let owned = playlist.owner.1.id() == user_id.id();
+        let restored = playlists.iter().any(|p| {
+            p.id.id() == playlist.id.id()
+                || (owned && p.owner.1.id() == user_id.id() && p.name == playlist.name)
+        });
+        if restored {
+            continue;
+        }
+        if owned {
+            actions.push(RestoreAction::CreatePlaylist {
+                name: playlist.name.clone(),
+                desc: playlist.desc.clone(),
+                collaborative: playlist.collaborative,
+                uris: export.track_uris.clone(),
+            });
+        } else {
+            actions.push(RestoreAction::FollowPlaylist(playlist.id.clone()));
+        }
+    }
+
+    actions
+}
+
+/// runs the writes planned by `plan_restore` in order with `execute`,
+/// or only reports them with `RestoreMode::DryRun`
+pub(crate) async fn run_restore<F, Fut>(
+    actions: Vec<RestoreAction>,
+    mode: RestoreMode,
+    mut execute: F,
+) -> Result<RestoreReport>
+where
+    F: FnMut(&RestoreAction) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let dry_run = mode == RestoreMode::DryRun;
+    for action in &actions {
+        if dry_run {
+            tracing::info!("Restore (dry run): {action}");
+        } else {
+            tracing::info!("Restore: {action}");
+            execute(action).await?;
+        }
+    }
+
+    Ok(RestoreReport { actions, dry_run })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+
+    fn track(id: &str) -> SavedTrack {
+        SavedTrack {
+            track: Track::fixture(id),
+            added_at: chrono::Utc::now(),
+        }
+    }
+
+    fn playlist(id: &str, owner: &str) -> PlaylistExport {
+        PlaylistExport {
+            playlist: Playlist {
+                id: PlaylistId::from_id(id.to_string()).unwrap(),
+                collaborative: false,
+                name: id.to_string(),
+                owner: (
+                    owner.to_string(),
+                    UserId::from_id(owner.to_string()).unwrap(),
+                ),
+                desc: String::new(),
+                snapshot_id: String::new(),
+            },
+            track_uris: vec!["spotify:track:a".to_string()],
+        }
+    }
+
+    fn export() -> LibraryExport {
+        LibraryExport {
+            version: LIBRARY_EXPORT_VERSION,
+            // the newest saved track first
+            saved_tracks: vec![track("c"), track("b"), track("a")],
+            saved_albums: vec![],
+            followed_artists: vec![Artist {
+                id: ArtistId::from_id("x").unwrap(),
+                name: "x".to_string(),
+                followers: None,
+                popularity: None,
+            }],
+            playlists: vec![
+                playlist("mine", "me"),
+                playlist("theirs", "other"),
+                playlist("existing", "me"),
+            ],
+        }
+    }
+
+    /// applies the writes of a restore to a library of `existing` URIs and `playlists`
+    fn apply(
+        action: &RestoreAction,
+        existing: &mut HashSet<String>,
+        playlists: &mut Vec<Playlist>,
+    ) {
+        match action {
+            RestoreAction::SaveTracks(ids) => existing.extend(ids.iter().map(|id| id.uri())),
+            RestoreAction::SaveAlbums(ids) => existing.extend(ids.iter().map(|id| id.uri())),
+            RestoreAction::FollowArtists(ids) => existing.extend(ids.iter().map(|id| id.uri())),
+            // a recreated playlist has a new id
+            RestoreAction::CreatePlaylist { name, .. } => playlists.push(Playlist {
+                name: name.clone(),
+                ..playlist(&format!("new{}", playlists.len()), "me").playlist
+            }),
+            RestoreAction::FollowPlaylist(id) => {
+                playlists.push(playlist(id.id(), "other").playlist)
+            }
+        }
+    }
+
+    #[test]
+    fn plan_library_restore() {
+        let export = export();
+        let existing = ["spotify:track:b".to_string()].into_iter().collect();
+        let playlists = [playlist("existing", "me").playlist];
+
+        let actions = plan_restore(
+            &export,
+            &UserId::from_id("me").unwrap(),
+            &existing,
+            &playlists,
+        );
+        assert_eq!(
+            actions,
+            vec![
+                RestoreAction::SaveTracks(vec![
+                    TrackId::from_id("a").unwrap(),
+                    TrackId::from_id("c").unwrap(),
+                ]),
+                RestoreAction::FollowArtists(vec![ArtistId::from_id("x").unwrap()]),
+                RestoreAction::CreatePlaylist {
+                    name: "mine".to_string(),
+                    desc: String::new(),
+                    collaborative: false,
+                    uris: vec!["spotify:track:a".to_string()],
+                },
+                RestoreAction::FollowPlaylist(PlaylistId::from_id("theirs").unwrap()),
+            ]
+        );
+
+        // a playlist of another user with the same name as an owned playlist isn't a match
+        let playlists = [Playlist {
+            name: "mine".to_string(),
+            ..playlist("other", "other").playlist
+        }];
+        let actions = plan_restore(
+            &export,
+            &UserId::from_id("me").unwrap(),
+            &existing,
+            &playlists,
+        );
+        assert_eq!(
+            actions
+                .iter()
+                .filter(|action| matches!(action, RestoreAction::CreatePlaylist { .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn restore_twice() {
+        let export = export();
+        let user_id = UserId::from_id("me").unwrap();
+        let mut existing = HashSet::new();
+        let mut playlists = vec![];
+
+        let actions = plan_restore(&export, &user_id, &existing, &playlists);
+        let report = run_restore(actions.clone(), RestoreMode::Merge, |action| {
+            apply(action, &mut existing, &mut playlists);
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.actions, actions);
+        assert_eq!(playlists.len(), 3);
+
+        // the restored library, with its recreated playlists, has nothing left to restore
+        assert!(plan_restore(&export, &user_id, &existing, &playlists).is_empty());
+    }
+
+    #[tokio::test]
+    async fn plan_dry_run_restore() {
+        let export = export();
+        let user_id = UserId::from_id("me").unwrap();
+        let existing = HashSet::new();
+
+        let actions = plan_restore(&export, &user_id, &existing, &[]);
+        let mut writes = 0;
+        let report = run_restore(actions.clone(), RestoreMode::DryRun, |_| {
+            writes += 1;
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.actions, actions);
+        assert_eq!(writes, 0);
+    }
+
+    #[test]
+    fn restore_deserialized_export() {
+        let export = LibraryExport {
+            version: LIBRARY_EXPORT_VERSION,
+            saved_tracks: vec![track("a")],
+            saved_albums: vec![],
+            followed_artists: vec![],
+            playlists: vec![playlist("mine", "me"), playlist("theirs", "other")],
+        };
+        let json = serde_json::to_string(&export).unwrap();
+        let deserialized = serde_json::from_str::<LibraryExport>(&json).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+
+        // the deserialized export is restored as the exported one
+        let user_id = UserId::from_id("me").unwrap();
+        let existing = HashSet::new();
+        assert_eq!(
+            plan_restore(&deserialized, &user_id, &existing, &[]),
+            plan_restore(&export, &user_id, &existing, &[])
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::Result;
+
+use super::operation;
+use crate::config::RetryConfig;
+use crate::utils::Rng;
+
+/// The status codes of the transient server errors, retried after a backoff
+const TRANSIENT_STATUS_CODES: [u16; 3] = [502, 503, 504];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a failed attempt of a request is retried
+enum Retry {
+    /// retry after the delay of a rate-limited response's `Retry-After` header
+    After(Duration),
+    /// retry after an exponential backoff delay
+    Backoff,
+    Never,
+}
+
+/// decides how an attempt whose response has a `status` code is retried.
+///
+/// A rate-limited request is always retried. A transient server error is only retried
+/// for an idempotent request, which may have been applied before the error.
+fn retry_on_status(status: u16, retry_after: Option<&str>, idempotent: bool) -> Retry {
+    if status == 429 {
+        match retry_after.and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(seconds) => Retry::After(Duration::from_secs(seconds)),
+            None => Retry::Backoff,
+        }
+    } else if idempotent && TRANSIENT_STATUS_CODES.contains(&status) {
+        Retry::Backoff
+    } else {
+        Retry::Never
+    }
+}
+
+/// gets the backoff delay before the `attempt`-th retry (starting from 1): the base delay
+/// doubled after each retry, capped by the maximum delay, with a random `jitter` (between 0 and 1)
+/// reducing the delay by up to a half to spread out the retries of concurrent requests
+fn backoff_delay(config: &RetryConfig, attempt: u32, jitter: f64) -> Duration {
+    let delay = config
+        .base_delay()
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(config.max_delay());
+    delay.mul_f64(1.0 - jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// sends a request with `send`, retrying the failed attempts following the retry configuration:
+/// - a rate-limited response (429) is retried after the delay of its `Retry-After` header,
+///   or after a backoff delay without the header
+/// - a transient server error (502, 503, 504) or a network failure is retried after
+///   an exponential backoff delay, only if the request is idempotent
+///
+/// The response of the last attempt is returned whatever its status code.
+pub(crate) async fn send_with_retry<F, Fut>(
+    config: &RetryConfig,
+    idempotent: bool,
+    mut send: F,
+) -> Result<reqwest::Response>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = reqwest::Result<reqwest::Response>>,
+{
+    let mut rng = Rng::new(None);
+    let mut attempt = 1;
+    loop {
+        let result = send().await;
+        let retry = match &result {
+            Ok(response) => retry_on_status(
+                response.status().as_u16(),
+                response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok()),
+                idempotent,
+            ),
+            Err(err) if idempotent && (err.is_connect() || err.is_timeout()) => Retry::Backoff,
+            Err(_) => Retry::Never,
+        };
+        let delay = match retry {
+            Retry::After(delay) => (delay <= config.max_delay()).then_some(delay),
+            Retry::Backoff => {
+                let jitter = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+                Some(backoff_delay(config, attempt, jitter))
+            }
+            Retry::Never => None,
+        };
+
+        match delay {
+            Some(delay) if attempt < config.max_attempts => {
+                let failure = match &result {
+                    Ok(response) => format!("status code {}", response.status()),
+                    Err(err) => format!("error {err}"),
+                };
+                tracing::warn!(
+                    "request failed with {failure}, retrying in {delay:?} (attempt {attempt}/{})",
+                    config.max_attempts
+                );
+                operation::sleep(delay).await?;
+                attempt += 1;
+            }
+            _ => return Ok(result?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retried_statuses() {
+        assert_eq!(
+            retry_on_status(429, Some("3"), false),
+            Retry::After(Duration::from_secs(3))
+        );
+        assert_eq!(retry_on_status(429, None, true), Retry::Backoff);
+        assert_eq!(retry_on_status(429, Some("soon"), true), Retry::Backoff);
+        assert_eq!(retry_on_status(503, None, true), Retry::Backoff);
+        // a non-idempotent request may have been applied before a server error
+        assert_eq!(retry_on_status(503, None, false), Retry::Never);
+        assert_eq!(retry_on_status(500, None, true), Retry::Never);
+        assert_eq!(retry_on_status(404, None, true), Retry::Never);
+    }
+
+    #[test]
+    fn exponential_backoff_delays() {
+        let config = RetryConfig {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        let delays = (1..=6)
+            .map(|attempt| backoff_delay(&config, attempt, 0.0).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(backoff_delay(&config, 2, 1.0), Duration::from_millis(100));
+        assert_eq!(backoff_delay(&config, 64, 0.0), Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn cancel_retry_sleep() {
+        let config = RetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 60_000,
+        };
+        let token = operation::CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let start = std::time::Instant::now();
+        let mut attempts = 0;
+        let (result, _) = operation::scope(
+            None,
+            Some(token),
+            send_with_retry(&config, true, || {
+                attempts += 1;
+                async {
+                    let response = http::Response::builder()
+                        .status(429)
+                        .header(reqwest::header::RETRY_AFTER, "30")
+                        .body("")
+                        .unwrap();
+                    Ok::<_, reqwest::Error>(reqwest::Response::from(response))
+                }
+            }),
+        )
+        .await;
+
+        // the rate-limited request is cancelled while waiting to be retried
+        assert!(matches!(
+            result
+                .unwrap_err()
+                .downcast_ref::<crate::error::ClientError>(),
+            Some(crate::error::ClientError::Cancelled)
+        ));
+        assert_eq!(attempts, 1);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, future::Future};
+
+use anyhow::Result;
+use tokio::sync::watch;
+
+use super::operation;
+
+/// A group of calls coalescing the concurrent calls of the same key into a single call,
+/// whose successful result is shared with every caller.
+///
+/// The errors are never shared: the callers waiting for a failed call make their own calls.
+/// If the leading call is dropped (e.g. its task is cancelled), a waiting caller takes over.
+pub(crate) struct SingleFlight<T> {
+    calls: parking_lot::Mutex<HashMap<String, watch::Receiver<Flight<T>>>>,
+}
+
+#[derive(Clone)]
+/// The state of an in-flight call
+enum Flight<T> {
+    Pending,
+    Done(T),
+    Failed,
+}
+
+/// The leader of an in-flight call, removing the call once finished or dropped
+struct Leader<'a, T> {
+    group: &'a SingleFlight<T>,
+    key: String,
+    sender: watch::Sender<Flight<T>>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            calls: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// runs `call` for a key, unless a call of the same key is in flight,
+    /// in which case the in-flight call's result is returned
+    pub async fn run<F, Fut>(&self, key: String, call: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        loop {
+            let receiver = {
+                let mut calls = self.calls.lock();
+                match calls.get(&key) {
+                    Some(receiver) => receiver.clone(),
+                    None => {
+                        let (sender, receiver) = watch::channel(Flight::Pending);
+                        calls.insert(key.clone(), receiver);
+                        drop(calls);
+
+                        let leader = Leader {
+                            group: self,
+                            key,
+                            sender,
+                        };
+                        let result = call().await;
+                        leader.finish(&result);
+                        return result;
+                    }
+                }
+            };
+
+            match Self::wait(receiver).await? {
+                Some(Flight::Done(value)) => return Ok(value),
+                // the leading call failed, its error isn't shared
+                Some(_) => return call().await,
+                // the leading call was dropped, retry to lead or join a new call
+                None => continue,
+            }
+        }
+    }
+
+    /// waits for an in-flight call to finish, returning `None` if it was dropped
+    async fn wait(mut receiver: watch::Receiver<Flight<T>>) -> Result<Option<Flight<T>>> {
+        loop {
+            let flight = receiver.borrow_and_update().clone();
+            if !matches!(flight, Flight::Pending) {
+                return Ok(Some(flight));
+            }
+            if operation::cancellable(receiver.changed()).await?.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+impl<T> Leader<'_, T> {
+    fn finish(self, result: &Result<T>)
+    where
+        T: Clone,
+    {
+        let flight = match result {
+            Ok(value) => Flight::Done(value.clone()),
+            Err(_) => Flight::Failed,
+        };
+        self.sender.send_replace(flight);
+    }
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        self.group.calls.lock().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn coalesce_concurrent_calls() {
+        let group = SingleFlight::default();
+        let counter = AtomicUsize::new(0);
+        let calls = &counter;
+        let call = move || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            anyhow::Ok("artist".to_string())
+        };
+
+        let results = futures::future::join_all(
+            (0..5).map(|_| group.run("spotify:artist:1".to_string(), call)),
+        )
+        .await;
+        assert!(results.iter().all(|r| r.as_deref().ok() == Some("artist")));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // a finished call isn't cached
+        group
+            .run("spotify:artist:1".to_string(), call)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn never_share_errors() {
+        let group = SingleFlight::<String>::default();
+        let counter = AtomicUsize::new(0);
+        let calls = &counter;
+        let call = move || async move {
+            let i = calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if i == 0 {
+                anyhow::bail!("failed");
+            }
+            Ok(format!("call {i}"))
+        };
+
+        let (first, second) = tokio::join!(group.run("k".to_string(), call), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            group.run("k".to_string(), call).await
+        });
+        assert!(first.is_err());
+        assert_eq!(second.unwrap(), "call 1");
+    }
+
+    #[tokio::test]
+    async fn take_over_dropped_calls() {
+        let group = SingleFlight::<String>::default();
+        let call = |name: &'static str| {
+            move || async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                anyhow::Ok(name.to_string())
+            }
+        };
+
+        let leader = group.run("k".to_string(), call("leader"));
+        let waiter = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            group.run("k".to_string(), call("waiter")).await
+        };
+        // the leading call is dropped when it times out
+        let (dropped, waited) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(20), leader),
+            waiter
+        );
+        assert!(dropped.is_err());
+        assert_eq!(waited.unwrap(), "waiter");
+        assert!(group.calls.lock().is_empty());
+    }
+}
@@ -3,14 +3,20 @@ use librespot_core::session::Session;
 use maybe_async::maybe_async;
 use rspotify::{
     clients::{BaseClient, OAuthClient},
-    http::HttpClient,
+    http::{BaseHttpClient, HttpClient, Query},
     sync::Mutex,
     ClientResult, Config, Credentials, OAuth, Token,
 };
+use serde_json::Value;
 use std::{fmt, sync::Arc};
+use tokio::sync::{Semaphore, SemaphorePermit};
 
+use super::operation;
 use crate::token;
 
+/// The margin before a token's expiry at which the token is proactively refreshed
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+
 #[derive(Clone, Default)]
 /// A Spotify client to interact with Spotify API server
 pub struct Spotify {
@@ -18,6 +24,10 @@ pub struct Spotify {
     oauth: OAuth,
     config: Config,
     token: Arc<Mutex<Option<Token>>>,
+    /// held while refreshing the token, so that the concurrent requests refresh it only once
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
+    /// the permits of the concurrent requests to Spotify Web API, unbounded if `None`
+    requests: Option<Arc<Semaphore>>,
     client_id: String,
     http: HttpClient,
     // session should always be non-empty, but `Option` is used to implement `Default`,
@@ -38,8 +48,8 @@ impl fmt::Debug for Spotify {
 }
 
 impl Spotify {
-    /// creates a new Spotify client
-    pub fn new(session: Session, client_id: String) -> Spotify {
+    /// creates a new Spotify client, sending at most `max_concurrent_requests` requests at once
+    pub fn new(session: Session, client_id: String, max_concurrent_requests: usize) -> Spotify {
         Self {
             creds: Credentials::default(),
             oauth: OAuth::default(),
@@ -48,6 +58,8 @@ impl Spotify {
                 ..Default::default()
             },
             token: Arc::new(Mutex::new(None)),
+            refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
+            requests: Some(Arc::new(Semaphore::new(max_concurrent_requests.max(1)))),
             http: HttpClient::default(),
             session: Arc::new(tokio::sync::Mutex::new(Some(session))),
             client_id,
@@ -64,16 +76,56 @@ impl Spotify {
 
     /// gets a Spotify access token.
     /// The function may retrieve a new token and update the current token
-    /// stored inside the client if the old one is expired.
+    /// stored inside the client if the old one expires within a minute.
     pub async fn access_token(&self) -> Result<String> {
-        let should_update = match self.token.lock().await.unwrap().as_ref() {
-            Some(token) => token.is_expired(),
-            None => true,
-        };
-        if should_update {
-            self.refresh_token().await?;
+        if let Some(token) = self.valid_access_token().await {
+            return Ok(token);
+        }
+
+        let _refreshing = self.refresh_lock.lock().await;
+        // the token may have been refreshed while waiting for the lock
+        if let Some(token) = self.valid_access_token().await {
+            return Ok(token);
+        }
+        self.refresh_token().await?;
+        self.stored_access_token().await
+    }
+
+    /// retrieves a new token, even if the current token is still valid
+    pub async fn force_token_refresh(&self) -> Result<String> {
+        let _refreshing = self.refresh_lock.lock().await;
+        self.refresh_token().await?;
+        self.stored_access_token().await
+    }
+
+    /// waits for a permit to send a request, which is released when dropped.
+    ///
+    /// The permits bound the concurrent requests of the client, whatever the features
+    /// sending them, see `AppConfig::max_concurrent_requests`.
+    pub async fn request_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.requests {
+            Some(requests) => requests.acquire().await.ok(),
+            None => None,
         }
+    }
+
+    /// gets the stored access token if it doesn't expire within the refresh margin
+    async fn valid_access_token(&self) -> Option<String> {
+        let margin = chrono::Duration::seconds(TOKEN_REFRESH_MARGIN_SECS);
+        self.token
+            .lock()
+            .await
+            .unwrap()
+            .as_ref()
+            .filter(|token| {
+                token
+                    .expires_at
+                    .is_some_and(|expires_at| expires_at - chrono::Utc::now() > margin)
+            })
+            .map(|token| token.access_token.clone())
+    }
 
+    async fn stored_access_token(&self) -> Result<String> {
         match self.token.lock().await.unwrap().as_ref() {
             Some(token) => Ok(token.access_token.clone()),
             None => Err(anyhow!(
@@ -83,6 +135,15 @@ impl Spotify {
     }
 }
 
+/// fails a request before it's sent if the current operation is cancelled or its deadline
+/// is reached, see `operation::check`
+fn check_operation() -> ClientResult<()> {
+    match operation::stop_error() {
+        Some(err) => Err(rspotify::ClientError::Io(std::io::Error::other(err))),
+        None => Ok(()),
+    }
+}
+
 // TODO: remove the below uses of `maybe_async` crate once
 // async trait is fully supported in stable Rust.
 
@@ -104,6 +165,44 @@ impl BaseClient for Spotify {
         &self.config
     }
 
+    // the requests of the API endpoints are overridden to bound their concurrency,
+    // see `Spotify::request_permit`, and to stop the cancelled or timed out operations
+
+    async fn api_get(&self, url: &str, payload: &Query<'_>) -> ClientResult<String> {
+        let url = self.api_url(url);
+        let headers = self.auth_headers().await?;
+        let _permit = self.request_permit().await;
+        check_operation()?;
+        Ok(self.get_http().get(&url, Some(&headers), payload).await?)
+    }
+
+    async fn api_post(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let url = self.api_url(url);
+        let headers = self.auth_headers().await?;
+        let _permit = self.request_permit().await;
+        check_operation()?;
+        Ok(self.get_http().post(&url, Some(&headers), payload).await?)
+    }
+
+    async fn api_put(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let url = self.api_url(url);
+        let headers = self.auth_headers().await?;
+        let _permit = self.request_permit().await;
+        check_operation()?;
+        Ok(self.get_http().put(&url, Some(&headers), payload).await?)
+    }
+
+    async fn api_delete(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let url = self.api_url(url);
+        let headers = self.auth_headers().await?;
+        let _permit = self.request_permit().await;
+        check_operation()?;
+        Ok(self
+            .get_http()
+            .delete(&url, Some(&headers), payload)
+            .await?)
+    }
+
     async fn refetch_token(&self) -> ClientResult<Option<Token>> {
         let session = self.session().await;
         let old_token = self.token.lock().await.unwrap().clone();
@@ -141,3 +240,57 @@ impl OAuthClient for Spotify {
         panic!("`OAuthClient::request_token` should never be called!")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ClientError;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn release_locks_on_cancellation() {
+        let spotify = Spotify::default();
+        let token = operation::CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        // a call is cancelled while holding the session and waiting for the token refresh
+        let refreshing = spotify.refresh_lock.lock().await;
+        let (result, _) = operation::scope(None, Some(token), async {
+            let _session = spotify.session.lock().await;
+            let _refreshing = operation::cancellable(spotify.refresh_lock.lock()).await?;
+            anyhow::Ok(())
+        })
+        .await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<ClientError>(),
+            Some(ClientError::Cancelled)
+        ));
+
+        // the locks are released, so that the next calls don't wait forever
+        drop(refreshing);
+        assert!(spotify.session.try_lock().is_ok());
+        assert!(spotify.refresh_lock.try_lock().is_ok());
+        let access_token = tokio::time::timeout(Duration::from_secs(1), spotify.token.lock()).await;
+        assert!(access_token.unwrap().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn stop_requests_of_stopped_operations() {
+        assert!(check_operation().is_ok());
+
+        let (result, _) =
+            operation::scope(Some(Instant::now()), None, async { check_operation() }).await;
+        let Err(rspotify::ClientError::Io(err)) = result else {
+            panic!("unexpected result: {result:?}");
+        };
+        assert!(matches!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<ClientError>()),
+            Some(ClientError::DeadlineExceeded)
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    ops::Range,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A move of a playlist item, whose positions are relative to the playlist
+/// after the removals and the previous moves
+pub(crate) struct Move {
+    pub range_start: usize,
+    /// the item's position after the move
+    pub insert_index: usize,
+    /// the item's index in the desired list
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The operations transforming a playlist's items into a desired list,
+/// to be applied in order: removals, then moves, then insertions
+pub(crate) struct PlaylistDiff {
+    /// the positions of the removed items in the current playlist
+    pub removals: Vec<usize>,
+    pub moves: Vec<Move>,
+    /// the insertions of desired items (by their indices in the desired list) at a position,
+    /// relative to the playlist after the moves and the previous insertions
+    pub insertions: Vec<(usize, Range<usize>)>,
+}
+
+/// computes a longest common subsequence of the current and desired items,
+/// returning the pairs of matched indices in increasing order.
+///
+/// The LCS is computed as the longest increasing subsequence of the matching index pairs
+/// (Hunt–Szymanski), which is fast for lists with few duplicated items like playlists.
+fn longest_common_subsequence<T: Eq + Hash>(
+    current: &[Option<T>],
+    desired: &[T],
+) -> Vec<(usize, usize)> {
+    let mut positions: HashMap<&T, Vec<usize>> = HashMap::new();
+    for (i, item) in current.iter().enumerate() {
+        if let Some(item) = item {
+            positions.entry(item).or_default().push(i);
+        }
+    }
+
+    // the matching pairs are ordered by their desired index, then by their current index
+    // in the decreasing order, so that an increasing subsequence never reuses a desired item
+    let matches = desired
+        .iter()
+        .enumerate()
+        .flat_map(|(j, item)| {
+            positions
+                .get(item)
+                .into_iter()
+                .flat_map(move |ps| ps.iter().rev().map(move |&i| (i, j)))
+        })
+        .collect::<Vec<_>>();
+
+    // patience sorting: `tails[k]` is the last match of the best increasing subsequence of length `k + 1`
+    let mut tails: Vec<usize> = vec![];
+    let mut predecessors = vec![None; matches.len()];
+    for (m, &(i, _)) in matches.iter().enumerate() {
+        let k = tails.partition_point(|&t| matches[t].0 < i);
+        predecessors[m] = k.checked_sub(1).map(|k| tails[k]);
+        if k == tails.len() {
+            tails.push(m);
+        } else {
+            tails[k] = m;
+        }
+    }
+
+    let mut lcs = vec![];
+    let mut m = tails.last().copied();
+    while let Some(i) = m {
+        lcs.push(matches[i]);
+        m = predecessors[i];
+    }
+    lcs.reverse();
+    lcs
+}
+
+/// computes a minimal set of operations transforming the current items of a playlist
+/// into the desired items.
+///
+/// The items of the longest common subsequence are kept in place, the other current items
+/// are moved if they are desired elsewhere, or removed otherwise. `None` items (unavailable items)
+/// cannot be removed, so they are kept, next to their current neighbours.
+pub(crate) fn diff_playlist<T: Eq + Hash>(current: &[Option<T>], desired: &[T]) -> PlaylistDiff {
+    let lcs = longest_common_subsequence(current, desired);
+
+    // the desired index of each kept current item
+    let mut kept = vec![None; current.len()];
+    let mut matched = vec![false; desired.len()];
+    // whether a desired item is placed in the right order relatively to the other settled items
+    let mut settled = vec![false; desired.len()];
+    for &(i, j) in &lcs {
+        kept[i] = Some(j);
+        matched[j] = true;
+        settled[j] = true;
+    }
+
+    // pair the remaining current items with the remaining desired items of the same value
+    let mut unmatched: HashMap<&T, VecDeque<usize>> = HashMap::new();
+    for (j, item) in desired.iter().enumerate() {
+        if !matched[j] {
+            unmatched.entry(item).or_default().push_back(j);
+        }
+    }
+    let mut diff = PlaylistDiff::default();
+    let mut moved = vec![];
+    for (i, item) in current.iter().enumerate() {
+        let Some(item) = item else {
+            continue;
+        };
+        if kept[i].is_some() {
+            continue;
+        }
+        match unmatched.get_mut(item).and_then(|js| js.pop_front()) {
+            Some(j) => {
+                kept[i] = Some(j);
+                matched[j] = true;
+                moved.push(j);
+            }
+            None => diff.removals.push(i),
+        }
+    }
+
+    // the playlist's items after the removals, `None` for an unavailable item
+    let mut items = current
+        .iter()
+        .zip(&kept)
+        .filter(|(item, j)| item.is_none() || j.is_some())
+        .map(|(_, j)| *j)
+        .collect::<Vec<_>>();
+    let position = |items: &[Option<usize>], j: usize| {
+        items
+            .iter()
+            .position(|&k| k == Some(j))
+            .expect("item should be in the playlist")
+    };
+
+    // move each moved item right after its closest settled predecessor in the desired order,
+    // which keeps the settled items ordered
+    moved.sort_unstable();
+    for j in moved {
+        let range_start = position(&items, j);
+        items.remove(range_start);
+        let insert_index = (0..j)
+            .rev()
+            .find(|&k| settled[k])
+            .map_or(0, |k| position(&items, k) + 1);
+        items.insert(insert_index, Some(j));
+        settled[j] = true;
+
+        if insert_index != range_start {
+            diff.moves.push(Move {
+                range_start,
+                insert_index,
+                index: j,
+            });
+        }
+    }
+
+    // insert the remaining desired items right after their predecessors,
+    // grouping the consecutive items
+    for j in (0..desired.len()).filter(|&j| !matched[j]) {
+        let insert_index = match j {
+            0 => 0,
+            _ => position(&items, j - 1) + 1,
+        };
+        items.insert(insert_index, Some(j));
+
+        match diff.insertions.last_mut() {
+            Some((start, range)) if range.end == j && *start + range.len() == insert_index => {
+                range.end += 1;
+            }
+            _ => diff.insertions.push((insert_index, j..j + 1)),
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// applies a diff to the current items, returning the resulting items
+    fn apply(current: &[Option<char>], desired: &[char], diff: &PlaylistDiff) -> Vec<Option<char>> {
+        let mut items = current
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !diff.removals.contains(i))
+            .map(|(_, item)| *item)
+            .collect::<Vec<_>>();
+        for m in &diff.moves {
+            let item = items.remove(m.range_start);
+            assert_eq!(item, Some(desired[m.index]));
+            items.insert(m.insert_index, item);
+        }
+        for (position, range) in &diff.insertions {
+            for (k, j) in range.clone().enumerate() {
+                items.insert(position + k, Some(desired[j]));
+            }
+        }
+        items
+    }
+
+    fn check(current: &str, desired: &str) -> PlaylistDiff {
+        // `?` stands for an unavailable item
+        let current = current
+            .chars()
+            .map(|c| (c != '?').then_some(c))
+            .collect::<Vec<_>>();
+        let desired = desired.chars().collect::<Vec<_>>();
+
+        let diff = diff_playlist(&current, &desired);
+        let result = apply(&current, &desired, &diff);
+        assert_eq!(
+            result.iter().flatten().copied().collect::<Vec<_>>(),
+            desired,
+            "{diff:?}"
+        );
+        // unavailable items are never removed
+        assert_eq!(
+            result.iter().filter(|c| c.is_none()).count(),
+            current.iter().filter(|c| c.is_none()).count()
+        );
+        diff
+    }
+
+    #[test]
+    fn diff_identical_playlists() {
+        assert_eq!(check("", ""), PlaylistDiff::default());
+        assert_eq!(check("abcde", "abcde"), PlaylistDiff::default());
+    }
+
+    #[test]
+    fn diff_additions_and_removals() {
+        let diff = check("abc", "");
+        assert_eq!(diff.removals, vec![0, 1, 2]);
+
+        let diff = check("", "abc");
+        assert_eq!(diff.insertions, vec![(0, 0..3)]);
+
+        let diff = check("abcd", "xabyzd");
+        assert_eq!(diff.removals, vec![2]);
+        assert!(diff.moves.is_empty());
+        assert_eq!(diff.insertions, vec![(0, 0..1), (3, 3..5)]);
+    }
+
+    #[test]
+    fn diff_moves() {
+        // a single item moved to the front
+        let diff = check("abcde", "eabcd");
+        assert!(diff.removals.is_empty() && diff.insertions.is_empty());
+        assert_eq!(
+            diff.moves,
+            vec![Move {
+                range_start: 4,
+                insert_index: 0,
+                index: 0
+            }]
+        );
+
+        assert_eq!(check("abcde", "adcbe").moves.len(), 2);
+        // swapping adjacent items only needs a single move
+        assert_eq!(check("abcdef", "abdcef").moves.len(), 1);
+        assert_eq!(check("abcdef", "fedcba").moves.len(), 5);
+    }
+
+    #[test]
+    fn diff_duplicated_items() {
+        let diff = check("abab", "ab");
+        assert_eq!(diff.removals.len(), 2);
+        assert!(diff.moves.is_empty());
+
+        let diff = check("aab", "baa");
+        assert_eq!(diff.moves.len(), 1);
+        check("abcabc", "cbacba");
+        check("aaaa", "aa");
+        check("a", "aaaa");
+    }
+
+    #[test]
+    fn diff_with_unavailable_items() {
+        check("?ab?c", "cab");
+        check("?", "ab");
+        check("a?b", "ba");
+        let diff = check("a??b", "ab");
+        assert_eq!(diff, PlaylistDiff::default());
+    }
+
+    #[test]
+    fn diff_mixed_operations() {
+        for (current, desired) in [
+            ("abcdefgh", "hgxbdcya"),
+            ("abcabcabc", "cab"),
+            ("xyz", "zzyyxx"),
+            ("a?b?c?d", "dcbae"),
+        ] {
+            check(current, desired);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{collections::HashMap, future::Future, ops::Range, pin::Pin, sync::Arc};
+
+use anyhow::Result;
+use tokio::sync::OnceCell;
+
+/// A future fetching a page of items, resolving to the page's items and the collection's total
+pub type PageFuture<'a, T> = Pin<Box<dyn Future<Output = Result<(Vec<T>, usize)>> + Send + 'a>>;
+
+type PageFetcher<'a, T> = Box<dyn Fn(usize, usize) -> PageFuture<'a, Option<T>> + Send + Sync + 'a>;
+
+/// A handle to an offset-based paginated collection whose pages are lazily fetched
+/// when a window (range) of the collection is requested, e.g. the rows visible in a list.
+///
+/// Fetched pages are cached until `refresh` is called. Concurrent `get_range` calls
+/// for overlapping windows share the fetches of their common pages.
+///
+/// A page may have gaps (e.g. unavailable tracks), which keep their positions
+/// in the collection but are skipped in the windows, see `WindowedCollection::with_gaps`.
+pub struct WindowedCollection<'a, T> {
+    page_size: usize,
+    fetch_page: PageFetcher<'a, T>,
+    pages: parking_lot::Mutex<HashMap<usize, Arc<OnceCell<Vec<Option<T>>>>>>,
+    total: parking_lot::Mutex<Option<usize>>,
+}
+
+impl<'a, T: Clone + Send + 'a> WindowedCollection<'a, T> {
+    /// creates a windowed collection with pages of `page_size` items (clamped between 1
+    /// and the Spotify Web API's maximum page size of 50), fetched with `fetch_page(offset, limit)`
+    pub fn new<F>(page_size: usize, fetch_page: F) -> Self
+    where
+        F: Fn(usize, usize) -> PageFuture<'a, T> + Send + Sync + 'a,
+    {
+        Self::with_gaps(page_size, move |offset, limit| {
+            let page = fetch_page(offset, limit);
+            Box::pin(async move {
+                let (items, total) = page.await?;
+                anyhow::Ok((items.into_iter().map(Some).collect(), total))
+            })
+        })
+    }
+
+    /// creates a windowed collection with pages of `page_size` items (clamped, see `new`),
+    /// fetched with `fetch_page(offset, limit)`, whose `None` items are gaps: they keep their positions
+    /// in the collection, but are skipped in the windows
+    pub fn with_gaps<F>(page_size: usize, fetch_page: F) -> Self
+    where
+        F: Fn(usize, usize) -> PageFuture<'a, Option<T>> + Send + Sync + 'a,
+    {
+        Self {
+            page_size: page_size.clamp(1, super::MAX_PAGE_SIZE),
+            fetch_page: Box::new(fetch_page),
+            pages: Default::default(),
+            total: Default::default(),
+        }
+    }
+
+    /// gets the items of a window of the collection, fetching the pages covering
+    /// the window if they are not cached.
+    ///
+    /// The window is clamped to the collection's size and its gaps are skipped, so it may
+    /// contain fewer items than its length. The items are cloned from the cache,
+    /// as the cached pages are shared with the concurrent calls.
+    pub async fn get_range(&self, range: Range<usize>) -> Result<Vec<T>> {
+        let mut items = vec![];
+        if range.is_empty() {
+            return Ok(items);
+        }
+
+        let first_page = range.start / self.page_size;
+        let last_page = (range.end - 1) / self.page_size;
+        for page in first_page..=last_page {
+            let offset = page * self.page_size;
+            if matches!(*self.total.lock(), Some(total) if offset >= total) {
+                break;
+            }
+
+            let page_items = self.page(page).await?;
+            items.extend(
+                page_items
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| range.contains(&(offset + i)))
+                    .filter_map(|(_, item)| item),
+            );
+        }
+
+        Ok(items)
+    }
+
+    /// gets the total number of items in the collection, fetching the first page if unknown
+    pub async fn total(&self) -> Result<usize> {
+        let total = *self.total.lock();
+        if let Some(total) = total {
+            return Ok(total);
+        }
+        self.page(0).await?;
+        Ok(self.total.lock().unwrap_or_default())
+    }
+
+    /// invalidates the cached pages, which will be fetched again on the next requests
+    pub fn refresh(&self) {
+        self.pages.lock().clear();
+        *self.total.lock() = None;
+    }
+
+    /// gets the items of a page, fetching the page if it is not cached
+    async fn page(&self, page: usize) -> Result<Vec<Option<T>>> {
+        let cell = self.pages.lock().entry(page).or_default().clone();
+        let items = cell
+            .get_or_try_init(|| async {
+                let (items, total) =
+                    (self.fetch_page)(page * self.page_size, self.page_size).await?;
+                *self.total.lock() = Some(total);
+                anyhow::Ok(items)
+            })
+            .await?;
+        Ok(items.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// creates a windowed collection of `total` numbers, recording the offsets of the page requests
+    fn collection(
+        total: usize,
+        page_size: usize,
+        requests: &parking_lot::Mutex<Vec<usize>>,
+    ) -> WindowedCollection<'_, usize> {
+        WindowedCollection::new(page_size, move |offset, limit| {
+            requests.lock().push(offset);
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let items = (offset..std::cmp::min(offset + limit, total)).collect();
+                anyhow::Ok((items, total))
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn scroll_fetches_minimal_pages() {
+        let requests = parking_lot::Mutex::new(vec![]);
+        let collection = collection(500, 50, &requests);
+
+        // scroll through the list with a window of 40 rows
+        for start in (0..500).step_by(10) {
+            let items = collection.get_range(start..start + 40).await.unwrap();
+            let end = std::cmp::min(start + 40, 500);
+            assert_eq!(items, (start..end).collect::<Vec<_>>());
+        }
+        assert_eq!(*requests.lock(), (0..500).step_by(50).collect::<Vec<_>>());
+        assert_eq!(collection.total().await.unwrap(), 500);
+
+        // scrolling back uses the cached pages
+        collection.get_range(100..140).await.unwrap();
+        assert_eq!(requests.lock().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn lazy_window_fetch() {
+        let requests = parking_lot::Mutex::new(vec![]);
+        let collection = collection(500, 50, &requests);
+
+        assert!(collection.get_range(10..10).await.unwrap().is_empty());
+        assert!(requests.lock().is_empty());
+
+        assert_eq!(collection.get_range(230..260).await.unwrap().len(), 30);
+        assert_eq!(*requests.lock(), vec![200, 250]);
+
+        // a window past the end of the collection is clamped
+        assert_eq!(collection.get_range(490..600).await.unwrap().len(), 10);
+        assert!(collection.get_range(600..650).await.unwrap().is_empty());
+        assert_eq!(*requests.lock(), vec![200, 250, 450]);
+    }
+
+    #[tokio::test]
+    async fn coalesce_concurrent_fetches() {
+        let requests = parking_lot::Mutex::new(vec![]);
+        let collection = collection(500, 50, &requests);
+
+        let (a, b, c) = tokio::join!(
+            collection.get_range(0..40),
+            collection.get_range(20..80),
+            collection.get_range(40..120),
+        );
+        assert_eq!(a.unwrap(), (0..40).collect::<Vec<_>>());
+        assert_eq!(b.unwrap(), (20..80).collect::<Vec<_>>());
+        assert_eq!(c.unwrap(), (40..120).collect::<Vec<_>>());
+
+        let mut offsets = requests.lock().clone();
+        offsets.sort_unstable();
+        assert_eq!(offsets, vec![0, 50, 100]);
+    }
+
+    #[tokio::test]
+    async fn refresh_invalidates_pages() {
+        let requests = parking_lot::Mutex::new(vec![]);
+        let collection = collection(500, 50, &requests);
+
+        collection.get_range(0..40).await.unwrap();
+        collection.refresh();
+        collection.get_range(0..40).await.unwrap();
+        assert_eq!(*requests.lock(), vec![0, 0]);
+    }
+
+    #[tokio::test]
+    async fn clamp_page_size() {
+        let requests = parking_lot::Mutex::new(vec![]);
+        let empty_pages = collection(500, 0, &requests);
+        assert_eq!(empty_pages.get_range(0..2).await.unwrap(), vec![0, 1]);
+        assert_eq!(*requests.lock(), vec![0, 1]);
+
+        let requests = parking_lot::Mutex::new(vec![]);
+        let large_pages = collection(500, 300, &requests);
+        assert_eq!(large_pages.get_range(0..100).await.unwrap().len(), 100);
+        assert_eq!(*requests.lock(), vec![0, 50]);
+    }
+
+    #[tokio::test]
+    async fn skip_gaps_at_their_positions() {
+        // the odd numbers are gaps
+        let collection = WindowedCollection::with_gaps(10, |offset, limit| {
+            Box::pin(async move {
+                let items = (offset..std::cmp::min(offset + limit, 100))
+                    .map(|i| (i % 2 == 0).then_some(i))
+                    .collect();
+                anyhow::Ok((items, 100))
+            })
+        });
+
+        // the windows are positioned by the collection's items, gaps included
+        assert_eq!(
+            collection.get_range(5..15).await.unwrap(),
+            vec![6, 8, 10, 12, 14]
+        );
+        assert_eq!(
+            collection.get_range(15..25).await.unwrap(),
+            vec![16, 18, 20, 22, 24]
+        );
+        assert!(collection.get_range(99..100).await.unwrap().is_empty());
+        assert_eq!(collection.total().await.unwrap(), 100);
+    }
+}
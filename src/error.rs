@@ -0,0 +1,235 @@
+use std::{fmt, time::Duration};
+
+#[derive(Debug)]
+/// Typed errors returned by the client.
+///
+/// The errors are surfaced as `anyhow::Error`, callers can inspect them
+/// with `err.downcast_ref::<ClientError>()`.
+pub enum ClientError {
+    /// The session was reconnecting for longer than the call's deadline
+    ReconnectTimeout(Duration),
+    /// The deadline of the current operation was reached before sending a request
+    DeadlineExceeded,
+    /// The current operation was cancelled, see `Client::with_cancellation`
+    Cancelled,
+    /// The playlist was modified since the version identified by the expected snapshot id
+    SnapshotMismatch { expected: String, actual: String },
+    /// The feature (e.g. `"audiobooks"`) is unavailable in the current user's market
+    FeatureUnavailableInMarket(&'static str),
+    /// The browse category of the given id is unavailable in the current user's market
+    CategoryUnavailableInMarket(String),
+    /// The request was rejected as unauthorized (401) even after re-authenticating
+    Unauthorized,
+    /// The Spotify Web API responded with an error status code
+    Api(ApiError),
+    /// The configuration is invalid, e.g. an unparsable proxy URL
+    InvalidConfig(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReconnectTimeout(deadline) => write!(
+                f,
+                "timeout after waiting {deadline:?} for the session to reconnect"
+            ),
+            Self::DeadlineExceeded => write!(f, "the operation's deadline is exceeded"),
+            Self::Cancelled => write!(f, "the operation is cancelled"),
+            Self::SnapshotMismatch { expected, actual } => write!(
+                f,
+                "the playlist was modified: expected snapshot {expected}, found {actual}"
+            ),
+            Self::FeatureUnavailableInMarket(feature) => {
+                write!(f, "{feature} are unavailable in the user's market")
+            }
+            Self::CategoryUnavailableInMarket(id) => {
+                write!(f, "the category {id} is unavailable in the user's market")
+            }
+            Self::Unauthorized => write!(
+                f,
+                "the request is unauthorized even after re-authenticating the session"
+            ),
+            Self::Api(err) => err.fmt(f),
+            Self::InvalidConfig(message) => write!(f, "invalid configuration: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error response (non-2xx status code) of the Spotify Web API
+pub struct ApiError {
+    /// the response's status code
+    pub status: u16,
+    /// the error message of the response's `{"error": {"status": .., "message": ..}}` envelope,
+    /// or the response's text if it isn't an error envelope
+    pub message: Option<String>,
+    /// the requested URL
+    pub url: String,
+}
+
+impl ApiError {
+    /// parses the error response of a request to `url` with a `status` code and a `body` text
+    pub(crate) fn from_response(status: u16, url: &str, body: &str) -> Self {
+        let text = || Some(body.trim().to_string()).filter(|text| !text.is_empty());
+        let message = match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(envelope) => match &envelope["error"] {
+                serde_json::Value::Object(error) => error
+                    .get("message")
+                    .and_then(|message| message.as_str())
+                    .map(|message| message.to_string()),
+                // the authentication errors have a `{"error": .., "error_description": ..}` envelope
+                serde_json::Value::String(error) => Some(
+                    envelope["error_description"]
+                        .as_str()
+                        .unwrap_or(error.as_str())
+                        .to_string(),
+                ),
+                _ => text(),
+            },
+            Err(_) => text(),
+        };
+
+        Self {
+            status,
+            message,
+            url: url.to_string(),
+        }
+    }
+
+    /// whether the requested item doesn't exist (404)
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
+
+    /// whether the request is forbidden (403), e.g. because the item is private
+    /// or the feature requires a Premium account
+    pub fn is_forbidden(&self) -> bool {
+        self.status == 403
+    }
+
+    /// whether the request is forbidden (403) because the requested content or feature
+    /// is unavailable in the current user's market, as told by the response's message.
+    /// A request forbidden for another reason (e.g. a missing scope) isn't.
+    pub fn is_unavailable_in_market(&self) -> bool {
+        self.is_forbidden()
+            && self.message.as_deref().is_some_and(|message| {
+                let message = message.to_lowercase();
+                ["market", "country", "region"]
+                    .iter()
+                    .any(|word| message.contains(word))
+            })
+    }
+
+    /// whether the request was rate-limited (429)
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == 429
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "request to {} failed with status code {}",
+            self.url, self.status
+        )?;
+        if let Some(message) = &self.message {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://api.spotify.com/v1/playlists/37i9dQZF1DXcBWIGoYBM5M";
+
+    #[test]
+    fn parse_api_error_envelopes() {
+        let not_found = ApiError::from_response(
+            404,
+            URL,
+            r#"{"error": {"status": 404, "message": "Resource not found"}}"#,
+        );
+        assert!(not_found.is_not_found());
+        assert_eq!(not_found.message.as_deref(), Some("Resource not found"));
+        assert_eq!(
+            not_found.to_string(),
+            format!("request to {URL} failed with status code 404: Resource not found")
+        );
+
+        let forbidden = ApiError::from_response(
+            403,
+            URL,
+            r#"{"error": {"status": 403, "message": "Player command failed: Premium required"}}"#,
+        );
+        assert!(forbidden.is_forbidden());
+        assert_eq!(
+            forbidden.message.as_deref(),
+            Some("Player command failed: Premium required")
+        );
+
+        let rate_limited = ApiError::from_response(
+            429,
+            URL,
+            r#"{"error": {"status": 429, "message": "API rate limit exceeded"}}"#,
+        );
+        assert!(rate_limited.is_rate_limited());
+        assert!(!forbidden.is_unavailable_in_market());
+        assert_eq!(
+            rate_limited.message.as_deref(),
+            Some("API rate limit exceeded")
+        );
+    }
+
+    #[test]
+    fn parse_non_envelope_api_errors() {
+        let auth = ApiError::from_response(
+            400,
+            URL,
+            r#"{"error": "invalid_client", "error_description": "Invalid client"}"#,
+        );
+        assert_eq!(auth.message.as_deref(), Some("Invalid client"));
+
+        let html = ApiError::from_response(502, URL, "<html>Bad Gateway</html>\n");
+        assert_eq!(html.message.as_deref(), Some("<html>Bad Gateway</html>"));
+
+        let empty = ApiError::from_response(404, URL, "");
+        assert_eq!(empty.message, None);
+        assert_eq!(
+            empty.to_string(),
+            format!("request to {URL} failed with status code 404")
+        );
+    }
+
+    #[test]
+    fn detect_market_restrictions() {
+        let unavailable = ApiError::from_response(
+            403,
+            URL,
+            r#"{"error": {"status": 403, "message": "Content is not available in this market"}}"#,
+        );
+        assert!(unavailable.is_unavailable_in_market());
+
+        // a forbidden request for another reason or without a message isn't a market restriction
+        let missing_scope = ApiError::from_response(
+            403,
+            URL,
+            r#"{"error": {"status": 403, "message": "Insufficient client scope"}}"#,
+        );
+        assert!(!missing_scope.is_unavailable_in_market());
+        assert!(!ApiError::from_response(403, URL, "").is_unavailable_in_market());
+        let not_found = ApiError::from_response(
+            404,
+            URL,
+            r#"{"error": {"status": 404, "message": "Not available in this market"}}"#,
+        );
+        assert!(!not_found.is_unavailable_in_market());
+    }
+}
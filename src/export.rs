@@ -0,0 +1,587 @@
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context as _, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::model::ImportRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The output format of an export
+pub enum ExportFormat {
+    /// A single JSON document
+    Json,
+    /// Newline-delimited JSON, one item per line.
+    /// This format is the most suitable for huge exports as
+    /// it can be consumed line by line.
+    JsonLines,
+    /// Comma-separated values, one item per record.
+    /// The columns are the fields of the first item, nested values are written as JSON.
+    Csv,
+}
+
+/// The source of an imported playlist, see `Client::import_playlist`
+pub enum ImportSource {
+    /// A file, whose format is detected from its extension:
+    /// `.csv` for CSV, `.jsonl` for JSON lines and JSON otherwise
+    Path(PathBuf),
+    Reader(Box<dyn Read + Send>, ExportFormat),
+}
+
+impl ImportSource {
+    /// reads the source's content and format
+    pub(crate) fn read(self) -> Result<(String, ExportFormat)> {
+        match self {
+            Self::Path(path) => {
+                let format = match path.extension().and_then(|e| e.to_str()) {
+                    Some(e) if e.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+                    Some(e) if e.eq_ignore_ascii_case("jsonl") => ExportFormat::JsonLines,
+                    _ => ExportFormat::Json,
+                };
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                Ok((text, format))
+            }
+            Self::Reader(mut reader, format) => {
+                let mut text = String::new();
+                reader.read_to_string(&mut text)?;
+                Ok((text, format))
+            }
+        }
+    }
+}
+
+/// An incremental writer used by the export features.
+///
+/// Each exported item is serialized into the underlying writer as soon as it is
+/// fetched, so that an export never needs to materialize the whole collection
+/// (or its serialized form) in memory.
+pub struct ExportWriter<W: Write> {
+    writer: W,
+    format: ExportFormat,
+    count: usize,
+    closing: &'static [u8],
+    /// the CSV columns, known after the first item is written
+    columns: Option<Vec<String>>,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// creates a new writer exporting the items as a top-level JSON array or as JSON lines
+    pub fn new(mut writer: W, format: ExportFormat) -> Result<Self> {
+        let closing: &[u8] = match format {
+            ExportFormat::Json => {
+                writer.write_all(b"[")?;
+                b"]"
+            }
+            ExportFormat::JsonLines | ExportFormat::Csv => b"",
+        };
+
+        Ok(Self {
+            writer,
+            format,
+            count: 0,
+            closing,
+            columns: None,
+        })
+    }
+
+    /// creates a new writer exporting a header object followed by the items.
+    ///
+    /// - For `ExportFormat::Json`, the output is an object of the form
+    ///   `{"<header_key>": <header>, "<items_key>": [<items>...]}`
+    /// - For `ExportFormat::JsonLines`, the header is written as the first line
+    /// - For `ExportFormat::Csv`, the header is omitted as it cannot be represented in a table
+    pub fn with_header<H: Serialize>(
+        mut writer: W,
+        format: ExportFormat,
+        header_key: &str,
+        header: &H,
+        items_key: &str,
+    ) -> Result<Self> {
+        let closing: &[u8] = match format {
+            ExportFormat::Json => {
+                write!(writer, "{{{}:", serde_json::to_string(header_key)?)?;
+                serde_json::to_writer(&mut writer, header)?;
+                write!(writer, ",{}:[", serde_json::to_string(items_key)?)?;
+                b"]}"
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut writer, header)?;
+                writer.write_all(b"\n")?;
+                b""
+            }
+            ExportFormat::Csv => b"",
+        };
+
+        Ok(Self {
+            writer,
+            format,
+            count: 0,
+            closing,
+            columns: None,
+        })
+    }
+
+    /// serializes an item into the underlying writer
+    pub fn write_item<T: Serialize>(&mut self, item: &T) -> Result<()> {
+        match self.format {
+            ExportFormat::Json => {
+                if self.count > 0 {
+                    self.writer.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut self.writer, item)?;
+            }
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, item)?;
+                self.writer.write_all(b"\n")?;
+            }
+            ExportFormat::Csv => {
+                let Value::Object(fields) = serde_json::to_value(item)? else {
+                    anyhow::bail!("only items serialized as objects can be exported as CSV");
+                };
+                let columns = match &self.columns {
+                    Some(columns) => columns,
+                    None => {
+                        let columns = fields.keys().cloned().collect::<Vec<_>>();
+                        write_csv_record(&mut self.writer, &columns)?;
+                        &*self.columns.insert(columns)
+                    }
+                };
+                let record = columns
+                    .iter()
+                    .map(|column| match fields.get(column) {
+                        None | Some(Value::Null) => String::new(),
+                        Some(Value::String(s)) => s.clone(),
+                        Some(value) => value.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                write_csv_record(&mut self.writer, &record)?;
+            }
+        }
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// gets the number of items written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// completes the export and returns the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(self.closing)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// escapes a CSV field, quoting it if it contains a separator, a quote or a line break
+fn escape_csv_field(field: &str) -> Cow<str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn write_csv_record<W: Write>(writer: &mut W, fields: &[String]) -> Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(escape_csv_field(field).as_bytes())?;
+    }
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// parses the records of a CSV document, supporting quoted fields
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("unterminated quoted field in CSV");
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// parses an exported track (JSON) into an import row
+fn import_row_from_json(item: &Value) -> Option<ImportRow> {
+    let name = item.get("name")?.as_str()?.to_string();
+    let artists = match item.get("artists")? {
+        Value::String(artists) => artists.clone(),
+        Value::Array(artists) => artists
+            .iter()
+            .filter_map(|a| a.get("name").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => return None,
+    };
+    let uri = ["uri", "id"]
+        .into_iter()
+        .find_map(|key| item.get(key).and_then(Value::as_str))
+        .map(str::to_string);
+
+    Some(ImportRow { name, artists, uri })
+}
+
+/// parses the tracks of a playlist exported in a given format.
+///
+/// - JSON: a top-level array of tracks or an object with a `tracks` array
+/// - JSON lines: one track per line, other lines (e.g. the playlist header) are skipped
+/// - CSV: a header row followed by the tracks, with a `name` column and optional `artists` and `uri` columns
+pub(crate) fn parse_import(text: &str, format: ExportFormat) -> Result<Vec<ImportRow>> {
+    match format {
+        ExportFormat::Json => {
+            let value = serde_json::from_str::<Value>(text)?;
+            let tracks = match &value {
+                Value::Array(tracks) => tracks,
+                Value::Object(fields) => fields
+                    .get("tracks")
+                    .and_then(Value::as_array)
+                    .context("expected a `tracks` array in the JSON document")?,
+                _ => anyhow::bail!("expected a JSON array or object"),
+            };
+            Ok(tracks.iter().filter_map(import_row_from_json).collect())
+        }
+        ExportFormat::JsonLines => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(import_row_from_json(&serde_json::from_str(line)?)))
+            .filter_map(Result::transpose)
+            .collect(),
+        ExportFormat::Csv => {
+            let mut records = parse_csv(text)?.into_iter();
+            let header = records.next().unwrap_or_default();
+            let column = |names: &[&str]| {
+                header
+                    .iter()
+                    .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+            };
+            let name = column(&["name", "track"]).context("missing a `name` column in CSV")?;
+            let artists = column(&["artists", "artist"]);
+            let uri = column(&["uri"]);
+
+            let field = |record: &[String], i: Option<usize>| {
+                i.and_then(|i| record.get(i))
+                    .map(|f| f.trim().to_string())
+                    .filter(|f| !f.is_empty())
+            };
+            Ok(records
+                .filter_map(|record| {
+                    Some(ImportRow {
+                        name: field(&record, Some(name))?,
+                        artists: field(&record, artists).unwrap_or_default(),
+                        uri: field(&record, uri),
+                    })
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Item {
+        name: &'static str,
+    }
+
+    fn write_items(mut writer: ExportWriter<Vec<u8>>) -> String {
+        writer.write_item(&Item { name: "a" }).unwrap();
+        writer.write_item(&Item { name: "b, \"c\"" }).unwrap();
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn export_json() {
+        let output = write_items(ExportWriter::new(vec![], ExportFormat::Json).unwrap());
+        assert_eq!(output, r#"[{"name":"a"},{"name":"b, \"c\""}]"#);
+
+        let empty = ExportWriter::new(vec![], ExportFormat::Json).unwrap();
+        assert_eq!(empty.finish().unwrap(), b"[]");
+    }
+
+    #[test]
+    fn export_json_with_header() {
+        let writer = ExportWriter::with_header(
+            vec![],
+            ExportFormat::Json,
+            "playlist",
+            &Item { name: "p" },
+            "tracks",
+        )
+        .unwrap();
+        let output = write_items(writer);
+        assert_eq!(
+            output,
+            r#"{"playlist":{"name":"p"},"tracks":[{"name":"a"},{"name":"b, \"c\""}]}"#
+        );
+        serde_json::from_str::<serde_json::Value>(&output).unwrap();
+    }
+
+    #[test]
+    fn export_json_lines() {
+        let output = write_items(ExportWriter::new(vec![], ExportFormat::JsonLines).unwrap());
+        assert_eq!(output, "{\"name\":\"a\"}\n{\"name\":\"b, \\\"c\\\"\"}\n");
+    }
+
+    #[test]
+    fn export_writes_incrementally() {
+        let mut writer = ExportWriter::new(vec![], ExportFormat::JsonLines).unwrap();
+        writer.write_item(&Item { name: "a" }).unwrap();
+        // the item should reach the underlying writer before the export is finished
+        assert_eq!(writer.writer, b"{\"name\":\"a\"}\n");
+        assert_eq!(writer.count(), 1);
+    }
+
+    /// An allocator counting the memory allocated by the current thread,
+    /// to measure the peak memory of an export regardless of the tests running in parallel
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATED: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+        static PEAK: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    }
+
+    fn count_allocation(size: isize) {
+        let _ = ALLOCATED.try_with(|allocated| {
+            allocated.set(allocated.get() + size);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+        });
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let ptr = std::alloc::System.alloc(layout);
+            if !ptr.is_null() {
+                count_allocation(layout.size() as isize);
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout);
+            count_allocation(-(layout.size() as isize));
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// runs `f`, returning the peak memory it allocated on the current thread
+    fn peak_allocated(f: impl FnOnce()) -> usize {
+        let start = ALLOCATED.with(|allocated| allocated.get());
+        PEAK.with(|peak| peak.set(start));
+        f();
+        (PEAK.with(|peak| peak.get()) - start).max(0) as usize
+    }
+
+    /// A writer discarding its output, counting the written bytes
+    struct CountingWriter(usize);
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn export_memory_is_bounded() {
+        #[derive(Serialize)]
+        struct Track {
+            id: String,
+            name: String,
+            artists: Vec<String>,
+            duration_ms: u32,
+        }
+
+        for format in [
+            ExportFormat::Json,
+            ExportFormat::JsonLines,
+            ExportFormat::Csv,
+        ] {
+            let mut written = 0;
+            // a fixture of 100k tracks, generated as they are exported like fetched pages
+            let peak = peak_allocated(|| {
+                let mut writer = ExportWriter::with_header(
+                    CountingWriter(0),
+                    format,
+                    "playlist",
+                    &Item { name: "p" },
+                    "tracks",
+                )
+                .unwrap();
+                for i in 0..100_000 {
+                    let track = Track {
+                        id: format!("{i:022}"),
+                        name: format!("Track {i}"),
+                        artists: vec![format!("Artist {}", i % 100)],
+                        duration_ms: 180_000,
+                    };
+                    writer.write_item(&track).unwrap();
+                }
+                written = writer.finish().unwrap().0;
+            });
+
+            // the export's peak memory doesn't grow with the number of items
+            assert!(written > 5_000_000, "{format:?}: {written} bytes written");
+            assert!(
+                peak < 64 * 1024,
+                "{format:?}: {peak} bytes allocated at peak"
+            );
+        }
+    }
+
+    #[test]
+    fn export_csv() {
+        let output = write_items(ExportWriter::new(vec![], ExportFormat::Csv).unwrap());
+        assert_eq!(output, "name\na\n\"b, \"\"c\"\"\"\n");
+
+        #[derive(Serialize)]
+        struct Row {
+            name: &'static str,
+            artists: Vec<&'static str>,
+            duration: u32,
+            added_at: Option<&'static str>,
+        }
+        let mut writer = ExportWriter::with_header(
+            vec![],
+            ExportFormat::Csv,
+            "playlist",
+            &Item { name: "p" },
+            "tracks",
+        )
+        .unwrap();
+        writer
+            .write_item(&Row {
+                name: "line 1\nline 2",
+                artists: vec!["x", "y"],
+                duration: 180,
+                added_at: None,
+            })
+            .unwrap();
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+        // the columns keep the fields' order
+        assert_eq!(
+            output,
+            "name,artists,duration,added_at\n\"line 1\nline 2\",\"[\"\"x\"\",\"\"y\"\"]\",180,\n"
+        );
+    }
+
+    #[test]
+    fn escape_csv_fields() {
+        assert_eq!(escape_csv_field("plain text"), "plain text");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("\r\n"), "\"\r\n\"");
+    }
+
+    #[test]
+    fn parse_csv_records() {
+        assert_eq!(
+            parse_csv("a,b\r\n\"x, \"\"y\"\"\",\"line 1\nline 2\"\n,z").unwrap(),
+            vec![
+                vec!["a", "b"],
+                vec!["x, \"y\"", "line 1\nline 2"],
+                vec!["", "z"]
+            ]
+        );
+        assert!(parse_csv("\"unterminated").is_err());
+        assert!(parse_csv("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_exported_csv() {
+        let text = "name,artists,album,duration_ms,uri,added_at\n\
+            \"Hello, World\",\"A, B\",Album,1000,spotify:track:abc,\n\
+            No URI,C,Album,1000,,\n\
+            ,D,Album,1000,,\n";
+        assert_eq!(
+            parse_import(text, ExportFormat::Csv).unwrap(),
+            vec![
+                ImportRow {
+                    name: "Hello, World".to_string(),
+                    artists: "A, B".to_string(),
+                    uri: Some("spotify:track:abc".to_string()),
+                },
+                ImportRow {
+                    name: "No URI".to_string(),
+                    artists: "C".to_string(),
+                    uri: None,
+                },
+            ]
+        );
+        assert!(parse_import("title\nfoo\n", ExportFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn import_exported_json() {
+        let row = ImportRow {
+            name: "a".to_string(),
+            artists: "x, y".to_string(),
+            uri: Some("abc".to_string()),
+        };
+        let track =
+            r#"{"id":"abc","name":"a","artists":[{"id":"1","name":"x"},{"id":"2","name":"y"}]}"#;
+
+        let text = format!(
+            r#"{{"playlist":{{"id":"p","name":"p","owner":["o","o"]}},"tracks":[{track}]}}"#
+        );
+        assert_eq!(
+            parse_import(&text, ExportFormat::Json).unwrap(),
+            vec![row.clone()]
+        );
+        let text = format!("[{track}]");
+        assert_eq!(
+            parse_import(&text, ExportFormat::Json).unwrap(),
+            vec![row.clone()]
+        );
+
+        // the playlist header line is skipped
+        let text = format!("{{\"id\":\"p\",\"name\":\"p\"}}\n{track}\n");
+        assert_eq!(
+            parse_import(&text, ExportFormat::JsonLines).unwrap(),
+            vec![row]
+        );
+    }
+}
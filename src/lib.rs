@@ -3,12 +3,32 @@ mod utils;
 mod constant;
 mod config;
 mod auth;
-mod model;
+pub mod model;
 mod client;
+mod error;
+mod export;
+mod timeseries;
 
 pub mod require {
-    pub use crate::config::{Configs, get_config, set_config};
-    pub use crate::client::Client;
+    pub use crate::config::{
+        AppConfig, CacheConfig, Configs, HttpConfig, RetryConfig, get_config, set_config,
+    };
+    pub use crate::client::{
+        CacheControl, CacheStats, CancellationToken, Client, ConnectionState, DeadlineClient,
+        OperationClient, Paginator, Partial, Quirk, Quirks, SearchPage, WindowedCollection,
+    };
+    pub use crate::error::{ApiError, ClientError};
+    pub use crate::model::{
+        AddTrackOptions, AlbumGroup, AlbumGroups, AnalysisInterval, AnalysisSection,
+        AnalysisSegment, ArtistAlbumsOptions, ArtistSort, BatchReport, BrowseParams, CachePolicy,
+        DedupKey, DedupReport, DedupStrategy, Exclusion, FetchOptions, GenerationCriteria,
+        GenerationOrder, GenerationReport, GenerationSource, ImportReport, ImportRow, KeepRelease,
+        LanguageTag, LibraryExport, LibraryItemId, MergeReport, MergeStrategy, PlaylistExport,
+        PlaylistFilter, PlaylistTrack, RecentCursor, RecommendationParams, RecommendationSeeds,
+        SearchOptions, SearchQuery, SearchTypeSet, ShuffleConstraints, SortStrategy, SyncReport,
+        TimeRange, TrackOrder, TunableAttribute,
+    };
+    pub use crate::export::{ExportFormat, ExportWriter, ImportSource};
     pub use crate::ClientHandler;
     pub use rspotify::clients::BaseClient as _;
     pub use rspotify::clients::OAuthClient as _;
@@ -38,7 +58,7 @@ impl ClientHandler {
 
         let auth_config = auth::AuthConfig::new(configs)?;
         let session = auth::new_session(&auth_config, true).await?;
-        let inner = client::Client::new(session, auth_config.to_owned(), configs.app_config.client_id.to_owned());
+        let inner = client::Client::new(session, auth_config.to_owned(), &configs.app_config)?;
         inner.refresh_token().await?;
 
         self.config = auth_config;
@@ -1,14 +1,22 @@
 pub use rspotify::model as rspotify_model;
 use rspotify::model::CurrentPlaybackContext;
-pub use rspotify::model::{AlbumId, ArtistId, Id, PlaylistId, TrackId, UserId};
+pub use rspotify::model::{
+    AlbumId, ArtistId, EpisodeId, Id, PlaylistId, ShowId, TimeRange, TrackId, UserId,
+};
 
-use crate::utils::map_join;
+use crate::timeseries::Sample;
+use crate::utils::{
+    html_to_text, map_join, normalize_text, normalize_title, string_similarity, unescape_html,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(untagged)]
-/// A Spotify context (playlist, album, artist)
+#[non_exhaustive]
+/// A Spotify context (playlist, album, artist, show)
 pub enum Context {
     Playlist {
         playlist: Playlist,
@@ -22,8 +30,13 @@ pub enum Context {
         artist: Artist,
         top_tracks: Vec<Track>,
         albums: Vec<Album>,
+        /// empty if the related artists were skipped, see `Client::artist_context_with`
         related_artists: Vec<Artist>,
     },
+    Show {
+        show: Show,
+        episodes: Vec<Episode>,
+    },
     Tracks {
         tracks: Vec<Track>,
         desc: String,
@@ -64,9 +77,17 @@ pub struct SearchResults {
     pub artists: Vec<Artist>,
     pub albums: Vec<Album>,
     pub playlists: Vec<Playlist>,
+    #[serde(default)]
+    pub shows: Vec<Show>,
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// A set of searched item types, combined with `|`
+pub struct SearchTypeSet(u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// A track order
 pub enum TrackOrder {
     AddedAt,
@@ -74,6 +95,34 @@ pub enum TrackOrder {
     Album,
     Artists,
     Duration,
+    /// the album's release date
+    ReleaseDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An artist order
+pub enum ArtistSort {
+    /// the name, alphabetically (ignoring case)
+    Name,
+    /// the number of followers, most followed first
+    Followers,
+    /// the popularity, most popular first
+    Popularity,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How `Client::sort_playlist` reorders a playlist
+pub enum SortStrategy {
+    /// move the items if only a few moves are needed or if the playlist has items which
+    /// cannot be re-added (local files), replace them otherwise
+    #[default]
+    Auto,
+    /// move the minimal set of items, which preserves the items' metadata (e.g. the date added)
+    Moves,
+    /// replace all the items with the sorted items in a few requests, which resets
+    /// the items' metadata. A playlist with items which cannot be re-added (local files)
+    /// is sorted by moving the items instead
+    Replace,
 }
 
 #[derive(Debug, Clone)]
@@ -119,6 +168,8 @@ pub struct Device {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 /// A Spotify track
 pub struct Track {
+    /// the track's id, which is the requested track's id (as stored in a playlist or a library)
+    /// if the track was relinked
     pub id: TrackId<'static>,
     pub name: String,
     pub artists: Vec<Artist>,
@@ -127,6 +178,118 @@ pub struct Track {
     pub explicit: bool,
     #[serde(skip)]
     pub added_at: u64,
+    /// the track's International Standard Recording Code (from its external ids), if known
+    #[serde(default)]
+    pub isrc: Option<String>,
+    /// the popularity (between 0 and 100), `None` for tracks converted from simplified tracks
+    #[serde(default)]
+    pub popularity: Option<u32>,
+    /// `Some(false)` for the tracks unavailable in the user's market, e.g. the external tracks
+    /// of a search with `SearchOptions::include_external_audio`, which are only converted
+    /// by `Track::try_from_full_track_unfiltered`.
+    /// `None` if the playability is unknown (the track was requested without a market).
+    #[serde(default)]
+    pub is_playable: Option<bool>,
+    /// the codes of the countries where the track is available, `None` if unknown.
+    /// Spotify omits them for the tracks requested with a market, see `Client::track_availability`.
+    #[serde(default)]
+    pub available_markets: Option<Vec<String>>,
+    /// the id of the track that the requested track was relinked to for the user's market,
+    /// i.e. the id of the track actually played. `None` if the track wasn't relinked.
+    #[serde(default)]
+    pub relinked_id: Option<TrackId<'static>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+/// A track of a playlist, along with the metadata of its addition to the playlist
+pub struct PlaylistTrack {
+    pub track: Track,
+    /// `None` for the tracks added before Spotify recorded the additions' dates
+    pub added_at: Option<DateTime<Utc>>,
+    /// the id of the user who added the track, if known
+    pub added_by: Option<String>,
+}
+
+/// the version of the `LibraryExport` format, incremented on incompatible format changes
+pub const LIBRARY_EXPORT_VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A backup of the current user's library, see `Client::export_library`
+pub struct LibraryExport {
+    /// the version of the export's format, see `LIBRARY_EXPORT_VERSION`
+    pub version: u32,
+    pub saved_tracks: Vec<SavedTrack>,
+    pub saved_albums: Vec<Album>,
+    pub followed_artists: Vec<Artist>,
+    pub playlists: Vec<PlaylistExport>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A playlist of a `LibraryExport`
+pub struct PlaylistExport {
+    #[serde(flatten)]
+    pub playlist: Playlist,
+    /// the URIs of the playlist's items ordered by their positions,
+    /// which may include episodes and local files. Unavailable items are skipped.
+    pub track_uris: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How `Client::restore_library` restores a library export
+pub enum RestoreMode {
+    /// add the exported items missing from the current library
+    Merge,
+    /// plan the restore without modifying the library
+    DryRun,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A write of a library restore, see `Client::restore_library`
+pub enum RestoreAction {
+    SaveTracks(Vec<TrackId<'static>>),
+    SaveAlbums(Vec<AlbumId<'static>>),
+    FollowArtists(Vec<ArtistId<'static>>),
+    /// recreate a playlist owned by the current user, with its items' URIs
+    CreatePlaylist {
+        name: String,
+        desc: String,
+        collaborative: bool,
+        uris: Vec<String>,
+    },
+    /// follow a playlist owned by another user
+    FollowPlaylist(PlaylistId<'static>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The result of `Client::restore_library`
+pub struct RestoreReport {
+    /// the planned writes, in their execution order
+    pub actions: Vec<RestoreAction>,
+    /// whether the writes were only planned, see `RestoreMode::DryRun`
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A saved (liked) track, along with the date of its saving
+pub struct SavedTrack {
+    pub track: Track,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+/// A play of a track in the current user's listening history
+pub struct PlayHistory {
+    pub track: Track,
+    pub played_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A cursor of the current user's listening history, as a unix timestamp in milliseconds
+pub enum RecentCursor {
+    /// the plays before the timestamp, i.e. older plays
+    Before(i64),
+    /// the plays after the timestamp, i.e. newer plays
+    After(i64),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -136,6 +299,160 @@ pub struct Album {
     pub release_date: String,
     pub name: String,
     pub artists: Vec<Artist>,
+    /// the group of the album in an artist's discography, if listed from an artist
+    #[serde(default)]
+    pub group: Option<AlbumGroup>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The relation of an album to an artist's discography
+pub enum AlbumGroup {
+    Album,
+    Single,
+    Compilation,
+    /// an album of other artists on which the artist appears
+    AppearsOn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A set of album groups, combined with `|`. Defaults to albums and singles.
+pub struct AlbumGroups(u8);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The id of an item that can be saved in the current user's library
+pub enum LibraryItemId<'a> {
+    Track(TrackId<'a>),
+    Album(AlbumId<'a>),
+    Show(ShowId<'a>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A Spotify show (podcast)
+pub struct Show {
+    pub id: ShowId<'static>,
+    pub name: String,
+    pub publisher: String,
+    pub description: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A Spotify episode of a show
+pub struct Episode {
+    pub id: EpisodeId<'static>,
+    pub name: String,
+    /// the show's name, empty for the episodes of search results
+    pub show_name: String,
+    pub description: String,
+    pub duration: std::time::Duration,
+    pub release_date: String,
+    /// the current user's playback progress, `None` if unknown
+    #[serde(default)]
+    pub resume_point: Option<ResumePoint>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// The current user's playback progress of an episode
+pub struct ResumePoint {
+    pub fully_played: bool,
+    pub resume_position: std::time::Duration,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+/// The audio features of a track, see `Client::audio_features`.
+/// The confidence measures and the perceptual features range from 0.0 to 1.0.
+pub struct AudioFeatures {
+    pub danceability: f32,
+    pub energy: f32,
+    /// the pitch class of the key, from 0 (C) to 11 (B), -1 if no key was detected
+    pub key: i32,
+    /// the average loudness, in decibels
+    pub loudness: f32,
+    /// 1 for major, 0 for minor
+    pub mode: i32,
+    pub speechiness: f32,
+    pub acousticness: f32,
+    pub instrumentalness: f32,
+    pub liveness: f32,
+    pub valence: f32,
+    /// the tempo, in beats per minute
+    pub tempo: f32,
+    /// the number of beats in each bar, from 3 to 7
+    pub time_signature: i32,
+    pub duration_ms: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+/// The audio analysis of a track, see `Client::audio_analysis`.
+/// The times are in seconds from the track's start.
+pub struct AudioAnalysis {
+    #[serde(default)]
+    pub bars: Vec<AnalysisInterval>,
+    #[serde(default)]
+    pub beats: Vec<AnalysisInterval>,
+    /// the smallest regular pulses, which subdivide the beats
+    #[serde(default)]
+    pub tatums: Vec<AnalysisInterval>,
+    #[serde(default)]
+    pub sections: Vec<AnalysisSection>,
+    #[serde(default)]
+    pub segments: Vec<AnalysisSegment>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+/// A bar, beat or tatum of an audio analysis
+pub struct AnalysisInterval {
+    pub start: f32,
+    pub duration: f32,
+    /// the confidence of the interval, from 0.0 to 1.0
+    pub confidence: f32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+/// A section of an audio analysis, e.g. a verse or a chorus
+pub struct AnalysisSection {
+    pub start: f32,
+    pub duration: f32,
+    pub confidence: f32,
+    /// the average loudness, in decibels
+    pub loudness: f32,
+    /// the tempo, in beats per minute
+    pub tempo: f32,
+    /// the pitch class of the key, -1 if no key was detected (see `AudioFeatures::key`)
+    pub key: i32,
+    /// 1 for major, 0 for minor, -1 if no mode was detected
+    pub mode: i32,
+    pub time_signature: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+/// A segment of an audio analysis, a short sound of roughly consistent timbre
+pub struct AnalysisSegment {
+    pub start: f32,
+    pub duration: f32,
+    pub confidence: f32,
+    /// the loudness at the segment's start, in decibels
+    pub loudness_start: f32,
+    /// the peak loudness, in decibels
+    pub loudness_max: f32,
+    /// the offset of the peak loudness from the segment's start
+    pub loudness_max_time: f32,
+    /// the relative dominance of the 12 pitch classes, from 0.0 to 1.0
+    #[serde(default)]
+    pub pitches: Vec<f32>,
+    /// the 12 timbre coefficients
+    #[serde(default)]
+    pub timbre: Vec<f32>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A Spotify audiobook
+pub struct Audiobook {
+    /// the audiobook's Spotify id
+    pub id: String,
+    pub name: String,
+    pub authors: Vec<String>,
+    pub narrators: Vec<String>,
+    pub total_chapters: u32,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -143,6 +460,32 @@ pub struct Album {
 pub struct Artist {
     pub id: ArtistId<'static>,
     pub name: String,
+    /// the number of followers, `None` for artists converted from simplified artists
+    #[serde(default)]
+    pub followers: Option<u32>,
+    /// the popularity (between 0 and 100), `None` for artists converted from simplified artists
+    #[serde(default)]
+    pub popularity: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// A snapshot of an artist's statistics
+pub struct ArtistStats {
+    pub followers: u32,
+    pub popularity: u32,
+    /// `false` if the artist was no longer followed when the snapshot was taken
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The growth of an artist's statistics between two snapshots
+pub struct ArtistGrowth {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub followers: i64,
+    pub popularity: i64,
+    /// whether the artist was still followed in the latest snapshot
+    pub active: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -153,6 +496,267 @@ pub struct Playlist {
     pub name: String,
     pub owner: (String, UserId<'static>),
     pub desc: String,
+    /// the version of the playlist, see `Client::refresh_snapshot`
+    #[serde(default)]
+    pub snapshot_id: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// A Spotify user's profile
+pub struct User {
+    pub id: UserId<'static>,
+    pub display_name: Option<String>,
+    /// the user's country (an ISO 3166-1 alpha-2 code), only known for the current user
+    pub country: Option<String>,
+    /// the user's subscription level (e.g. `"premium"` or `"free"`),
+    /// only known for the current user
+    pub product: Option<String>,
+    pub followers: u32,
+    pub images: Vec<Image>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+/// A Spotify image.
+/// The image's size may be unknown, e.g. for a playlist's generated mosaic cover.
+pub struct Image {
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Constraints of a smart shuffle, see `utils::smart_shuffle`
+pub struct ShuffleConstraints {
+    /// the minimum number of positions between two tracks of the same artist
+    pub min_artist_spacing: usize,
+    /// the minimum number of positions between two tracks of the same album
+    pub min_album_spacing: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Options of `Client::add_track_to_playlist`
+///
+/// If `dedup` is enabled and the playlist already contains the track, the track is not added
+/// and `position` is ignored: the existing occurrence stays at its current position.
+/// Otherwise, the track is inserted at `position`.
+pub struct AddTrackOptions {
+    /// skip adding the track if the playlist already contains it (default to `false`)
+    pub dedup: bool,
+    /// the zero-based position to insert the track at, the track is appended if `None`
+    pub position: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// A filter of the current user's playlists, see `Client::current_user_playlists_filtered`
+pub enum PlaylistFilter {
+    #[default]
+    All,
+    /// the playlists owned by the user
+    Owned,
+    /// the playlists followed but not owned by the user
+    Followed,
+    /// the collaborative playlists, owned or followed by the user
+    Collaborative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The deduplication strategy of `Client::merge_playlists`
+pub enum MergeStrategy {
+    /// keep all the tracks, including the duplicates
+    Append,
+    /// keep the first occurrence of each track
+    DedupById,
+    /// keep the first occurrence of each track name and artists,
+    /// e.g. the same song released in a single and in an album
+    DedupByNameAndArtist,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The key identifying the duplicated tracks of `Client::dedup_playlist`
+pub enum DedupKey {
+    /// the same track id
+    #[default]
+    TrackId,
+    /// the same track name and artists (case-insensitive),
+    /// e.g. the same song released in a single and in an album
+    NameAndArtists,
+    /// the same recording (ISRC), e.g. a song released in multiple markets.
+    /// Tracks without ISRC are identified by their ids.
+    Isrc,
+}
+
+#[derive(Clone, Debug)]
+/// The items fetched by their ids, see `Client::artists_batch`
+pub struct BatchReport<T> {
+    /// the found items, in the requested ids' order
+    pub items: Vec<T>,
+    /// the ids of the items that were not found, e.g. unavailable in the user's market
+    pub missing: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// The result of a playlist deduplication
+pub struct DedupReport {
+    /// the removed tracks along with their positions in the playlist before the deduplication
+    pub removed: Vec<(Track, usize)>,
+    /// the playlist's snapshot id after the deduplication
+    pub snapshot_id: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The result of a playlist merge
+pub struct MergeReport {
+    /// the number of tracks kept from each source, in the sources' order
+    pub source_counts: Vec<usize>,
+    /// the number of dropped duplicated tracks
+    pub duplicates: usize,
+    /// the target playlist's snapshot id after the merge
+    pub snapshot_id: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The changes made by `Client::sync_playlist`
+pub struct SyncReport {
+    pub added: Vec<TrackId<'static>>,
+    /// the URIs of the removed items, which may include episodes and local files
+    pub removed: Vec<String>,
+    pub moved: Vec<TrackId<'static>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A track entry of an imported playlist, see `Client::import_playlist`
+pub struct ImportRow {
+    pub name: String,
+    /// the track's artists, separated by commas
+    pub artists: String,
+    /// the track's URI (or id), if known
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+/// The result of a playlist import
+pub struct ImportReport {
+    pub playlist: Playlist,
+    /// the number of imported tracks
+    pub imported: usize,
+    /// the entries that could not be matched with a Spotify track
+    pub unmatched: Vec<ImportRow>,
+}
+
+#[derive(Clone, Debug)]
+/// The candidate tracks of a generated playlist, see `Client::generate_playlist`
+pub enum GenerationSource {
+    /// the saved (liked) tracks of the current user
+    LikedSongs,
+    /// the tracks of a set of playlists
+    Playlists(Vec<PlaylistId<'static>>),
+    /// the recommendations based on seed artists, tracks and genres (at most 5 seeds in total)
+    Recommendations {
+        artists: Vec<ArtistId<'static>>,
+        tracks: Vec<TrackId<'static>>,
+        genres: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+/// The seeds of recommendations, between 1 and 5 artists, tracks and genres in total
+pub struct RecommendationSeeds {
+    pub artists: Vec<ArtistId<'static>>,
+    pub tracks: Vec<TrackId<'static>>,
+    pub genres: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A tunable audio attribute of recommendations, see `RecommendationParams`
+pub enum TunableAttribute {
+    Acousticness,
+    Danceability,
+    /// the duration, in milliseconds
+    DurationMs,
+    Energy,
+    Instrumentalness,
+    /// the pitch class of the key, from 0 (C) to 11 (B)
+    Key,
+    Liveness,
+    /// the loudness, in decibels
+    Loudness,
+    /// the modality, 0 for minor and 1 for major
+    Mode,
+    Popularity,
+    Speechiness,
+    /// the tempo, in beats per minute
+    Tempo,
+    TimeSignature,
+    Valence,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Tunable audio attributes of recommendations, see `Client::recommendations`.
+///
+/// The values are validated by `RecommendationParams::query`.
+pub struct RecommendationParams {
+    /// the `min_`, `max_` or `target_` prefixed attributes and their values
+    values: Vec<(&'static str, TunableAttribute, f32)>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The tracks' order of a generated playlist
+pub enum GenerationOrder {
+    #[default]
+    Shuffled,
+    /// e.g. a warmup
+    TempoAscending,
+    /// e.g. a cooldown
+    TempoDescending,
+    EnergyAscending,
+    EnergyDescending,
+}
+
+#[derive(Clone, Debug)]
+/// Criteria of a playlist generated from the tracks' audio features,
+/// see `Client::generate_playlist`
+pub struct GenerationCriteria {
+    /// the generated playlist's name
+    pub name: String,
+    pub source: GenerationSource,
+    /// the accepted tempo range, in beats per minute
+    pub tempo: Option<std::ops::RangeInclusive<f32>>,
+    /// the minimum energy, from 0.0 to 1.0
+    pub min_energy: Option<f32>,
+    /// the maximum acousticness, from 0.0 to 1.0
+    pub max_acousticness: Option<f32>,
+    /// the maximum duration of the playlist, the playlist includes all the accepted tracks if `None`
+    pub target_duration: Option<std::time::Duration>,
+    pub order: GenerationOrder,
+    /// the seed of the random choices, which makes the generation deterministic
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The reason of a candidate track's exclusion from a generated playlist.
+/// A track failing multiple filters is excluded by the first one, in the declaration order.
+pub enum Exclusion {
+    /// the track is already a candidate
+    Duplicate,
+    /// the track's audio features are unavailable
+    MissingFeatures,
+    Tempo,
+    Energy,
+    Acousticness,
+    /// the track doesn't fit in the target duration
+    Duration,
+}
+
+#[derive(Debug, Clone)]
+/// The result of a playlist generation
+pub struct GenerationReport {
+    pub playlist: Playlist,
+    /// the number of candidate tracks
+    pub considered: usize,
+    /// the number of tracks added to the playlist
+    pub selected: usize,
+    /// the number of excluded tracks of each reason, sorted by the decreasing number of tracks
+    pub exclusions: Vec<(Exclusion, usize)>,
 }
 
 #[derive(Clone, Debug)]
@@ -160,6 +764,109 @@ pub struct Playlist {
 pub struct Category {
     pub id: String,
     pub name: String,
+    /// `None` if the category has no icon
+    pub icon: Option<Image>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A validated locale tag consisting of an ISO 639-1 language code and
+/// an ISO 3166-1 alpha-2 country code joined by an underscore, e.g. `en_US`
+pub struct LanguageTag(String);
+
+#[derive(Clone, Debug, Default)]
+/// Optional parameters of Spotify's browse endpoints
+/// (featured playlists, new releases and categories)
+pub struct BrowseParams {
+    timestamp: Option<DateTime<Utc>>,
+    country: Option<rspotify_model::Country>,
+    locale: Option<LanguageTag>,
+}
+
+/// the minimum score of a track matching a title and an artist,
+/// see `SearchResults::best_track_match`
+const MIN_TRACK_MATCH_SCORE: f64 = 0.7;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A search query made of free text and field filters, e.g. `artist:Radiohead year:2000-2010`.
+///
+/// A plain string converts into a query of free text, sent as is.
+pub struct SearchQuery {
+    text: String,
+    filters: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Optional parameters of a search, see `Client::search_with`
+pub struct SearchOptions {
+    pub(crate) limit: Option<u32>,
+    pub(crate) offset: Option<u32>,
+    pub(crate) market: Option<rspotify_model::Market>,
+    pub(crate) include_external_audio: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How the albums of an artist sharing a name are deduplicated
+pub enum DedupStrategy {
+    /// keep all the albums
+    None,
+    /// keep one album for each exact name
+    #[default]
+    ExactName,
+    /// keep one album for each normalized name, which ignores the case, the punctuation
+    /// and the version suffixes (e.g. "Deluxe Edition", "2011 Remaster")
+    NormalizedName,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Which release is kept among deduplicated albums
+pub enum KeepRelease {
+    Earliest,
+    #[default]
+    Latest,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Options of the list and context APIs, e.g. `Client::current_user_saved_tracks_with`.
+/// By default, all the items are fetched in pages of the endpoint's maximum size,
+/// in the current user's market and without reading the persisted contexts.
+pub struct FetchOptions {
+    /// the number of items per page, the endpoint's maximum if `None`
+    pub page_size: Option<u32>,
+    /// the maximum number of items, all the items if `None`.
+    /// The pages after the `max_items`-th item are not fetched.
+    pub max_items: Option<usize>,
+    /// the market whose items are fetched, relinking the tracks unavailable in the market.
+    /// The current user's market if `None`.
+    pub market: Option<rspotify_model::Market>,
+    /// how a context is read from the persisted contexts, see `CacheConfig::persist_contexts`.
+    /// Only the contexts of all the items in the current user's market are persisted.
+    pub cache: CachePolicy,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How a context is read from the persisted contexts (see `CacheConfig::persist_contexts`),
+/// e.g. by `Client::playlist_context_with`
+pub enum CachePolicy {
+    #[default]
+    /// fetch the context, ignoring its cached copy
+    FreshOnly,
+    /// use the cached copy if it is up to date, fetch the context otherwise: a playlist's
+    /// cached copy is checked against the playlist's snapshot id, an album or artist context
+    /// (without a version) is always fetched. The cached copy is used if the fetch fails.
+    CacheThenRefresh,
+    /// use the cached copy if it was cached less than the duration ago, without any request,
+    /// otherwise behave like `CacheThenRefresh`
+    CacheIfFresh(std::time::Duration),
+}
+
+#[derive(Clone, Debug, Default)]
+/// Optional parameters of `Client::artist_albums_with`
+pub struct ArtistAlbumsOptions {
+    pub(crate) groups: AlbumGroups,
+    pub(crate) limit_per_group: Option<usize>,
+    pub(crate) dedup: DedupStrategy,
+    pub(crate) keep: KeepRelease,
+    pub(crate) market: Option<rspotify_model::Market>,
 }
 
 impl Context {
@@ -189,9 +896,28 @@ impl Context {
                 )
             }
             Context::Artist { ref artist, .. } => artist.name.to_string(),
+            Context::Show { show, episodes } => {
+                format!(
+                    "{} | {} | {} episodes",
+                    show.name,
+                    show.publisher,
+                    episodes.len()
+                )
+            }
             Context::Tracks { desc, tracks } => format!("{} | {} songs", desc, tracks.len()),
         }
     }
+
+    /// gets the context's tracks (the top tracks for an artist context, none for a show context)
+    pub fn tracks(&self) -> &[Track] {
+        match self {
+            Context::Playlist { tracks, .. }
+            | Context::Album { tracks, .. }
+            | Context::Tracks { tracks, .. } => tracks,
+            Context::Artist { top_tracks, .. } => top_tracks,
+            Context::Show { .. } => &[],
+        }
+    }
 }
 
 impl ContextId {
@@ -205,6 +931,17 @@ impl ContextId {
     }
 }
 
+impl ArtistSort {
+    /// sorts artists stably. Artists without the sorted statistic are sorted last.
+    pub fn sort(&self, artists: &mut [Artist]) {
+        match self {
+            Self::Name => artists.sort_by_cached_key(|a| a.name.to_lowercase()),
+            Self::Followers => artists.sort_by_key(|a| std::cmp::Reverse(a.followers)),
+            Self::Popularity => artists.sort_by_key(|a| std::cmp::Reverse(a.popularity)),
+        }
+    }
+}
+
 impl TrackOrder {
     pub fn compare(&self, x: &Track, y: &Track) -> std::cmp::Ordering {
         match *self {
@@ -213,8 +950,33 @@ impl TrackOrder {
             Self::Album => x.album_info().cmp(&y.album_info()),
             Self::Duration => x.duration.cmp(&y.duration),
             Self::Artists => x.artists_info().cmp(&y.artists_info()),
+            Self::ReleaseDate => {
+                let release_date = |t: &Track| t.album.as_ref().map(|a| a.release_date.clone());
+                release_date(x).cmp(&release_date(y))
+            }
         }
     }
+
+    /// computes the sorted order of a playlist's tracks, returning the positions of the tracks
+    /// in the sorted order. `None` items (e.g. unavailable tracks) are not sorted.
+    ///
+    /// The sort is stable: tracks with equal keys keep their relative order,
+    /// in both the ascending and the descending orders.
+    pub fn sorted_positions(&self, tracks: &[Option<Track>], ascending: bool) -> Vec<usize> {
+        let mut positions = (0..tracks.len())
+            .filter(|&i| tracks[i].is_some())
+            .collect::<Vec<_>>();
+        let track = |i: usize| tracks[i].as_ref().expect("track should be available");
+        positions.sort_by(|&x, &y| {
+            let ordering = self.compare(track(x), track(y));
+            if ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+        positions
+    }
 }
 
 impl Device {
@@ -227,7 +989,49 @@ impl Device {
     }
 }
 
+/// converts a Spotify URI (`spotify:{type}:{id}`) into its open.spotify.com share link
+fn share_url(uri: &str) -> String {
+    let path = uri
+        .strip_prefix("spotify:")
+        .unwrap_or(uri)
+        .replace(':', "/");
+    format!("https://open.spotify.com/{path}")
+}
+
 impl Track {
+    /// gets the track's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the track's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+
+    /// gets the id of the track actually played, which is the relinked id
+    /// if the track was relinked
+    pub fn playable_id(&self) -> &TrackId<'static> {
+        self.relinked_id.as_ref().unwrap_or(&self.id)
+    }
+
+    /// checks if an id is the track's id or, for a relinked track, the relinked id
+    pub fn matches_id(&self, id: &TrackId<'_>) -> bool {
+        self.id.id() == id.id()
+            || self
+                .relinked_id
+                .as_ref()
+                .is_some_and(|relinked| relinked.id() == id.id())
+    }
+
+    /// gets the URIs of the track's ids, see `Track::matches_id`
+    fn id_uris(&self) -> Vec<String> {
+        std::iter::once(&self.id)
+            .chain(&self.relinked_id)
+            .map(|id| id.uri())
+            .collect()
+    }
+
     /// gets the track's artists information
     pub fn artists_info(&self) -> String {
         map_join(&self.artists, |a| &a.name, ", ")
@@ -250,12 +1054,21 @@ impl Track {
         }
     }
 
+    /// gets a case-insensitive key of the track's name and artists
+    fn name_and_artists_key(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.name.to_lowercase(),
+            self.artists_info().to_lowercase()
+        )
+    }
+
     /// tries to convert from a `rspotify_model::SimplifiedTrack` into `Track`
     pub fn try_from_simplified_track(track: rspotify_model::SimplifiedTrack) -> Option<Self> {
         if track.is_playable.unwrap_or(true) {
-            let id = match track.linked_from {
-                Some(d) => d.id,
-                None => track.id?,
+            let (id, relinked_id) = match track.linked_from {
+                Some(d) => (d.id, track.id),
+                None => (track.id?, None),
             };
             Some(Self {
                 id,
@@ -265,30 +1078,175 @@ impl Track {
                 duration: track.duration.to_std().expect("valid chrono duration"),
                 explicit: track.explicit,
                 added_at: 0,
+                isrc: None,
+                popularity: None,
+                is_playable: track.is_playable,
+                available_markets: track.available_markets,
+                relinked_id,
             })
         } else {
             None
         }
     }
 
-    /// tries to convert from a `rspotify_model::FullTrack` into `Track`
+    /// tries to convert from a `rspotify_model::FullTrack` into `Track`.
+    /// Local files and unplayable tracks cannot be converted,
+    /// see `Track::try_from_full_track_unfiltered` to keep the unplayable tracks.
     pub fn try_from_full_track(track: rspotify_model::FullTrack) -> Option<Self> {
-        if track.is_playable.unwrap_or(true) {
-            let id = match track.linked_from {
-                Some(d) => d.id,
-                None => track.id?,
-            };
-            Some(Self {
-                id,
-                name: track.name,
-                artists: from_simplified_artists_to_artists(track.artists),
-                album: Album::try_from_simplified_album(track.album),
-                duration: track.duration.to_std().expect("valid chrono duration"),
-                explicit: track.explicit,
-                added_at: 0,
-            })
-        } else {
-            None
+        Self::try_from_full_track_unfiltered(track).filter(|t| t.is_playable.unwrap_or(true))
+    }
+
+    /// tries to convert from a `rspotify_model::FullTrack` into `Track`, keeping the unplayable
+    /// tracks (e.g. the external tracks of a search with `SearchOptions::include_external_audio`)
+    /// with `is_playable` set to `Some(false)`. Only local files cannot be converted.
+    pub fn try_from_full_track_unfiltered(track: rspotify_model::FullTrack) -> Option<Self> {
+        let (id, relinked_id) = match track.linked_from {
+            Some(d) => (d.id, track.id),
+            None => (track.id?, None),
+        };
+        Some(Self {
+            id,
+            name: track.name,
+            artists: from_simplified_artists_to_artists(track.artists),
+            album: Album::try_from_simplified_album(track.album),
+            duration: track.duration.to_std().expect("valid chrono duration"),
+            explicit: track.explicit,
+            added_at: 0,
+            isrc: track.external_ids.get("isrc").cloned(),
+            popularity: Some(track.popularity),
+            is_playable: track.is_playable,
+            available_markets: (!track.available_markets.is_empty())
+                .then_some(track.available_markets),
+            relinked_id,
+        })
+    }
+}
+
+/// Builders of the tracks used by the tests
+#[cfg(test)]
+impl Track {
+    /// creates a 3-minute track named after its id, without artists nor album
+    pub(crate) fn fixture(id: &str) -> Self {
+        Self {
+            id: TrackId::from_id(id.to_string()).unwrap(),
+            name: id.to_string(),
+            artists: vec![],
+            album: None,
+            duration: std::time::Duration::from_secs(180),
+            explicit: false,
+            added_at: 0,
+            isrc: None,
+            popularity: None,
+            is_playable: None,
+            available_markets: None,
+            relinked_id: None,
+        }
+    }
+
+    /// sets the track's name
+    pub(crate) fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// adds an artist to the track
+    pub(crate) fn by(mut self, artist_id: &str, name: &str) -> Self {
+        self.artists.push(Artist {
+            id: ArtistId::from_id(artist_id.to_string()).unwrap(),
+            name: name.to_string(),
+            followers: None,
+            popularity: None,
+        });
+        self
+    }
+
+    /// sets the track's album
+    pub(crate) fn on_album(mut self, album_id: &str, name: &str, release_date: &str) -> Self {
+        self.album = Some(Album {
+            id: AlbumId::from_id(album_id.to_string()).unwrap(),
+            release_date: release_date.to_string(),
+            name: name.to_string(),
+            artists: vec![],
+            group: None,
+        });
+        self
+    }
+}
+
+impl PlaylistTrack {
+    /// tries to convert from a `rspotify_model::PlaylistItem` into `PlaylistTrack`.
+    /// Local files, episodes and unavailable tracks cannot be converted.
+    pub fn try_from_playlist_item(item: rspotify_model::PlaylistItem) -> Option<Self> {
+        let Some(rspotify_model::PlayableItem::Track(track)) = item.track else {
+            return None;
+        };
+        let mut track = Track::try_from_full_track(track)?;
+        if let Some(added_at) = item.added_at {
+            track.added_at = added_at.timestamp().try_into().unwrap_or_default();
+        }
+
+        Some(Self {
+            track,
+            added_at: item.added_at,
+            added_by: item.added_by.map(|user| user.id.id().to_string()),
+        })
+    }
+
+    /// returns the ids of the users who added the tracks, in the order of their first addition.
+    /// Each user is listed once, and the tracks without a known adder are ignored.
+    pub fn collaborators(tracks: &[PlaylistTrack]) -> Vec<UserId<'static>> {
+        let mut seen = std::collections::HashSet::new();
+        tracks
+            .iter()
+            .filter_map(|t| t.added_by.as_deref())
+            .filter(|id| seen.insert(*id))
+            .filter_map(|id| UserId::from_id(id.to_string()).ok())
+            .collect()
+    }
+}
+
+impl SavedTrack {
+    /// tries to convert from a `rspotify_model::SavedTrack` into `SavedTrack`
+    pub fn try_from_saved_track(saved: rspotify_model::SavedTrack) -> Option<Self> {
+        let mut track = Track::try_from_full_track(saved.track)?;
+        track.added_at = saved.added_at.timestamp().try_into().unwrap_or_default();
+        Some(Self {
+            track,
+            added_at: saved.added_at,
+        })
+    }
+}
+
+impl PlayHistory {
+    /// tries to convert from a `rspotify_model::PlayHistory` into `PlayHistory`
+    pub fn try_from_play_history(history: rspotify_model::PlayHistory) -> Option<Self> {
+        Some(Self {
+            track: Track::try_from_full_track(history.track)?,
+            played_at: history.played_at,
+        })
+    }
+}
+
+impl RecentCursor {
+    /// returns the cursor continuing a request made with `cursor`,
+    /// given the plays' dates returned by the request and whether older plays remain.
+    ///
+    /// A request for newer plays is continued by the newest returned play (or the same
+    /// cursor if no play is returned), so that polling never returns a play twice.
+    /// Otherwise, the older plays are requested, if any.
+    pub fn next(cursor: Option<Self>, played_at: &[DateTime<Utc>], has_more: bool) -> Option<Self> {
+        match cursor {
+            Some(Self::After(after)) => Some(Self::After(
+                played_at
+                    .iter()
+                    .map(DateTime::timestamp_millis)
+                    .max()
+                    .map_or(after, |newest| newest.max(after)),
+            )),
+            _ => {
+                let oldest = played_at.iter().map(DateTime::timestamp_millis).min();
+                oldest.filter(|_| has_more).map(Self::Before)
+            }
         }
     }
 }
@@ -306,6 +1264,21 @@ impl std::fmt::Display for Track {
 }
 
 impl Album {
+    /// gets the album's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the album's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+
+    /// gets the album's share link highlighting one of its tracks
+    pub fn url_with_highlight(&self, track_id: &TrackId<'_>) -> String {
+        format!("{}?highlight={}", self.url(), track_id.uri())
+    }
+
     /// tries to convert from a `rspotify_model::SimplifiedAlbum` into `Album`
     pub fn try_from_simplified_album(album: rspotify_model::SimplifiedAlbum) -> Option<Self> {
         Some(Self {
@@ -313,6 +1286,7 @@ impl Album {
             name: album.name,
             release_date: album.release_date.unwrap_or_default(),
             artists: from_simplified_artists_to_artists(album.artists),
+            group: None,
         })
     }
 
@@ -324,6 +1298,33 @@ impl Album {
             .unwrap_or("")
             .to_string()
     }
+
+    /// gets the album's release date. A date known with a year or month precision
+    /// is the first day of the year or the month.
+    pub fn release_day(&self) -> Option<chrono::NaiveDate> {
+        let mut parts = self.release_date.splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next().map_or(Some(1), |m| m.parse().ok())?;
+        let day = parts.next().map_or(Some(1), |d| d.parse().ok())?;
+        chrono::NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    /// checks if the album was released after `since`. A date known with a year or month
+    /// precision is compared by the last day of its year or month, so that an album
+    /// possibly released after `since` isn't missed.
+    pub fn released_after(&self, since: chrono::NaiveDate) -> bool {
+        let Some(day) = self.release_day() else {
+            return false;
+        };
+        let months = match self.release_date.matches('-').count() {
+            0 => 12,
+            1 => 1,
+            _ => return day > since,
+        };
+        day.checked_add_months(chrono::Months::new(months))
+            .and_then(|next| next.pred_opt())
+            .is_some_and(|last_day| last_day > since)
+    }
 }
 
 impl From<rspotify_model::FullAlbum> for Album {
@@ -333,10 +1334,33 @@ impl From<rspotify_model::FullAlbum> for Album {
             id: album.id,
             release_date: album.release_date,
             artists: from_simplified_artists_to_artists(album.artists),
+            group: None,
         }
     }
 }
 
+impl std::fmt::Display for RestoreAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SaveTracks(ids) => write!(f, "save {} tracks", ids.len()),
+            Self::SaveAlbums(ids) => write!(f, "save {} albums", ids.len()),
+            Self::FollowArtists(ids) => write!(f, "follow {} artists", ids.len()),
+            Self::CreatePlaylist { name, uris, .. } => {
+                write!(f, "create the playlist {name} with {} items", uris.len())
+            }
+            Self::FollowPlaylist(id) => write!(f, "follow the playlist {id}"),
+        }
+    }
+}
+
+impl User {
+    /// checks if the user has a premium subscription, which is required to control playback.
+    /// Returns `false` if the subscription level is unknown.
+    pub fn is_premium(&self) -> bool {
+        self.product.as_deref() == Some("premium")
+    }
+}
+
 impl std::fmt::Display for Album {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -349,12 +1373,164 @@ impl std::fmt::Display for Album {
     }
 }
 
-impl Artist {
-    /// tries to convert from a `rspotify_model::SimplifiedArtist` into `Artist`
-    pub fn try_from_simplified_artist(artist: rspotify_model::SimplifiedArtist) -> Option<Self> {
-        Some(Self {
-            id: artist.id?,
-            name: artist.name,
+impl LibraryItemId<'_> {
+    /// returns the Spotify URI of the item
+    pub fn uri(&self) -> String {
+        match self {
+            Self::Track(id) => id.uri(),
+            Self::Album(id) => id.uri(),
+            Self::Show(id) => id.uri(),
+        }
+    }
+}
+
+impl Show {
+    /// gets the show's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the show's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+}
+
+impl From<rspotify_model::SimplifiedShow> for Show {
+    fn from(show: rspotify_model::SimplifiedShow) -> Self {
+        Self {
+            id: show.id,
+            name: show.name,
+            publisher: show.publisher,
+            description: show.description,
+        }
+    }
+}
+
+impl From<rspotify_model::FullShow> for Show {
+    fn from(show: rspotify_model::FullShow) -> Self {
+        Self {
+            id: show.id,
+            name: show.name,
+            publisher: show.publisher,
+            description: show.description,
+        }
+    }
+}
+
+impl std::fmt::Display for Show {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} • {}", self.name, self.publisher)
+    }
+}
+
+impl From<rspotify_model::FullEpisode> for Episode {
+    fn from(episode: rspotify_model::FullEpisode) -> Self {
+        Self {
+            id: episode.id,
+            name: episode.name,
+            show_name: episode.show.name,
+            description: unescape_html(&episode.description).into_owned(),
+            duration: episode.duration.to_std().expect("valid chrono duration"),
+            release_date: episode.release_date,
+            resume_point: episode.resume_point.map(ResumePoint::from),
+        }
+    }
+}
+
+impl From<rspotify_model::SimplifiedEpisode> for Episode {
+    fn from(episode: rspotify_model::SimplifiedEpisode) -> Self {
+        Self {
+            id: episode.id,
+            name: episode.name,
+            show_name: String::new(),
+            description: unescape_html(&episode.description).into_owned(),
+            duration: episode.duration.to_std().expect("valid chrono duration"),
+            release_date: episode.release_date,
+            resume_point: episode.resume_point.map(ResumePoint::from),
+        }
+    }
+}
+
+impl From<rspotify_model::ResumePoint> for ResumePoint {
+    fn from(point: rspotify_model::ResumePoint) -> Self {
+        Self {
+            fully_played: point.fully_played,
+            resume_position: point.resume_position.to_std().unwrap_or_default(),
+        }
+    }
+}
+
+impl Episode {
+    /// gets the episode's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the episode's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+
+    /// gets the remaining playback time of the episode for the current user,
+    /// `None` if the playback progress is unknown
+    pub fn remaining(&self) -> Option<std::time::Duration> {
+        let point = self.resume_point?;
+        if point.fully_played {
+            return Some(std::time::Duration::ZERO);
+        }
+        Some(self.duration.saturating_sub(point.resume_position))
+    }
+}
+
+impl std::fmt::Display for Episode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} • {} ({})",
+            self.name, self.show_name, self.release_date
+        )
+    }
+}
+
+impl std::fmt::Display for Audiobook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} • {}", self.name, self.authors.join(", "))
+    }
+}
+
+impl Artist {
+    /// gets the artist's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the artist's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+
+    /// tries to convert from a `rspotify_model::SimplifiedArtist` into `Artist`
+    pub fn try_from_simplified_artist(artist: rspotify_model::SimplifiedArtist) -> Option<Self> {
+        Some(Self {
+            id: artist.id?,
+            name: artist.name,
+            followers: None,
+            popularity: None,
+        })
+    }
+}
+
+impl ArtistGrowth {
+    /// computes the growth between the first and the last snapshots ordered by time
+    pub fn from_samples(samples: &[Sample<ArtistStats>]) -> Option<Self> {
+        let (first, last) = (samples.first()?, samples.last()?);
+        Some(Self {
+            from: first.timestamp,
+            to: last.timestamp,
+            followers: i64::from(last.value.followers) - i64::from(first.value.followers),
+            popularity: i64::from(last.value.popularity) - i64::from(first.value.popularity),
+            active: last.value.active,
         })
     }
 }
@@ -364,6 +1540,8 @@ impl From<rspotify_model::FullArtist> for Artist {
         Self {
             name: artist.name,
             id: artist.id,
+            followers: Some(artist.followers.total),
+            popularity: Some(artist.popularity),
         }
     }
 }
@@ -385,6 +1563,18 @@ fn from_simplified_artists_to_artists(
         .collect()
 }
 
+impl Playlist {
+    /// gets the playlist's Spotify URI
+    pub fn uri(&self) -> String {
+        self.id.uri()
+    }
+
+    /// gets the playlist's open.spotify.com share link
+    pub fn url(&self) -> String {
+        share_url(&self.id.uri())
+    }
+}
+
 impl From<rspotify_model::SimplifiedPlaylist> for Playlist {
     fn from(playlist: rspotify_model::SimplifiedPlaylist) -> Self {
         Self {
@@ -396,17 +1586,14 @@ impl From<rspotify_model::SimplifiedPlaylist> for Playlist {
                 playlist.owner.id,
             ),
             desc: String::new(),
+            snapshot_id: playlist.snapshot_id,
         }
     }
 }
 
 impl From<rspotify_model::FullPlaylist> for Playlist {
     fn from(playlist: rspotify_model::FullPlaylist) -> Self {
-        // remove HTML tags from the description
-        // TODO: may also need to do HTML escaping here
-        let re = regex::Regex::new("(<.*?>|</.*?>)").expect("valid regex");
-        let desc = playlist.description.unwrap_or_default();
-        let desc = re.replace_all(&desc, "").to_string();
+        let desc = html_to_text(&playlist.description.unwrap_or_default());
 
         Self {
             id: playlist.id,
@@ -417,6 +1604,7 @@ impl From<rspotify_model::FullPlaylist> for Playlist {
                 playlist.owner.id,
             ),
             desc,
+            snapshot_id: playlist.snapshot_id,
         }
     }
 }
@@ -432,6 +1620,7 @@ impl From<rspotify_model::category::Category> for Category {
         Self {
             name: c.name,
             id: c.id,
+            icon: c.icons.into_iter().next().map(Image::from),
         }
     }
 }
@@ -442,6 +1631,682 @@ impl std::fmt::Display for Category {
     }
 }
 
+impl RecommendationSeeds {
+    /// checks that there are between 1 and 5 seeds in total
+    pub fn validate(&self) -> Result<()> {
+        let count = self.artists.len() + self.tracks.len() + self.genres.len();
+        if !(1..=5).contains(&count) {
+            anyhow::bail!("invalid recommendation seeds: expected 1 to 5 seeds, found {count}");
+        }
+        Ok(())
+    }
+}
+
+impl TunableAttribute {
+    /// gets the attribute's name in the recommendations' query parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Acousticness => "acousticness",
+            Self::Danceability => "danceability",
+            Self::DurationMs => "duration_ms",
+            Self::Energy => "energy",
+            Self::Instrumentalness => "instrumentalness",
+            Self::Key => "key",
+            Self::Liveness => "liveness",
+            Self::Loudness => "loudness",
+            Self::Mode => "mode",
+            Self::Popularity => "popularity",
+            Self::Speechiness => "speechiness",
+            Self::Tempo => "tempo",
+            Self::TimeSignature => "time_signature",
+            Self::Valence => "valence",
+        }
+    }
+
+    /// gets the attribute's valid range of values
+    fn range(&self) -> std::ops::RangeInclusive<f32> {
+        match self {
+            Self::Acousticness
+            | Self::Danceability
+            | Self::Energy
+            | Self::Instrumentalness
+            | Self::Liveness
+            | Self::Mode
+            | Self::Speechiness
+            | Self::Valence => 0.0..=1.0,
+            Self::DurationMs | Self::Tempo => 0.0..=f32::MAX,
+            Self::Key => 0.0..=11.0,
+            Self::Loudness => -60.0..=0.0,
+            Self::Popularity => 0.0..=100.0,
+            Self::TimeSignature => 3.0..=7.0,
+        }
+    }
+
+    /// checks if the attribute only takes integer values
+    fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::DurationMs | Self::Key | Self::Mode | Self::Popularity | Self::TimeSignature
+        )
+    }
+}
+
+impl RecommendationParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the minimum value of an attribute
+    pub fn min(mut self, attribute: TunableAttribute, value: f32) -> Self {
+        self.values.push(("min", attribute, value));
+        self
+    }
+
+    /// sets the maximum value of an attribute
+    pub fn max(mut self, attribute: TunableAttribute, value: f32) -> Self {
+        self.values.push(("max", attribute, value));
+        self
+    }
+
+    /// sets the target value of an attribute, the closest tracks being preferred
+    pub fn target(mut self, attribute: TunableAttribute, value: f32) -> Self {
+        self.values.push(("target", attribute, value));
+        self
+    }
+
+    /// gets the query parameters corresponding to the tunable attributes (e.g. `min_energy`),
+    /// failing on the first value outside of its attribute's range
+    pub fn query(&self) -> Result<Vec<(String, String)>> {
+        self.values
+            .iter()
+            .map(|&(prefix, attribute, value)| {
+                let name = format!("{prefix}_{}", attribute.name());
+                let range = attribute.range();
+                if !range.contains(&value) {
+                    anyhow::bail!(
+                        "invalid {name} {value}: expected a value between {} and {}",
+                        range.start(),
+                        range.end()
+                    );
+                }
+                if attribute.is_integer() && value.fract() != 0.0 {
+                    anyhow::bail!("invalid {name} {value}: expected an integer");
+                }
+                Ok((name, value.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl<T> BatchReport<T> {
+    /// creates a report from the requested ids paired with their fetched items,
+    /// `None` for the items that were not found
+    pub(crate) fn from_items<R>(items: Vec<(String, Option<R>)>, convert: impl Fn(R) -> T) -> Self {
+        let mut report = Self {
+            items: vec![],
+            missing: vec![],
+        };
+        for (id, item) in items {
+            match item {
+                Some(item) => report.items.push(convert(item)),
+                None => report.missing.push(id),
+            }
+        }
+        report
+    }
+}
+
+impl Default for ShuffleConstraints {
+    fn default() -> Self {
+        Self {
+            min_artist_spacing: 1,
+            min_album_spacing: 1,
+        }
+    }
+}
+
+impl AddTrackOptions {
+    /// checks whether a track should be added given whether the playlist already contains it
+    pub fn should_add(&self, exists: bool) -> bool {
+        !(self.dedup && exists)
+    }
+}
+
+impl PlaylistFilter {
+    /// checks if a playlist of a user's library matches the filter
+    pub fn matches(&self, playlist: &Playlist, user_id: &UserId<'_>) -> bool {
+        let owned = playlist.owner.1.id() == user_id.id();
+        match self {
+            Self::All => true,
+            Self::Owned => owned,
+            Self::Followed => !owned,
+            Self::Collaborative => playlist.collaborative,
+        }
+    }
+}
+
+impl MergeStrategy {
+    /// merges the tracks of multiple sources, the sources' order defining the tracks' order
+    pub fn merge(&self, sources: Vec<Vec<Track>>) -> (Vec<Track>, MergeReport) {
+        let mut seen = std::collections::HashSet::new();
+        let mut tracks = vec![];
+        let mut report = MergeReport::default();
+
+        for source in sources {
+            let num_tracks = tracks.len();
+            for track in source {
+                let is_new = match self {
+                    Self::Append => true,
+                    // a relinked track is a duplicate of both its ids
+                    Self::DedupById => {
+                        let uris = track.id_uris();
+                        let is_new = !uris.iter().any(|uri| seen.contains(uri));
+                        seen.extend(uris);
+                        is_new
+                    }
+                    Self::DedupByNameAndArtist => seen.insert(track.name_and_artists_key()),
+                };
+                if is_new {
+                    tracks.push(track);
+                } else {
+                    report.duplicates += 1;
+                }
+            }
+            report.source_counts.push(tracks.len() - num_tracks);
+        }
+
+        (tracks, report)
+    }
+}
+
+impl DedupKey {
+    /// gets the keys of a track, a relinked track having a key for each of its ids
+    fn keys(&self, track: &Track) -> Vec<String> {
+        match (self, &track.isrc) {
+            (Self::NameAndArtists, _) => vec![track.name_and_artists_key()],
+            (Self::Isrc, Some(isrc)) => vec![format!("isrc:{}", isrc.to_uppercase())],
+            (Self::TrackId | Self::Isrc, _) => track.id_uris(),
+        }
+    }
+
+    /// finds the positions of a playlist's duplicated tracks, keeping the first occurrence
+    /// of each track. A `None` item (e.g. an unavailable track) is never a duplicate.
+    pub fn duplicates(&self, tracks: &[Option<Track>]) -> Vec<usize> {
+        let mut seen = std::collections::HashSet::new();
+        tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, track)| track.as_ref().map(|t| (i, t)))
+            .filter(|(_, track)| {
+                let keys = self.keys(track);
+                let is_duplicate = keys.iter().any(|key| seen.contains(key));
+                seen.extend(keys);
+                is_duplicate
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl Image {
+    /// selects the image whose width is the closest to a given width,
+    /// preferring the larger image on ties.
+    /// Images with an unknown size are only selected if no other image is available.
+    pub fn best_for(images: &[Image], width: u32) -> Option<&Image> {
+        images
+            .iter()
+            .filter(|i| i.width.is_some())
+            .min_by_key(|i| {
+                let w = i.width.unwrap_or_default();
+                (w.abs_diff(width), std::cmp::Reverse(w))
+            })
+            .or_else(|| images.first())
+    }
+}
+
+impl From<rspotify_model::Image> for Image {
+    fn from(image: rspotify_model::Image) -> Self {
+        Self {
+            url: image.url,
+            width: image.width,
+            height: image.height,
+        }
+    }
+}
+
+impl LanguageTag {
+    /// creates a new locale tag, validating that it has the `ll_CC` format
+    pub fn new(tag: &str) -> Result<Self> {
+        let is_valid = match tag.split_once('_') {
+            Some((language, country)) => {
+                language.len() == 2
+                    && language.chars().all(|c| c.is_ascii_lowercase())
+                    && country.len() == 2
+                    && country.chars().all(|c| c.is_ascii_uppercase())
+            }
+            None => false,
+        };
+        if !is_valid {
+            anyhow::bail!(
+                "invalid locale `{tag}`: expected the format `ll_CC` (e.g. `en_US`, `sv_SE`)"
+            );
+        }
+
+        Ok(Self(tag.to_string()))
+    }
+
+    /// creates a locale tag from a POSIX locale (e.g. the `LANG` environment variable),
+    /// ignoring its codeset and modifier as in `en_US.UTF-8`.
+    /// Returns `None` for the locales without a country, e.g. `C` or `POSIX`.
+    pub fn from_posix_locale(locale: &str) -> Option<Self> {
+        let tag = locale.split(['.', '@']).next().unwrap_or_default();
+        Self::new(tag).ok()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for LanguageTag {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl std::fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl BrowseParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the timestamp used to get the featured playlists of a specific date and time
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// sets the country used to get the country-specific items.
+    ///
+    /// The browse endpoints only accept a country code, not the user's market (`from_token`).
+    pub fn country(mut self, country: rspotify_model::Country) -> Self {
+        self.country = Some(country);
+        self
+    }
+
+    /// sets the locale used to get the localized items
+    pub fn locale(mut self, locale: LanguageTag) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// sets the locale if no locale was set, e.g. to apply the configured locale
+    pub(crate) fn or_locale(mut self, locale: Option<&LanguageTag>) -> Self {
+        if self.locale.is_none() {
+            self.locale = locale.cloned();
+        }
+        self
+    }
+
+    /// gets the query parameters corresponding to the browse parameters
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![];
+        if let Some(timestamp) = self.timestamp {
+            // Spotify expects an ISO 8601 timestamp without the timezone part
+            query.push((
+                "timestamp",
+                timestamp.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            ));
+        }
+        if let Some(country) = self.country {
+            query.push(("country", <&str>::from(country).to_string()));
+        }
+        if let Some(locale) = &self.locale {
+            query.push(("locale", locale.to_string()));
+        }
+        query
+    }
+}
+
+impl SearchResults {
+    /// finds the track best matching a title and an artist, e.g. to skip the karaoke covers
+    /// ranked above the original recording.
+    ///
+    /// The tracks are scored by the similarity of their normalized title (ignoring version
+    /// suffixes like `- 2011 Remaster`) and of their most similar artist, the most popular track
+    /// being preferred on ties. Returns `None` if no track is similar enough.
+    pub fn best_track_match(&self, title: &str, artist: &str) -> Option<&Track> {
+        let (title, artist) = (normalize_title(title), normalize_text(artist));
+        let score = |track: &Track| {
+            let title_score = string_similarity(&title, &normalize_title(&track.name));
+            let artist_score = track
+                .artists
+                .iter()
+                .map(|a| string_similarity(&artist, &normalize_text(&a.name)))
+                .fold(0.0, f64::max);
+            (title_score + artist_score) / 2.0
+        };
+
+        // `rev` keeps the first of the equally ranked tracks, as `max_by` returns the last
+        self.tracks
+            .iter()
+            .rev()
+            .map(|track| (score(track), track))
+            .filter(|&(score, _)| score >= MIN_TRACK_MATCH_SCORE)
+            .max_by(|(x, a), (y, b)| x.total_cmp(y).then(a.popularity.cmp(&b.popularity)))
+            .map(|(_, track)| track)
+    }
+}
+
+impl SearchQuery {
+    /// creates a query searching the given free text, which can be empty
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            filters: vec![],
+        }
+    }
+
+    /// adds a field filter, quoting the value if it has multiple words
+    /// (an unquoted value would only filter by its first word)
+    fn filter(mut self, field: &str, value: &str) -> Self {
+        let value = if value.contains(|c: char| c.is_whitespace() || c == '"') {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            value.to_string()
+        };
+        self.filters.push(format!("{field}:{value}"));
+        self
+    }
+
+    pub fn track(self, name: &str) -> Self {
+        self.filter("track", name)
+    }
+
+    pub fn artist(self, name: &str) -> Self {
+        self.filter("artist", name)
+    }
+
+    pub fn album(self, name: &str) -> Self {
+        self.filter("album", name)
+    }
+
+    /// filters the items released between two years (inclusive)
+    pub fn year_range(mut self, start: u16, end: u16) -> Self {
+        if start == end {
+            self.filters.push(format!("year:{start}"));
+        } else {
+            self.filters.push(format!("year:{start}-{end}"));
+        }
+        self
+    }
+
+    pub fn genre(self, genre: &str) -> Self {
+        self.filter("genre", genre)
+    }
+
+    pub fn isrc(self, isrc: &str) -> Self {
+        self.filter("isrc", isrc)
+    }
+
+    pub fn upc(self, upc: &str) -> Self {
+        self.filter("upc", upc)
+    }
+
+    /// filters the albums released in the past two weeks
+    pub fn tag_new(mut self) -> Self {
+        self.filters.push("tag:new".to_string());
+        self
+    }
+
+    /// filters the albums with the lowest 10% popularity
+    pub fn tag_hipster(mut self) -> Self {
+        self.filters.push("tag:hipster".to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = std::iter::once(self.text.trim())
+            .filter(|text| !text.is_empty())
+            .chain(self.filters.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl From<&str> for SearchQuery {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&String> for SearchQuery {
+    fn from(text: &String) -> Self {
+        Self::new(text.as_str())
+    }
+}
+
+impl From<String> for SearchQuery {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl SearchTypeSet {
+    pub const TRACK: Self = Self(1);
+    pub const ARTIST: Self = Self(1 << 1);
+    pub const ALBUM: Self = Self(1 << 2);
+    pub const PLAYLIST: Self = Self(1 << 3);
+    pub const SHOW: Self = Self(1 << 4);
+    pub const EPISODE: Self = Self(1 << 5);
+    pub const ALL: Self = Self(0b11_1111);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// checks if the set contains all types of another set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// gets the comma-separated `type` parameter of the search endpoint
+    pub fn type_param(self) -> String {
+        [
+            (Self::TRACK, "track"),
+            (Self::ARTIST, "artist"),
+            (Self::ALBUM, "album"),
+            (Self::PLAYLIST, "playlist"),
+            (Self::SHOW, "show"),
+            (Self::EPISODE, "episode"),
+        ]
+        .into_iter()
+        .filter(|&(t, _)| self.contains(t))
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+impl std::ops::BitOr for SearchTypeSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl AlbumGroup {
+    /// gets the album type of the artist's albums endpoint
+    pub fn album_type(self) -> rspotify_model::AlbumType {
+        match self {
+            Self::Album => rspotify_model::AlbumType::Album,
+            Self::Single => rspotify_model::AlbumType::Single,
+            Self::Compilation => rspotify_model::AlbumType::Compilation,
+            Self::AppearsOn => rspotify_model::AlbumType::AppearsOn,
+        }
+    }
+}
+
+impl AlbumGroups {
+    pub const ALBUM: Self = Self(1);
+    pub const SINGLE: Self = Self(1 << 1);
+    pub const COMPILATION: Self = Self(1 << 2);
+    pub const APPEARS_ON: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// checks if the set contains all groups of another set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// gets the groups of the set, in the order of an artist's discography
+    pub fn groups(self) -> Vec<AlbumGroup> {
+        [
+            (Self::ALBUM, AlbumGroup::Album),
+            (Self::SINGLE, AlbumGroup::Single),
+            (Self::COMPILATION, AlbumGroup::Compilation),
+            (Self::APPEARS_ON, AlbumGroup::AppearsOn),
+        ]
+        .into_iter()
+        .filter(|&(g, _)| self.contains(g))
+        .map(|(_, group)| group)
+        .collect()
+    }
+}
+
+impl Default for AlbumGroups {
+    fn default() -> Self {
+        Self::ALBUM | Self::SINGLE
+    }
+}
+
+impl std::ops::BitOr for AlbumGroups {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl FetchOptions {
+    /// gets the size of the first page, at most the endpoint's maximum page size
+    /// and no more than the wanted items
+    pub(crate) fn page_size(&self, max_page_size: u32) -> u32 {
+        let page_size = self
+            .page_size
+            .unwrap_or(max_page_size)
+            .clamp(1, max_page_size);
+        match self.max_items {
+            Some(max_items) => page_size.min(max_items.clamp(1, u32::MAX as usize) as u32),
+            None => page_size,
+        }
+    }
+
+    /// checks if the fetched context is read from and written to the persisted contexts,
+    /// i.e. if it includes all the items in the current user's market
+    pub(crate) fn persists_context(&self) -> bool {
+        self.max_items.is_none() && self.market.is_none()
+    }
+}
+
+impl ArtistAlbumsOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the album groups to fetch, albums and singles by default
+    pub fn groups(mut self, groups: AlbumGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// sets the maximum number of albums fetched for each group
+    pub fn limit_per_group(mut self, limit: usize) -> Self {
+        self.limit_per_group = Some(limit);
+        self
+    }
+
+    /// sets how albums sharing a name are deduplicated, by their exact name by default
+    pub fn dedup(mut self, dedup: DedupStrategy) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// sets which release is kept among deduplicated albums, the latest by default
+    pub fn keep(mut self, keep: KeepRelease) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// sets the market whose available albums are fetched, the current user's market by default
+    pub fn market(mut self, market: rspotify_model::Market) -> Self {
+        self.market = Some(market);
+        self
+    }
+}
+
+impl SearchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the maximum number of items (between 1 and 50) of each searched type
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// sets the index of the first item of each searched type, used to get the next results
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// sets the market whose available items are searched
+    pub fn market(mut self, market: rspotify_model::Market) -> Self {
+        self.market = Some(market);
+        self
+    }
+
+    /// includes the externally hosted audio content in the results, keeping its unplayable tracks
+    pub fn include_external_audio(mut self) -> Self {
+        self.include_external_audio = true;
+        self
+    }
+
+    /// gets the query parameters corresponding to the search options
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![];
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            query.push(("offset", offset.to_string()));
+        }
+        if let Some(market) = self.market {
+            query.push(("market", <&str>::from(market).to_string()));
+        }
+        if self.include_external_audio {
+            query.push(("include_external", "audio".to_string()));
+        }
+        query
+    }
+}
+
 impl TracksId {
     pub fn new<U, K>(uri: U, kind: K) -> Self
         where
@@ -499,3 +2364,819 @@ impl PlaybackMetadata {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn add_track_options() {
+        let options = AddTrackOptions::default();
+        assert!(!options.dedup && options.position.is_none());
+        // without dedup, a track is always added
+        assert!(options.should_add(true));
+        assert!(options.should_add(false));
+
+        // with dedup, an existing track is not added, even with a position
+        let options = AddTrackOptions {
+            dedup: true,
+            position: Some(0),
+        };
+        assert!(!options.should_add(true));
+        assert!(options.should_add(false));
+    }
+
+    #[test]
+    fn filter_playlists() {
+        let user_id = UserId::from_id("me").unwrap();
+        let playlist = |owner: &str, collaborative: bool| Playlist {
+            id: PlaylistId::from_id("37i9dQZF1DXcBWIGoYBM5M").unwrap(),
+            collaborative,
+            name: String::new(),
+            owner: (
+                owner.to_string(),
+                UserId::from_id(owner).unwrap().into_static(),
+            ),
+            desc: String::new(),
+            snapshot_id: String::new(),
+        };
+        let playlists = [
+            playlist("me", false),
+            playlist("me", true),
+            playlist("friend", true),
+            playlist("spotify", false),
+        ];
+        let filtered = |filter: PlaylistFilter| {
+            (0..playlists.len())
+                .filter(|&i| filter.matches(&playlists[i], &user_id))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(filtered(PlaylistFilter::All), vec![0, 1, 2, 3]);
+        assert_eq!(filtered(PlaylistFilter::Owned), vec![0, 1]);
+        assert_eq!(filtered(PlaylistFilter::Followed), vec![2, 3]);
+        assert_eq!(filtered(PlaylistFilter::Collaborative), vec![1, 2]);
+    }
+
+    #[test]
+    fn image_best_for() {
+        let image = |width: Option<u32>| Image {
+            url: format!("{width:?}"),
+            width,
+            height: width,
+        };
+
+        assert_eq!(Image::best_for(&[], 300), None);
+        assert_eq!(Image::best_for(&[image(None)], 300), Some(&image(None)));
+
+        let images = [
+            image(Some(640)),
+            image(None),
+            image(Some(300)),
+            image(Some(60)),
+        ];
+        assert_eq!(Image::best_for(&images, 300), Some(&image(Some(300))));
+        assert_eq!(Image::best_for(&images, 50), Some(&image(Some(60))));
+        assert_eq!(Image::best_for(&images, 2000), Some(&image(Some(640))));
+        // ties are broken by preferring the larger image
+        assert_eq!(Image::best_for(&images, 470), Some(&image(Some(640))));
+    }
+
+    #[test]
+    fn artist_growth() {
+        let sample = |day: u32, followers: u32, popularity: u32, active: bool| Sample {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            key: "spotify:artist:0OdUWJ0sBjDrqHygGUXeCF".to_string(),
+            value: ArtistStats {
+                followers,
+                popularity,
+                active,
+            },
+        };
+
+        assert_eq!(ArtistGrowth::from_samples(&[]), None);
+        assert_eq!(
+            ArtistGrowth::from_samples(&[
+                sample(1, 1000, 60, true),
+                sample(2, 1500, 58, true),
+                sample(3, 1400, 61, false)
+            ]),
+            Some(ArtistGrowth {
+                from: sample(1, 0, 0, true).timestamp,
+                to: sample(3, 0, 0, true).timestamp,
+                followers: 400,
+                popularity: 1,
+                active: false,
+            })
+        );
+    }
+
+    #[test]
+    fn category_icons() {
+        let category = |icons| rspotify_model::category::Category {
+            href: String::new(),
+            icons,
+            id: "toplists".to_string(),
+            name: "Top Lists".to_string(),
+        };
+        assert_eq!(Category::from(category(vec![])).icon, None);
+
+        let icon = rspotify_model::Image {
+            url: "https://i.scdn.co/image/a".to_string(),
+            width: Some(275),
+            height: Some(275),
+        };
+        assert_eq!(
+            Category::from(category(vec![icon])).icon.map(|i| i.url),
+            Some("https://i.scdn.co/image/a".to_string())
+        );
+    }
+
+    #[test]
+    fn audio_features_with_null_entries() {
+        let features = serde_json::from_str::<Vec<Option<AudioFeatures>>>(
+            r#"[
+                null,
+                {"danceability": 0.735, "energy": 0.578, "key": 5, "loudness": -11.84, "mode": 0,
+                 "speechiness": 0.0461, "acousticness": 0.514, "instrumentalness": 0.0902,
+                 "liveness": 0.159, "valence": 0.624, "tempo": 98.002, "type": "audio_features",
+                 "id": "06AKEBrKUckW0KREUWRnvT", "duration_ms": 255349, "time_signature": 4}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(features.len(), 2);
+        assert!(features[0].is_none());
+        let features = features[1].unwrap();
+        assert_eq!(features.tempo, 98.002);
+        assert_eq!((features.key, features.mode), (5, 0));
+        assert_eq!(features.duration_ms, 255349);
+    }
+
+    #[test]
+    fn audio_analysis_deserialization() {
+        let analysis = serde_json::from_str::<AudioAnalysis>(
+            r#"{
+                "meta": {"analyzer_version": "4.0.0"},
+                "track": {"num_samples": 4585515, "duration": 207.95985},
+                "bars": [{"start": 0.49567, "duration": 2.18749, "confidence": 0.925}],
+                "beats": [
+                    {"start": 0.49567, "duration": 0.5486, "confidence": 0.848},
+                    {"start": 1.04427, "duration": 0.54718, "confidence": 0.609}
+                ],
+                "sections": [{"start": 0, "duration": 6.97092, "confidence": 1,
+                    "loudness": -14.938, "tempo": 113.178, "tempo_confidence": 0.647,
+                    "key": 9, "key_confidence": 0.297, "mode": -1, "mode_confidence": 0.471,
+                    "time_signature": 4, "time_signature_confidence": 1}],
+                "segments": [{"start": 0.70154, "duration": 0.19891, "confidence": 0.435,
+                    "loudness_start": -23.053, "loudness_max": -14.25,
+                    "loudness_max_time": 0.07305, "loudness_end": 0,
+                    "pitches": [0.212, 0.141, 0.294], "timbre": [42.115, 64.373, -0.233]}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(analysis.bars.len(), 1);
+        assert_eq!(analysis.beats[1].start, 1.04427);
+        assert!(analysis.tatums.is_empty());
+        assert_eq!(analysis.sections[0].mode, -1);
+        assert_eq!(analysis.segments[0].pitches.len(), 3);
+    }
+
+    #[test]
+    fn batch_report_of_missing_ids() {
+        let items = [("a", Some(1)), ("b", None), ("c", Some(3)), ("d", None)]
+            .map(|(id, item)| (id.to_string(), item))
+            .to_vec();
+        let report = BatchReport::from_items(items, |i: usize| i * 10);
+        assert_eq!(report.items, vec![10, 30]);
+        assert_eq!(report.missing, vec!["b".to_string(), "d".to_string()]);
+    }
+
+    #[test]
+    fn recommendation_seeds_count() {
+        let genres = |n: usize| RecommendationSeeds {
+            genres: vec!["rock".to_string(); n],
+            ..Default::default()
+        };
+        assert!(genres(1).validate().is_ok());
+        assert!(genres(5).validate().is_ok());
+        for n in [0, 6] {
+            let err = genres(n).validate().unwrap_err();
+            assert!(err.to_string().contains(&format!("found {n}")));
+        }
+
+        let seeds = RecommendationSeeds {
+            artists: vec![ArtistId::from_id("4Z8W4fKeB5YxbusRsdQVPb").unwrap(); 3],
+            tracks: vec![TrackId::from_id("6rqhFgbbKwnb9MLmUQDhG6").unwrap(); 2],
+            genres: vec!["rock".to_string()],
+        };
+        assert!(seeds.validate().is_err());
+    }
+
+    #[test]
+    fn recommendation_params_query() {
+        assert!(RecommendationParams::new().query().unwrap().is_empty());
+        assert_eq!(
+            RecommendationParams::new()
+                .min(TunableAttribute::Energy, 0.5)
+                .max(TunableAttribute::Tempo, 140.0)
+                .target(TunableAttribute::Popularity, 80.0)
+                .target(TunableAttribute::Loudness, -8.5)
+                .query()
+                .unwrap(),
+            vec![
+                ("min_energy".to_string(), "0.5".to_string()),
+                ("max_tempo".to_string(), "140".to_string()),
+                ("target_popularity".to_string(), "80".to_string()),
+                ("target_loudness".to_string(), "-8.5".to_string()),
+            ]
+        );
+
+        // the offending field is named
+        let err = RecommendationParams::new()
+            .min(TunableAttribute::Danceability, 0.2)
+            .target(TunableAttribute::Energy, 1.3)
+            .query()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid target_energy 1.3: expected a value between 0 and 1"
+        );
+        let err = RecommendationParams::new()
+            .max(TunableAttribute::Key, 4.5)
+            .query()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid max_key 4.5: expected an integer");
+        assert!(RecommendationParams::new()
+            .min(TunableAttribute::Tempo, f32::NAN)
+            .query()
+            .is_err());
+    }
+
+    #[test]
+    fn language_tag_validation() {
+        assert!(LanguageTag::new("en_US").is_ok());
+        assert!(LanguageTag::new("sv_SE").is_ok());
+        for tag in ["EN", "en", "en-US", "EN_us", "eng_US", ""] {
+            let err = LanguageTag::new(tag).unwrap_err();
+            assert!(err.to_string().contains("ll_CC"));
+        }
+
+        for (locale, tag) in [
+            ("en_US.UTF-8", Some("en_US")),
+            ("sv_SE@euro", Some("sv_SE")),
+            ("de_DE", Some("de_DE")),
+            ("C.UTF-8", None),
+            ("POSIX", None),
+            ("", None),
+        ] {
+            assert_eq!(
+                LanguageTag::from_posix_locale(locale)
+                    .as_ref()
+                    .map(LanguageTag::as_str),
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn browse_params_query() {
+        assert!(BrowseParams::new().query().is_empty());
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 5, 17, 21, 30, 0).unwrap();
+        assert_eq!(
+            BrowseParams::new().timestamp(timestamp).query(),
+            vec![("timestamp", "2024-05-17T21:30:00".to_string())]
+        );
+        assert_eq!(
+            BrowseParams::new()
+                .country(rspotify_model::Country::Sweden)
+                .query(),
+            vec![("country", "SE".to_string())]
+        );
+        assert_eq!(
+            BrowseParams::new()
+                .locale(LanguageTag::new("sv_SE").unwrap())
+                .query(),
+            vec![("locale", "sv_SE".to_string())]
+        );
+
+        // the configured locale is forwarded unless a locale is explicitly set
+        let configured = LanguageTag::new("ja_JP").unwrap();
+        assert_eq!(
+            BrowseParams::new().or_locale(Some(&configured)).query(),
+            vec![("locale", "ja_JP".to_string())]
+        );
+        assert_eq!(
+            BrowseParams::new()
+                .locale(LanguageTag::new("sv_SE").unwrap())
+                .or_locale(Some(&configured))
+                .query(),
+            vec![("locale", "sv_SE".to_string())]
+        );
+        assert!(BrowseParams::new().or_locale(None).query().is_empty());
+    }
+
+    #[test]
+    fn best_track_match_skips_covers() {
+        let track = |id: &str, name: &str, artist: &str, popularity: u32| Track {
+            popularity: Some(popularity),
+            ..Track::fixture(id).named(name).by("x", artist)
+        };
+        let results = SearchResults {
+            tracks: vec![
+                track("a", "Creep (Karaoke Version)", "Karaoke Hits Band", 90),
+                track("b", "Creep - 2008 Remaster", "Radiohead", 60),
+                track("c", "Creep", "Radiohead", 80),
+                track("d", "Creep (Acoustic)", "Radiohead", 80),
+            ],
+            ..Default::default()
+        };
+
+        let best = results.best_track_match("Creep", "radiohead").unwrap();
+        assert_eq!(best.id.id(), "c");
+        // the first track is kept on ties
+        let best = results
+            .best_track_match("creep (feat. X)", "Radiohead")
+            .unwrap();
+        assert_eq!(best.id.id(), "c");
+
+        assert!(results.best_track_match("Creep", "Sigur Rós").is_none());
+        assert!(results
+            .best_track_match("Karma Police", "Radiohead")
+            .is_none());
+        assert!(SearchResults::default()
+            .best_track_match("Creep", "Radiohead")
+            .is_none());
+    }
+
+    #[test]
+    fn search_query_filters() {
+        let query = SearchQuery::new("creep")
+            .artist("Radiohead")
+            .year_range(1990, 2000)
+            .tag_hipster();
+        assert_eq!(
+            query.to_string(),
+            "creep artist:Radiohead year:1990-2000 tag:hipster"
+        );
+        assert_eq!(
+            SearchQuery::default()
+                .genre("rock")
+                .year_range(2020, 2020)
+                .tag_new()
+                .to_string(),
+            "genre:rock year:2020 tag:new"
+        );
+        assert_eq!(
+            SearchQuery::default()
+                .isrc("USUM71703861")
+                .upc("00602537518357")
+                .to_string(),
+            "isrc:USUM71703861 upc:00602537518357"
+        );
+
+        // plain strings are sent as is
+        assert_eq!(
+            SearchQuery::from("artist:Radiohead").to_string(),
+            "artist:Radiohead"
+        );
+    }
+
+    #[test]
+    fn search_query_quoting() {
+        // multi-word values are quoted, otherwise only their first word would be filtered
+        assert_eq!(
+            SearchQuery::default()
+                .artist("Daft Punk")
+                .album("Random Access Memories")
+                .to_string(),
+            r#"artist:"Daft Punk" album:"Random Access Memories""#
+        );
+        // embedded quotes and backslashes are escaped
+        assert_eq!(
+            SearchQuery::default()
+                .track(r#"The "Real" Slim"#)
+                .to_string(),
+            r#"track:"The \"Real\" Slim""#
+        );
+        assert_eq!(
+            SearchQuery::default().track(r#"a\"b"#).to_string(),
+            r#"track:"a\\\"b""#
+        );
+    }
+
+    #[test]
+    fn search_type_set() {
+        let types = SearchTypeSet::TRACK | SearchTypeSet::PLAYLIST;
+        assert!(types.contains(SearchTypeSet::TRACK));
+        assert!(!types.contains(SearchTypeSet::ALBUM));
+        assert!(SearchTypeSet::ALL.contains(types));
+        assert_eq!(types.type_param(), "track,playlist");
+        assert_eq!(
+            SearchTypeSet::ALL.type_param(),
+            "track,artist,album,playlist,show,episode"
+        );
+        assert!(SearchTypeSet::default().is_empty());
+        assert_eq!(SearchTypeSet::default().type_param(), "");
+    }
+
+    #[test]
+    fn fetch_options_page_size() {
+        assert_eq!(FetchOptions::default().page_size(50), 50);
+        let options = FetchOptions {
+            page_size: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(options.page_size(50), 20);
+        assert_eq!(options.page_size(10), 10);
+        let options = FetchOptions {
+            max_items: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(options.page_size(50), 5);
+        let options = FetchOptions {
+            page_size: Some(0),
+            max_items: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(options.page_size(50), 1);
+
+        // only the full contexts in the current user's market are persisted
+        assert!(FetchOptions::default().persists_context());
+        assert!(!options.persists_context());
+        let options = FetchOptions {
+            market: Some(rspotify_model::Market::Country(
+                rspotify_model::Country::Sweden,
+            )),
+            ..Default::default()
+        };
+        assert!(!options.persists_context());
+    }
+
+    #[test]
+    fn album_groups() {
+        assert_eq!(
+            AlbumGroups::default().groups(),
+            vec![AlbumGroup::Album, AlbumGroup::Single]
+        );
+        let groups = AlbumGroups::APPEARS_ON | AlbumGroups::COMPILATION;
+        assert!(!groups.contains(AlbumGroups::ALBUM));
+        assert_eq!(
+            groups.groups(),
+            vec![AlbumGroup::Compilation, AlbumGroup::AppearsOn]
+        );
+        assert_eq!(AlbumGroups::ALL.groups().len(), 4);
+    }
+
+    #[test]
+    fn search_options_query() {
+        assert!(SearchOptions::new().query().is_empty());
+        assert_eq!(
+            SearchOptions::new()
+                .limit(50)
+                .offset(100)
+                .market(rspotify_model::Market::FromToken)
+                .include_external_audio()
+                .query(),
+            vec![
+                ("limit", "50".to_string()),
+                ("offset", "100".to_string()),
+                ("market", "from_token".to_string()),
+                ("include_external", "audio".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_strategies() {
+        let track =
+            |id: &str, name: &str, artist: &str| Track::fixture(id).named(name).by(artist, artist);
+        let sources = || {
+            vec![
+                vec![track("a", "Song", "x"), track("b", "Other", "x")],
+                vec![track("b", "Other", "x"), track("c", "song", "x")],
+                vec![track("d", "Song", "y"), track("a", "Song", "x")],
+            ]
+        };
+        let names = |tracks: Vec<Track>| tracks.into_iter().map(|t| t.name).collect::<Vec<_>>();
+
+        let (tracks, report) = MergeStrategy::Append.merge(sources());
+        assert_eq!(tracks.len(), 6);
+        assert_eq!(report.source_counts, vec![2, 2, 2]);
+        assert_eq!(report.duplicates, 0);
+
+        let (tracks, report) = MergeStrategy::DedupById.merge(sources());
+        assert_eq!(names(tracks), vec!["Song", "Other", "song", "Song"]);
+        assert_eq!(report.source_counts, vec![2, 1, 1]);
+        assert_eq!(report.duplicates, 2);
+
+        // a track name is compared case-insensitively
+        let (tracks, report) = MergeStrategy::DedupByNameAndArtist.merge(sources());
+        assert_eq!(names(tracks), vec!["Song", "Other", "Song"]);
+        assert_eq!(report.source_counts, vec![2, 0, 1]);
+        assert_eq!(report.duplicates, 3);
+    }
+
+    #[test]
+    fn sort_tracks_stably() {
+        let track = |id: &str, name: &str, release_date: &str, seconds: u64| Track {
+            duration: std::time::Duration::from_secs(seconds),
+            ..Track::fixture(id)
+                .named(name)
+                .on_album("album", "", release_date)
+        };
+        let tracks = vec![
+            Some(track("a", "b", "2020-05-01", 200)),
+            Some(track("b", "a", "2019", 180)),
+            None,
+            Some(track("c", "c", "2020-05-01", 200)),
+            Some(track("d", "a", "2021-01-01", 240)),
+        ];
+
+        // equal keys keep their relative order, in both directions
+        let order = TrackOrder::TrackName;
+        assert_eq!(order.sorted_positions(&tracks, true), vec![1, 4, 0, 3]);
+        assert_eq!(order.sorted_positions(&tracks, false), vec![3, 0, 1, 4]);
+        let order = TrackOrder::Duration;
+        assert_eq!(order.sorted_positions(&tracks, true), vec![1, 0, 3, 4]);
+        assert_eq!(order.sorted_positions(&tracks, false), vec![4, 0, 3, 1]);
+        let order = TrackOrder::ReleaseDate;
+        assert_eq!(order.sorted_positions(&tracks, true), vec![1, 0, 3, 4]);
+        assert!(order.sorted_positions(&[None, None], true).is_empty());
+    }
+
+    #[test]
+    fn dedup_keys() {
+        let track = |id: &str, name: &str, isrc: Option<&str>| Track {
+            isrc: isrc.map(str::to_string),
+            ..Track::fixture(id).named(name).by("x", "x")
+        };
+        let tracks = vec![
+            Some(track("a", "Song", Some("USRC17607839"))),
+            Some(track("b", "song", Some("usrc17607839"))),
+            None,
+            Some(track("a", "Song", Some("USRC17607839"))),
+            None,
+            Some(track("c", "Other", None)),
+            Some(track("c", "Other", None)),
+            Some(track("d", "Song", None)),
+        ];
+
+        // the first occurrence is kept, unavailable items are never duplicates
+        assert_eq!(DedupKey::TrackId.duplicates(&tracks), vec![3, 6]);
+        assert_eq!(
+            DedupKey::NameAndArtists.duplicates(&tracks),
+            vec![1, 3, 6, 7]
+        );
+        // tracks without ISRC are compared by their ids
+        assert_eq!(DedupKey::Isrc.duplicates(&tracks), vec![1, 3, 6]);
+        assert!(DedupKey::Isrc.duplicates(&[]).is_empty());
+    }
+
+    #[test]
+    fn relinked_track_ids() {
+        let track = |id: &str, relinked: Option<&str>| Track {
+            relinked_id: relinked.map(|id| TrackId::from_id(id.to_string()).unwrap()),
+            ..Track::fixture(id)
+        };
+        let relinked = track("a", Some("b"));
+        assert!(relinked.matches_id(&TrackId::from_id("a").unwrap()));
+        assert!(relinked.matches_id(&TrackId::from_id("b").unwrap()));
+        assert!(!relinked.matches_id(&TrackId::from_id("c").unwrap()));
+        assert_eq!(relinked.playable_id().id(), "b");
+        assert_eq!(track("c", None).playable_id().id(), "c");
+
+        // a relinked track duplicates the tracks with either of its ids
+        let tracks = vec![
+            Some(track("a", None)),
+            Some(relinked.clone()),
+            Some(track("b", None)),
+            Some(track("c", None)),
+        ];
+        assert_eq!(DedupKey::TrackId.duplicates(&tracks), vec![1, 2]);
+        let (merged, report) = MergeStrategy::DedupById.merge(vec![
+            vec![relinked],
+            vec![track("a", None), track("b", None)],
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(report.duplicates, 2);
+    }
+
+    #[test]
+    fn recent_cursor_continuation() {
+        let dates = [
+            Utc.timestamp_millis_opt(3_000).unwrap(),
+            Utc.timestamp_millis_opt(2_000).unwrap(),
+            Utc.timestamp_millis_opt(1_000).unwrap(),
+        ];
+
+        // newer plays are polled from the newest play
+        let after = Some(RecentCursor::After(500));
+        assert_eq!(
+            RecentCursor::next(after, &dates, false),
+            Some(RecentCursor::After(3_000))
+        );
+        assert_eq!(RecentCursor::next(after, &[], false), after);
+
+        // older plays are requested from the oldest play, while there are some
+        assert_eq!(
+            RecentCursor::next(None, &dates, true),
+            Some(RecentCursor::Before(1_000))
+        );
+        assert_eq!(
+            RecentCursor::next(Some(RecentCursor::Before(5_000)), &dates, true),
+            Some(RecentCursor::Before(1_000))
+        );
+        assert_eq!(RecentCursor::next(None, &dates, false), None);
+        assert_eq!(RecentCursor::next(None, &[], true), None);
+    }
+
+    #[test]
+    fn sort_artists() {
+        let artist = |name: &str, followers: Option<u32>, popularity: Option<u32>| Artist {
+            id: ArtistId::from_id("artist").unwrap(),
+            name: name.to_string(),
+            followers,
+            popularity,
+        };
+        let mut artists = vec![
+            artist("beta", Some(10), Some(50)),
+            artist("Alpha", None, None),
+            artist("gamma", Some(30), Some(50)),
+            artist("Delta", Some(10), Some(70)),
+        ];
+        let names = |artists: &[Artist]| artists.iter().map(|a| a.name.clone()).collect::<Vec<_>>();
+
+        ArtistSort::Name.sort(&mut artists);
+        assert_eq!(names(&artists), vec!["Alpha", "beta", "Delta", "gamma"]);
+        // equal statistics keep their relative order, missing statistics are last
+        ArtistSort::Followers.sort(&mut artists);
+        assert_eq!(names(&artists), vec!["gamma", "beta", "Delta", "Alpha"]);
+        ArtistSort::Popularity.sort(&mut artists);
+        assert_eq!(names(&artists), vec!["Delta", "gamma", "beta", "Alpha"]);
+    }
+
+    #[test]
+    fn episode_remaining_time() {
+        let episode = |resume_point: Option<ResumePoint>| Episode {
+            id: EpisodeId::from_id("episode").unwrap(),
+            name: String::new(),
+            show_name: String::new(),
+            description: String::new(),
+            duration: std::time::Duration::from_secs(3600),
+            release_date: String::new(),
+            resume_point,
+        };
+        let point = |fully_played: bool, seconds: u64| ResumePoint {
+            fully_played,
+            resume_position: std::time::Duration::from_secs(seconds),
+        };
+
+        assert_eq!(episode(None).remaining(), None);
+        assert_eq!(
+            episode(Some(point(false, 1680))).remaining(),
+            Some(std::time::Duration::from_secs(32 * 60))
+        );
+        assert_eq!(
+            episode(Some(point(true, 0))).remaining(),
+            Some(std::time::Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn share_links() {
+        let album = Album {
+            id: AlbumId::from_id("6dVIqQ8qmQ5GBnJ9shOYGE").unwrap(),
+            release_date: String::new(),
+            name: String::new(),
+            artists: vec![],
+            group: None,
+        };
+        assert_eq!(album.uri(), "spotify:album:6dVIqQ8qmQ5GBnJ9shOYGE");
+        assert_eq!(
+            album.url(),
+            "https://open.spotify.com/album/6dVIqQ8qmQ5GBnJ9shOYGE"
+        );
+        assert_eq!(
+            album.url_with_highlight(&TrackId::from_id("4uLU6hMCjMI75M1A2tKUQC").unwrap()),
+            "https://open.spotify.com/album/6dVIqQ8qmQ5GBnJ9shOYGE\
+             ?highlight=spotify:track:4uLU6hMCjMI75M1A2tKUQC"
+        );
+    }
+
+    /// builds a `FullTrack` from Spotify's JSON, the `fields` overriding the default ones
+    fn full_track(fields: serde_json::Value) -> rspotify_model::FullTrack {
+        let mut track = serde_json::json!({
+            "album": {
+                "artists": [],
+                "external_urls": {},
+                "id": "6dVIqQ8qmQ5GBnJ9shOYGE",
+                "images": [],
+                "name": "album",
+                "release_date": "2024-05-17",
+            },
+            "artists": [],
+            "disc_number": 1,
+            "duration_ms": 1000,
+            "explicit": false,
+            "external_ids": {},
+            "external_urls": {},
+            "id": "4uLU6hMCjMI75M1A2tKUQC",
+            "is_local": false,
+            "name": "track",
+            "popularity": 0,
+            "track_number": 1,
+        });
+        for (key, value) in fields.as_object().unwrap() {
+            track[key] = value.clone();
+        }
+        serde_json::from_value(track).unwrap()
+    }
+
+    #[test]
+    fn skip_unplayable_tracks() {
+        let track = |is_playable: bool| full_track(serde_json::json!({"is_playable": is_playable}));
+
+        assert!(Track::try_from_full_track(track(true)).is_some());
+        assert!(Track::try_from_full_track(track(false)).is_none());
+        let unplayable = Track::try_from_full_track_unfiltered(track(false)).unwrap();
+        assert_eq!(unplayable.is_playable, Some(false));
+    }
+
+    #[test]
+    fn keep_requested_id_of_relinked_tracks() {
+        let track = Track::try_from_full_track(full_track(serde_json::json!({
+            "id": "relinked",
+            "linked_from": {
+                "external_urls": {},
+                "href": "https://api.spotify.com/v1/tracks/requested",
+                "id": "requested",
+            },
+        })))
+        .unwrap();
+        // the requested id is the one stored in playlists and libraries
+        assert_eq!(track.id.id(), "requested");
+        assert_eq!(
+            track.relinked_id.as_ref().map(|id| id.id()),
+            Some("relinked")
+        );
+        assert_eq!(track.playable_id().id(), "relinked");
+
+        let track = Track::try_from_full_track(full_track(serde_json::json!({}))).unwrap();
+        assert_eq!(track.id.id(), "4uLU6hMCjMI75M1A2tKUQC");
+        assert!(track.relinked_id.is_none());
+    }
+
+    #[test]
+    fn album_release_day() {
+        let album = |release_date: &str| Album {
+            id: AlbumId::from_id("album").unwrap(),
+            release_date: release_date.to_string(),
+            name: String::new(),
+            artists: vec![],
+            group: None,
+        };
+        let day = |y, m, d| chrono::NaiveDate::from_ymd_opt(y, m, d);
+
+        assert_eq!(album("2024-05-17").release_day(), day(2024, 5, 17));
+        assert_eq!(album("2024-05").release_day(), day(2024, 5, 1));
+        assert_eq!(album("2024").release_day(), day(2024, 1, 1));
+        assert_eq!(album("").release_day(), None);
+        assert_eq!(album("2024-13").release_day(), None);
+
+        let since = day(2024, 5, 17).unwrap();
+        assert!(album("2024-05-18").released_after(since));
+        assert!(!album("2024-05-17").released_after(since));
+        // the dates are compared at their precision
+        assert!(album("2024-05").released_after(since));
+        assert!(!album("2024-04").released_after(since));
+        assert!(album("2024").released_after(since));
+        assert!(!album("2023").released_after(since));
+        assert!(!album("2024").released_after(day(2024, 12, 31).unwrap()));
+        assert!(!album("").released_after(since));
+    }
+
+    #[test]
+    fn playlist_collaborators() {
+        let track = |added_by: Option<&str>| PlaylistTrack {
+            track: Track::fixture("a"),
+            added_at: None,
+            added_by: added_by.map(str::to_string),
+        };
+        let tracks = vec![
+            track(Some("bob")),
+            track(None),
+            track(Some("alice")),
+            track(Some("bob")),
+            track(Some("carol")),
+        ];
+
+        let ids = PlaylistTrack::collaborators(&tracks);
+        assert_eq!(
+            ids.iter().map(|id| id.id()).collect::<Vec<_>>(),
+            vec!["bob", "alice", "carol"]
+        );
+        assert!(PlaylistTrack::collaborators(&[track(None)]).is_empty());
+    }
+}
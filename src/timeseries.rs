@@ -0,0 +1,168 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+/// A sample of a time series
+pub struct Sample<T> {
+    pub timestamp: DateTime<Utc>,
+    /// the key identifying the sampled item, e.g. an artist's URI
+    pub key: String,
+    pub value: T,
+}
+
+/// An append-only time-series store persisted as a JSON lines file, one sample per line
+pub struct TimeSeries<T> {
+    path: PathBuf,
+    _value: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> TimeSeries<T> {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            _value: PhantomData,
+        }
+    }
+
+    /// appends samples to the store, creating the store's file if needed
+    pub fn append(&self, samples: &[Sample<T>]) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        let mut content = vec![];
+        for sample in samples {
+            serde_json::to_writer(&mut content, sample)?;
+            content.push(b'\n');
+        }
+        // samples are written at once to avoid leaving a partially written batch
+        file.write_all(&content)?;
+
+        Ok(())
+    }
+
+    /// loads all samples of the store ordered by their insertion.
+    ///
+    /// A non-existing store has no samples. Corrupted lines are skipped.
+    pub fn load(&self) -> Result<Vec<Sample<T>>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut samples = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(sample) => samples.push(sample),
+                Err(err) => tracing::warn!(
+                    "skipped the corrupted sample at line {} of {}: {err:#}",
+                    i + 1,
+                    self.path.display()
+                ),
+            }
+        }
+        Ok(samples)
+    }
+
+    /// loads the samples of an item taken since a given time, ordered by their timestamps
+    pub fn history(&self, key: &str, since: DateTime<Utc>) -> Result<Vec<Sample<T>>> {
+        let mut samples = self
+            .load()?
+            .into_iter()
+            .filter(|s| s.key == key && s.timestamp >= since)
+            .collect::<Vec<_>>();
+        samples.sort_by_key(|s| s.timestamp);
+        Ok(samples)
+    }
+
+    /// loads the latest sample of every item
+    pub fn latest(&self) -> Result<Vec<Sample<T>>> {
+        let mut latest: Vec<Sample<T>> = vec![];
+        for sample in self.load()? {
+            match latest.iter_mut().find(|s| s.key == sample.key) {
+                Some(s) if s.timestamp <= sample.timestamp => *s = sample,
+                Some(_) => {}
+                None => latest.push(sample),
+            }
+        }
+        Ok(latest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample(day: u32, key: &str, value: u32) -> Sample<u32> {
+        Sample {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    fn store(name: &str) -> TimeSeries<u32> {
+        let path = std::env::temp_dir().join(format!(
+            "spotify-client-timeseries-{name}-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        TimeSeries::new(path)
+    }
+
+    #[test]
+    fn append_and_load() {
+        let store = store("append");
+        assert!(store.load().unwrap().is_empty());
+
+        store
+            .append(&[sample(1, "a", 1), sample(1, "b", 10)])
+            .unwrap();
+        store
+            .append(&[sample(3, "a", 3), sample(2, "a", 2)])
+            .unwrap();
+        assert_eq!(store.load().unwrap().len(), 4);
+
+        assert_eq!(
+            store.history("a", sample(2, "", 0).timestamp).unwrap(),
+            vec![sample(2, "a", 2), sample(3, "a", 3)]
+        );
+        assert_eq!(
+            store.latest().unwrap(),
+            vec![sample(3, "a", 3), sample(1, "b", 10)]
+        );
+    }
+
+    #[test]
+    fn skip_corrupted_samples() {
+        let store = store("corrupted");
+        store.append(&[sample(1, "a", 1)]).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&store.path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":\n")
+            .unwrap();
+        store.append(&[sample(2, "a", 2)]).unwrap();
+
+        assert_eq!(
+            store.load().unwrap(),
+            vec![sample(1, "a", 1), sample(2, "a", 2)]
+        );
+    }
+}
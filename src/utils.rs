@@ -1,5 +1,7 @@
 use std::borrow::Cow;
 
+use crate::model::{Id, ShuffleConstraints, Track};
+
 pub fn map_join<T, F>(v: &[T], f: F, sep: &str) -> String
     where
         F: Fn(&T) -> &str,
@@ -34,3 +36,579 @@ pub fn parse_uri(uri: &str) -> Cow<str> {
         Cow::Borrowed(uri)
     }
 }
+
+/// parses a Spotify URI (e.g. `spotify:album:{id}`) or an open.spotify.com share link
+/// (e.g. `https://open.spotify.com/intl-de/track/{id}?si=...`) into its item type and id.
+/// Returns `None` if the input isn't a Spotify URI or link or its id is invalid.
+pub fn parse_spotify_link(input: &str) -> Option<(rspotify::model::Type, &str)> {
+    use rspotify::model::Type;
+
+    let input = input.trim();
+    // the query string (e.g. `?si=...`) and the fragment of a share link are ignored
+    let input = input.split(['?', '#']).next().unwrap_or(input);
+
+    let parts = if let Some(uri) = input.strip_prefix("spotify:") {
+        uri.split(':').collect::<Vec<_>>()
+    } else {
+        let path = ["https://", "http://"]
+            .into_iter()
+            .fold(input, |input, scheme| {
+                input.strip_prefix(scheme).unwrap_or(input)
+            });
+        let path = path.strip_prefix("open.spotify.com/")?;
+        path.split('/').filter(|part| !part.is_empty()).collect()
+    };
+
+    // the type and the id are the last parts of the URI or the link's path, which can be
+    // prefixed by a user (`user:{user_id}`), a locale (`intl-de`) or an embed (`embed`)
+    let [.., kind, id] = parts[..] else {
+        return None;
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let kind = match kind {
+        "track" => Type::Track,
+        "album" => Type::Album,
+        "artist" => Type::Artist,
+        "playlist" => Type::Playlist,
+        "show" => Type::Show,
+        "episode" => Type::Episode,
+        "user" => Type::User,
+        _ => return None,
+    };
+    Some((kind, id))
+}
+
+/// unescapes the HTML entities (named and numeric) inside a text returned by Spotify APIs,
+/// e.g. a playlist's description
+pub fn unescape_html(text: &str) -> Cow<str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity.strip_prefix('#').and_then(|code| {
+                    match code.strip_prefix(|c| c == 'x' || c == 'X') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code.parse::<u32>().ok(),
+                    }
+                    .and_then(char::from_u32)
+                }),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    Cow::Owned(result)
+}
+
+/// converts an HTML text returned by Spotify APIs (e.g. a playlist's description) into a plain text,
+/// removing the HTML tags, then unescaping the HTML entities that Spotify uses to encode
+/// special characters (quotes, newlines, emoji, etc)
+pub fn html_to_text(text: &str) -> String {
+    let re = regex::Regex::new("(<.*?>|</.*?>)").expect("valid regex");
+    unescape_html(&re.replace_all(text, "")).into_owned()
+}
+
+/// removes the items whose key was already seen, keeping the first occurrence of each key
+pub(crate) fn dedup_by_key<T, K, F>(items: Vec<T>, mut key: F) -> Vec<T>
+where
+    K: std::hash::Hash + Eq,
+    F: FnMut(&T) -> K,
+{
+    let mut seen = std::collections::HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(key(item)))
+        .collect()
+}
+
+/// normalizes an International Standard Recording Code (e.g. `US-UM7-17-03861`) into
+/// its 12 uppercase characters, `None` if it isn't made of 2 letters (the country code),
+/// 3 alphanumeric characters (the registrant code) and 7 digits (the year and designation code)
+pub(crate) fn normalize_isrc(isrc: &str) -> Option<String> {
+    let isrc = isrc.trim().replace('-', "").to_ascii_uppercase();
+    let bytes = isrc.as_bytes();
+    let valid = bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_alphabetic)
+        && bytes[2..5].iter().all(u8::is_ascii_alphanumeric)
+        && bytes[5..].iter().all(u8::is_ascii_digit);
+    valid.then_some(isrc)
+}
+
+/// normalizes a Universal Product Code, `None` if it isn't made of 12 digits
+/// (or 13 digits for the EAN-13 form)
+pub(crate) fn normalize_upc(upc: &str) -> Option<String> {
+    let upc = upc.trim().replace(['-', ' '], "");
+    let valid = matches!(upc.len(), 12 | 13) && upc.bytes().all(|b| b.is_ascii_digit());
+    valid.then_some(upc)
+}
+
+/// the keywords of a title's ` - ` suffix describing a version of the same recording
+const VERSION_SUFFIX_KEYWORDS: [&str; 10] = [
+    "remaster", "version", "live", "edit", "mix", "mono", "stereo", "feat", "bonus", "deluxe",
+];
+
+/// folds the case and the diacritics of a Latin character, e.g. `É` into `e`
+fn fold_char(c: char) -> char {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' => 'd',
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ğ' => 'g',
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => 'i',
+        'ł' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' => 't',
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        c => c,
+    }
+}
+
+/// normalizes a text for comparisons: case and diacritics are folded, apostrophes are removed
+/// and the other punctuation is replaced by spaces between the words
+pub(crate) fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|&c| c != '\'' && c != '’')
+        .map(|c| {
+            if c.is_alphanumeric() {
+                fold_char(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// normalizes a track's title for comparisons (see `normalize_text`), removing the parts
+/// that differ between the versions of a recording: parenthetical and bracketed parts,
+/// featured artists and ` - ` suffixes like `- 2011 Remaster` or `- Live`
+pub(crate) fn normalize_title(title: &str) -> String {
+    let mut depth = 0usize;
+    let title = title
+        .chars()
+        .filter(|&c| match c {
+            '(' | '[' => {
+                depth += 1;
+                false
+            }
+            ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect::<String>()
+        .to_lowercase();
+
+    let is_version_suffix = |suffix: &str| {
+        suffix
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| VERSION_SUFFIX_KEYWORDS.contains(&word) || word.starts_with("remaster"))
+    };
+    let title = match title.split_once(" - ") {
+        Some((head, suffix)) if is_version_suffix(suffix) => head,
+        _ => &title,
+    };
+    let title = [" feat. ", " ft. ", " featuring "]
+        .into_iter()
+        .fold(title, |title, sep| title.split(sep).next().unwrap_or(title));
+
+    normalize_text(title)
+        .split(' ')
+        .filter(|word| !matches!(*word, "remaster" | "remastered"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// computes the similarity (between 0 and 1) of two strings,
+/// based on the Levenshtein distance between their characters
+pub(crate) fn string_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != cb);
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(previous + 1).min(distances[j] + 1);
+        }
+    }
+    1.0 - distances[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+/// A small deterministic pseudo-random number generator (SplitMix64)
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        use std::hash::{BuildHasher, Hasher};
+
+        Self(seed.unwrap_or_else(|| {
+            std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish()
+        }))
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// shuffles a slice using the Fisher-Yates algorithm
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// computes a shuffled order of tracks that spaces out the tracks of the same artist
+/// and the same album by at least the constraints' spacings where feasible.
+///
+/// Returns a permutation of the tracks' indices. The result is deterministic for a given `seed`.
+///
+/// The algorithm is greedy: the tracks are randomly shuffled, then each position is filled with
+/// the first track satisfying the constraints among those whose artist has the most remaining
+/// tracks, which prevents the most frequent artists from clustering at the end.
+/// If no track satisfies the constraints, the album constraint is relaxed first,
+/// then the track whose artist was played the longest time ago is picked.
+pub fn smart_shuffle(
+    tracks: &[Track],
+    seed: Option<u64>,
+    constraints: ShuffleConstraints,
+) -> Vec<usize> {
+    // a track's artist is its primary artist, a track without artist never conflicts
+    let artist = |i: usize| tracks[i].artists.first().map(|a| a.id.id());
+    let album = |i: usize| tracks[i].album.as_ref().map(|a| a.id.id());
+
+    let mut remaining = (0..tracks.len()).collect::<Vec<_>>();
+    Rng::new(seed).shuffle(&mut remaining);
+
+    let mut artist_counts = std::collections::HashMap::new();
+    for &i in &remaining {
+        *artist_counts.entry(artist(i)).or_insert(0_usize) += 1;
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(tracks.len());
+    while !remaining.is_empty() {
+        // the number of tracks played since the last track of the same artist/album (if any)
+        let artist_distance = |i: usize| {
+            artist(i).and_then(|id| order.iter().rev().position(|&j| artist(j) == Some(id)))
+        };
+        let album_distance = |i: usize| {
+            album(i).and_then(|id| order.iter().rev().position(|&j| album(j) == Some(id)))
+        };
+        let artist_spaced =
+            |i: usize| artist_distance(i).map_or(true, |d| d >= constraints.min_artist_spacing);
+        let album_spaced =
+            |i: usize| album_distance(i).map_or(true, |d| d >= constraints.min_album_spacing);
+        // prefer the artist with the most remaining tracks, then the shuffled order
+        let priority =
+            |&(pos, i): &(usize, usize)| (std::cmp::Reverse(artist_counts[&artist(i)]), pos);
+
+        let candidates = || remaining.iter().copied().enumerate();
+        let (pos, i) = candidates()
+            .filter(|&(_, i)| artist_spaced(i) && album_spaced(i))
+            .min_by_key(priority)
+            .or_else(|| {
+                candidates()
+                    .filter(|&(_, i)| artist_spaced(i))
+                    .min_by_key(priority)
+            })
+            .or_else(|| {
+                candidates().min_by_key(|&(pos, i)| {
+                    (
+                        std::cmp::Reverse(artist_distance(i).unwrap_or(usize::MAX)),
+                        pos,
+                    )
+                })
+            })
+            .expect("remaining tracks should not be empty");
+
+        remaining.remove(pos);
+        if let Some(count) = artist_counts.get_mut(&artist(i)) {
+            *count -= 1;
+        }
+        order.push(i);
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spotify_uris() {
+        use rspotify::model::Type;
+
+        let id = "6rqhFgbbKwnb9MLmUQDhG6";
+        assert_eq!(
+            parse_spotify_link(&format!("spotify:playlist:{id}")),
+            Some((Type::Playlist, id))
+        );
+        assert_eq!(
+            parse_spotify_link(&format!("spotify:user:someone:playlist:{id}")),
+            Some((Type::Playlist, id))
+        );
+        assert_eq!(
+            parse_spotify_link(&format!(" spotify:artist:{id}\n")),
+            Some((Type::Artist, id))
+        );
+        assert_eq!(parse_spotify_link("spotify:album"), None);
+        assert_eq!(parse_spotify_link("spotify:concert:abc"), None);
+        assert_eq!(parse_spotify_link("spotify:track:not-an-id"), None);
+    }
+
+    #[test]
+    fn parse_spotify_share_links() {
+        use rspotify::model::Type;
+
+        let id = "4uLU6hMCjMI75M1A2tKUQC";
+        for link in [
+            format!("https://open.spotify.com/track/{id}"),
+            format!("https://open.spotify.com/track/{id}?si=abc123&utm_source=copy-link"),
+            format!("https://open.spotify.com/intl-de/track/{id}?si=abc123"),
+            format!("http://open.spotify.com/track/{id}/"),
+            format!("open.spotify.com/embed/track/{id}#t=30"),
+        ] {
+            assert_eq!(parse_spotify_link(&link), Some((Type::Track, id)), "{link}");
+        }
+        assert_eq!(
+            parse_spotify_link(&format!(
+                "https://open.spotify.com/user/someone/playlist/{id}"
+            )),
+            Some((Type::Playlist, id))
+        );
+        assert_eq!(
+            parse_spotify_link(&format!("https://open.spotify.com/album/{id}")),
+            Some((Type::Album, id))
+        );
+        assert_eq!(
+            parse_spotify_link(&format!("https://example.com/track/{id}")),
+            None
+        );
+        assert_eq!(parse_spotify_link("https://open.spotify.com/"), None);
+        assert_eq!(parse_spotify_link("https://open.spotify.com/track/"), None);
+        assert_eq!(parse_spotify_link("not a link"), None);
+    }
+
+    #[test]
+    fn unescape_html_entities() {
+        assert_eq!(unescape_html("no entities"), "no entities");
+        assert_eq!(unescape_html("Rock &amp; Roll"), "Rock & Roll");
+        assert_eq!(unescape_html("&quot;quoted&quot; &#39;s"), "\"quoted\" 's");
+        assert_eq!(unescape_html("line 1&#10;line 2"), "line 1\nline 2");
+        assert_eq!(unescape_html("party &#x1F389;&#128512;"), "party 🎉😀");
+        assert_eq!(unescape_html("Tom & Jerry; &bogus;"), "Tom & Jerry; &bogus;");
+    }
+
+    #[test]
+    fn normalize_recording_and_product_codes() {
+        assert_eq!(
+            normalize_isrc("usum71703861").as_deref(),
+            Some("USUM71703861")
+        );
+        assert_eq!(
+            normalize_isrc(" US-UM7-17-03861 ").as_deref(),
+            Some("USUM71703861")
+        );
+        assert_eq!(
+            normalize_isrc("GBAYE0601498").as_deref(),
+            Some("GBAYE0601498")
+        );
+        // the country code is made of letters, the designation code of digits
+        assert_eq!(normalize_isrc("12UM71703861"), None);
+        assert_eq!(normalize_isrc("USUM7170386A"), None);
+        assert_eq!(normalize_isrc("USUM7170386"), None);
+        assert_eq!(normalize_isrc(""), None);
+
+        assert_eq!(normalize_upc("00602537518357"), None);
+        assert_eq!(
+            normalize_upc("602537518357").as_deref(),
+            Some("602537518357")
+        );
+        assert_eq!(
+            normalize_upc("0602537518357").as_deref(),
+            Some("0602537518357")
+        );
+        assert_eq!(normalize_upc("6025375183AB"), None);
+    }
+
+    #[test]
+    fn normalize_titles() {
+        assert_eq!(normalize_title("Song (feat. X) - 2011 Remaster"), "song");
+        assert_eq!(normalize_title("Song - Remastered 2009"), "song");
+        assert_eq!(normalize_title("Song [Live at Wembley] - Live"), "song");
+        assert_eq!(normalize_title("Song ft. Someone Else"), "song");
+        assert_eq!(normalize_title("Song (Radio Edit"), "song");
+        assert_eq!(
+            normalize_title("Café Del Mar - Energy 52 Mix"),
+            "cafe del mar"
+        );
+        assert_eq!(normalize_title("Don’t Stop Me Now"), "dont stop me now");
+        // suffixes not describing a version are part of the title
+        assert_eq!(normalize_title("Part 2 - The Return"), "part 2 the return");
+        assert_eq!(
+            normalize_title("Song - Oliver's Theme"),
+            "song olivers theme"
+        );
+        assert_eq!(normalize_title("Remastered"), "");
+    }
+
+    #[test]
+    fn normalize_texts() {
+        assert_eq!(normalize_text("Beyoncé"), "beyonce");
+        assert_eq!(normalize_text("  SIGUR RÓS "), "sigur ros");
+        assert_eq!(normalize_text("AC/DC"), "ac dc");
+        assert_eq!(normalize_text("Motörhead & Łódź"), "motorhead lodz");
+    }
+
+    #[test]
+    fn similarity_of_strings() {
+        assert_eq!(string_similarity("", ""), 1.0);
+        assert_eq!(string_similarity("song", "song"), 1.0);
+        assert_eq!(string_similarity("song", ""), 0.0);
+        assert!((string_similarity("kitten", "sitting") - (1.0 - 3.0 / 7.0)).abs() < 1e-9);
+        assert_eq!(
+            string_similarity("abc", "cba"),
+            string_similarity("cba", "abc")
+        );
+    }
+
+    #[test]
+    fn dedup_by_id_or_name() {
+        // recently played items keyed by their id, or by their name for local files
+        let items = vec![
+            (Some("intro1"), "Intro"),
+            (Some("intro2"), "Intro"),
+            (None, "Demo"),
+            (Some("intro1"), "Intro"),
+            (None, "Demo"),
+            (None, "Intro"),
+        ];
+        let items = dedup_by_key(items, |&(id, name)| id.ok_or(name));
+        assert_eq!(
+            items,
+            vec![
+                (Some("intro1"), "Intro"),
+                (Some("intro2"), "Intro"),
+                (None, "Demo"),
+                (None, "Intro"),
+            ]
+        );
+    }
+
+    fn track(i: usize, artist: usize, album: usize) -> Track {
+        Track::fixture(&format!("track{i}"))
+            .named(&format!("track {i}"))
+            .by(&format!("artist{artist}"), &format!("artist {artist}"))
+            .on_album(&format!("album{album}"), &format!("album {album}"), "")
+    }
+
+    /// creates tracks whose artists are given by `artists`, one album per artist
+    fn tracks(artists: &[usize]) -> Vec<Track> {
+        artists
+            .iter()
+            .enumerate()
+            .map(|(i, &artist)| track(i, artist, artist))
+            .collect()
+    }
+
+    #[test]
+    fn smart_shuffle_is_a_permutation() {
+        let tracks = tracks(&[0, 0, 0, 1, 1, 2, 3, 3, 3, 3]);
+        for seed in 0..50 {
+            let mut order = smart_shuffle(&tracks, Some(seed), ShuffleConstraints::default());
+            order.sort_unstable();
+            assert_eq!(order, (0..tracks.len()).collect::<Vec<_>>());
+        }
+        assert!(smart_shuffle(&[], None, ShuffleConstraints::default()).is_empty());
+    }
+
+    #[test]
+    fn smart_shuffle_spaces_out_artists() {
+        // an artist with up to ceil(n/2) tracks can always be spaced out
+        let cases: [&[usize]; 4] = [
+            &[0, 0, 0, 1, 1, 2],
+            &[0, 0, 0, 0, 1, 1, 2],
+            &[0, 0, 0, 0, 0, 1, 2, 3, 4, 5],
+            &[0, 1, 2, 0, 1, 2, 0, 1, 2, 3, 3, 3],
+        ];
+        for artists in cases {
+            let tracks = tracks(artists);
+            for seed in 0..50 {
+                let order = smart_shuffle(&tracks, Some(seed), ShuffleConstraints::default());
+                for pair in order.windows(2) {
+                    assert_ne!(
+                        artists[pair[0]], artists[pair[1]],
+                        "adjacent tracks of the same artist in {order:?} (seed {seed})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn smart_shuffle_is_deterministic() {
+        let tracks = tracks(&[0, 0, 1, 1, 2, 2, 3, 4, 5, 6]);
+        let constraints = ShuffleConstraints::default();
+        assert_eq!(
+            smart_shuffle(&tracks, Some(42), constraints),
+            smart_shuffle(&tracks, Some(42), constraints)
+        );
+        assert!((0..10).any(|seed| {
+            smart_shuffle(&tracks, Some(seed), constraints)
+                != smart_shuffle(&tracks, Some(42), constraints)
+        }));
+    }
+}